@@ -0,0 +1,533 @@
+use crate::cube::*;
+use crate::notation::{face_axis, Move, MoveKind};
+use crate::puzzle::{MoveQueue, MoveSource};
+use crate::save_data::SaveError;
+use crate::state::{Axis, Face, SideMoveEvent};
+use bevy::color::palettes;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+
+// 粘连配置文件格式版本
+pub const BANDAGE_FILE_VERSION: u32 = 1;
+pub const BANDAGE_FILE: &str = "rubiks-cube-bandages.ron";
+
+// 粘连魔方打乱的步数
+const BANDAGED_SCRAMBLE_LENGTH: usize = 25;
+
+// 转动被挡住时闪烁提示的时长
+const BLOCKED_FLASH_SECS: f32 = 0.6;
+
+/// 粘在一起的两个相邻块，用还原状态下的位置表示
+pub type Bond = [IVec3; 2];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BandageFile {
+    version: u32,
+    bonds: Vec<[[i32; 3]; 2]>,
+}
+
+/// 粘连三阶魔方的配置
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct BandageConfig {
+    pub bonds: Vec<Bond>,
+}
+
+impl Default for BandageConfig {
+    // 默认把UF棱两侧的角块粘成一条1x1x3的长条
+    fn default() -> Self {
+        Self {
+            bonds: vec![
+                [IVec3::new(-1, 1, 1), IVec3::new(0, 1, 1)],
+                [IVec3::new(0, 1, 1), IVec3::new(1, 1, 1)],
+            ],
+        }
+    }
+}
+
+// 三阶魔方中可见的块，坐标为-1、0、1且不是中心的内核
+fn is_visible(pos: IVec3) -> bool {
+    pos.abs().max_element() == 1
+}
+
+/// 两个块相邻时才能粘在一起
+pub fn is_adjacent(a: IVec3, b: IVec3) -> bool {
+    is_visible(a) && is_visible(b) && (a - b).abs().element_sum() == 1
+}
+
+impl BandageConfig {
+    pub fn contains(&self, a: IVec3, b: IVec3) -> bool {
+        self.bonds.contains(&[a, b]) || self.bonds.contains(&[b, a])
+    }
+
+    // 粘上或拆开两个相邻块
+    pub fn toggle(&mut self, a: IVec3, b: IVec3) {
+        if !is_adjacent(a, b) {
+            return;
+        }
+        if self.contains(a, b) {
+            self.bonds.retain(|&bond| bond != [a, b] && bond != [b, a]);
+        } else {
+            self.bonds.push([a, b]);
+        }
+    }
+
+    /// 转动axis方向上layers中的层时会被拆开的粘连，positions为各块从还原位置到当前位置的映射
+    pub fn blocked_bonds(
+        &self,
+        positions: &HashMap<IVec3, IVec3>,
+        axis: Axis,
+        layers: &[f32],
+    ) -> Vec<Bond> {
+        let turning = |home: &IVec3| {
+            positions
+                .get(home)
+                .is_some_and(|pos| layers.contains(&(pos[axis.index()] as f32)))
+        };
+        self.bonds
+            .iter()
+            .filter(|[a, b]| turning(a) != turning(b))
+            .copied()
+            .collect()
+    }
+
+    /// 从positions开始依次执行moves，返回第一步会拆开粘连的转动的下标及挡住它的粘连
+    pub fn first_blocked<'a>(
+        &self,
+        mut positions: HashMap<IVec3, IVec3>,
+        moves: impl IntoIterator<Item = &'a Vec<SideMoveEvent>>,
+    ) -> Option<(usize, Vec<Bond>)> {
+        for (i, cube_move) in moves.into_iter().enumerate() {
+            let Some(first) = cube_move.first() else {
+                continue;
+            };
+            let layers: Vec<f32> = cube_move.iter().map(|event| event.side.1).collect();
+            let blocked = self.blocked_bonds(&positions, first.side.0, &layers);
+            if !blocked.is_empty() {
+                return Some((i, blocked));
+            }
+            for event in cube_move {
+                apply_side_move(&mut positions, event);
+            }
+        }
+        None
+    }
+
+    /// 从队列中移除所有会拆开粘连的转动，其后的转动照常保留；
+    /// 返回挡住第一步被移除的转动的粘连
+    pub fn drop_blocked(
+        &self,
+        positions: &HashMap<IVec3, IVec3>,
+        queue: &mut VecDeque<(Vec<SideMoveEvent>, MoveSource)>,
+    ) -> Option<Vec<Bond>> {
+        let mut first = None;
+        while let Some((index, blocked)) =
+            self.first_blocked(positions.clone(), queue.iter().map(|(layers, _)| layers))
+        {
+            let dropped = queue.remove(index);
+            warn!("side move would split a bandage, dropped: {:?}", dropped);
+            first.get_or_insert(blocked);
+        }
+        first
+    }
+}
+
+/// 各块从还原位置到当前位置的映射
+pub fn piece_positions<'a>(
+    pieces: impl Iterator<Item = (&'a Piece, &'a Transform)>,
+) -> HashMap<IVec3, IVec3> {
+    pieces
+        .map(|(piece, transform)| {
+            (
+                piece.init_pos.round().as_ivec3(),
+                transform.translation.round().as_ivec3(),
+            )
+        })
+        .collect()
+}
+
+/// 还原状态下各块的位置
+pub fn solved_positions() -> HashMap<IVec3, IVec3> {
+    let mut positions = HashMap::new();
+    for x in -1..=1 {
+        for y in -1..=1 {
+            for z in -1..=1 {
+                let pos = IVec3::new(x, y, z);
+                if is_visible(pos) {
+                    positions.insert(pos, pos);
+                }
+            }
+        }
+    }
+    positions
+}
+
+/// 按面旋转更新各块的位置，与动画的转动方向一致
+pub fn apply_side_move(positions: &mut HashMap<IVec3, IVec3>, event: &SideMoveEvent) {
+    let (axis, layer) = event.side;
+    let rotation = Quat::from_axis_angle(axis.vector(), event.rotate.angle());
+    for pos in positions.values_mut() {
+        if pos[axis.index()] as f32 == layer {
+            *pos = (rotation * pos.as_vec3()).round().as_ivec3();
+        }
+    }
+}
+
+/// 粘连魔方的随机转动打乱：每一步只从当前不会拆开粘连的外层转动中选取，
+/// 相邻两步不转同一个轴；所有转动都被挡住时提前结束
+pub fn bandaged_scramble<R: Rng>(rng: &mut R, bandages: &BandageConfig) -> Vec<Move> {
+    let mut positions = solved_positions();
+    let mut moves: Vec<Move> = Vec::with_capacity(BANDAGED_SCRAMBLE_LENGTH);
+    while moves.len() < BANDAGED_SCRAMBLE_LENGTH {
+        let candidates: Vec<Move> = Face::ALL
+            .into_iter()
+            .filter(|&face| {
+                moves.last().is_none_or(|last| match last.kind {
+                    MoveKind::Layer(last_face, _) => face_axis(last_face).0 != face_axis(face).0,
+                    _ => true,
+                })
+            })
+            .flat_map(|face| {
+                (1..=3).map(move |amount| Move {
+                    kind: MoveKind::Layer(face, 1),
+                    amount,
+                })
+            })
+            .filter(|m| {
+                m.to_side_moves(3).iter().all(|event| {
+                    bandages
+                        .blocked_bonds(&positions, event.side.0, &[event.side.1])
+                        .is_empty()
+                })
+            })
+            .collect();
+        let Some(&m) = candidates.choose(rng) else {
+            break;
+        };
+        for event in m.to_side_moves(3) {
+            apply_side_move(&mut positions, &event);
+        }
+        moves.push(m);
+    }
+    moves
+}
+
+pub fn load_bandages(path: &str) -> Result<Option<Vec<Bond>>, SaveError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(SaveError::Io(err)),
+    };
+    let file: BandageFile = ron::from_str(&text).map_err(SaveError::Deserialize)?;
+    if file.version != BANDAGE_FILE_VERSION {
+        return Err(SaveError::UnsupportedVersion(file.version));
+    }
+    let bonds: Vec<Bond> = file
+        .bonds
+        .into_iter()
+        .map(|bond| bond.map(IVec3::from_array))
+        .collect();
+    if !bonds.iter().all(|&[a, b]| is_adjacent(a, b)) {
+        return Err(SaveError::InvalidState);
+    }
+    Ok(Some(bonds))
+}
+
+pub fn save_bandages(path: &str, bonds: &[Bond]) -> Result<(), SaveError> {
+    let file = BandageFile {
+        version: BANDAGE_FILE_VERSION,
+        bonds: bonds
+            .iter()
+            .map(|bond| bond.map(|pos| pos.to_array()))
+            .collect(),
+    };
+    let text = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+        .map_err(SaveError::Serialize)?;
+    fs::write(path, text).map_err(SaveError::Io)
+}
+
+// 读取保存的粘连配置，没有保存过时使用默认配置
+pub fn setup_bandages(mut config: ResMut<BandageConfig>) {
+    match load_bandages(BANDAGE_FILE) {
+        Ok(Some(bonds)) => config.bonds = bonds,
+        Ok(None) => {}
+        Err(err) => warn!("failed to load {}: {}", BANDAGE_FILE, err),
+    }
+}
+
+/// 粘连编辑器，编辑时依次点击两个相邻块来粘上或拆开
+#[derive(Debug, Default, Resource)]
+pub struct BandageEditor {
+    pub editing: bool,
+    pub selected: Option<IVec3>,
+    pub status: Option<Result<String, String>>,
+}
+
+/// 最近一次被粘连挡住的转动，挡住转动的块会闪烁
+#[derive(Debug, Resource)]
+pub struct BlockedTurn {
+    bonds: Vec<Bond>,
+    timer: Timer,
+}
+
+impl Default for BlockedTurn {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(BLOCKED_FLASH_SECS, TimerMode::Once);
+        timer.tick(timer.duration());
+        Self {
+            bonds: Vec::new(),
+            timer,
+        }
+    }
+}
+
+impl BlockedTurn {
+    pub fn flash(&mut self, bonds: Vec<Bond>) {
+        self.bonds = bonds;
+        self.timer.reset();
+    }
+}
+
+// 粘连的两个块在共同的外表面上用一条贴纸连起来，桥接贴纸属于第一个块
+pub fn spawn_bridges(
+    parent: &mut ChildBuilder,
+    piece: Piece,
+    bonds: &[Bond],
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    cube_settings: &CubeSettings,
+) {
+    let size = cube_settings.piece_size;
+    let home = piece.init_pos.round().as_ivec3();
+    for &[a, b] in bonds {
+        if a != home {
+            continue;
+        }
+        let dir = b - a;
+        for face in Face::ALL {
+            let normal = IVec3::from_array(face.normal());
+            // 只有垂直于连接方向、并且在魔方表面上的面才有贴纸
+            if normal.dot(dir) != 0 || normal.dot(home) != 1 {
+                continue;
+            }
+            let across = dir.cross(normal).abs().as_vec3();
+            let extent = dir.abs().as_vec3() * 0.1 * size
+                + normal.abs().as_vec3() * 0.01
+                + across * 0.9 * size;
+            let translation = normal.as_vec3() * (0.5 * size + 0.01) + dir.as_vec3() * 0.5 * size;
+            parent.spawn((
+                Mesh3d(meshes.add(Cuboid::from_size(extent).mesh())),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: cube_settings.face_color(face),
+                    unlit: true,
+                    ..default()
+                })),
+                Transform::from_translation(translation),
+                Sticker { face },
+            ));
+        }
+    }
+}
+
+// 编辑时点击块：第一次点击选中，再点击相邻的块粘上或拆开
+pub fn handle_piece_click(
+    click: Trigger<Pointer<Click>>,
+    q_pieces: Query<&Piece>,
+    cube_settings: Res<CubeSettings>,
+    mut editor: ResMut<BandageEditor>,
+    mut config: ResMut<BandageConfig>,
+    mut reset_event: EventWriter<ResetEvent>,
+) {
+    if cube_settings.puzzle != PuzzleKind::Bandaged || !editor.editing {
+        return;
+    }
+    let Ok(piece) = q_pieces.get(click.target) else {
+        return;
+    };
+    let home = piece.init_pos.round().as_ivec3();
+    match editor.selected {
+        Some(selected) if selected == home => editor.selected = None,
+        Some(selected) if is_adjacent(selected, home) => {
+            config.toggle(selected, home);
+            editor.selected = None;
+            editor.status = None;
+            // 重建魔方以更新桥接贴纸
+            reset_event.send_default();
+        }
+        _ => editor.selected = Some(home),
+    }
+}
+
+// 选中的块显示为灰色，挡住转动的块闪烁红色
+pub fn highlight_bandaged_pieces(
+    time: Res<Time>,
+    editor: Res<BandageEditor>,
+    mut blocked_turn: ResMut<BlockedTurn>,
+    q_pieces: Query<(&Piece, &MeshMaterial3d<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    blocked_turn.timer.tick(time.delta());
+    for (piece, material) in &q_pieces {
+        let home = piece.init_pos.round().as_ivec3();
+        let color: Color = if editor.editing && editor.selected == Some(home) {
+            palettes::css::GRAY.into()
+        } else if !blocked_turn.timer.finished()
+            && blocked_turn.bonds.iter().any(|bond| bond.contains(&home))
+        {
+            palettes::css::RED.into()
+        } else {
+            Color::BLACK
+        };
+        // 只在颜色变化时修改材质，避免每帧重新上传
+        if materials
+            .get(&material.0)
+            .is_some_and(|material| material.base_color != color)
+        {
+            if let Some(material) = materials.get_mut(&material.0) {
+                material.base_color = color;
+            }
+        }
+    }
+}
+
+pub fn bandage_ui(
+    mut egui_context: EguiContexts,
+    cube_settings: Res<CubeSettings>,
+    mut editor: ResMut<BandageEditor>,
+    mut config: ResMut<BandageConfig>,
+    mut side_move_queue: ResMut<MoveQueue<CubePuzzle>>,
+    mut reset_event: EventWriter<ResetEvent>,
+) {
+    if cube_settings.puzzle != PuzzleKind::Bandaged {
+        return;
+    }
+    egui::Window::new("Bandages").show(egui_context.ctx_mut(), |ui| {
+        if ui.checkbox(&mut editor.editing, "Edit").changed() {
+            // 在还原状态下编辑，块的当前位置即为还原位置
+            editor.selected = None;
+            side_move_queue.0.clear();
+            reset_event.send_default();
+        }
+        if editor.editing {
+            ui.label("Click two adjacent pieces to bandage or unbandage them");
+        }
+        ui.label(format!("{} bonds", config.bonds.len()));
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                editor.status = Some(
+                    save_bandages(BANDAGE_FILE, &config.bonds)
+                        .map(|_| format!("saved to {}", BANDAGE_FILE))
+                        .map_err(|err| err.to_string()),
+                );
+            }
+            if ui.button("Load").clicked() {
+                editor.status = Some(match load_bandages(BANDAGE_FILE) {
+                    Ok(Some(bonds)) => {
+                        config.bonds = bonds;
+                        side_move_queue.0.clear();
+                        reset_event.send_default();
+                        Ok(format!("loaded from {}", BANDAGE_FILE))
+                    }
+                    Ok(None) => Err(format!("{} not found", BANDAGE_FILE)),
+                    Err(err) => Err(err.to_string()),
+                });
+            }
+            if ui.button("Clear").clicked() {
+                config.bonds.clear();
+                editor.selected = None;
+                side_move_queue.0.clear();
+                reset_event.send_default();
+            }
+        });
+        match &editor.status {
+            Some(Ok(message)) => {
+                ui.label(message);
+            }
+            Some(Err(err)) => {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+            None => {}
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_side_moves;
+
+    fn moves(algorithm: &str) -> Vec<Vec<SideMoveEvent>> {
+        parse_side_moves(algorithm, 3).unwrap()
+    }
+
+    fn queue(algorithm: &str) -> VecDeque<(Vec<SideMoveEvent>, MoveSource)> {
+        moves(algorithm)
+            .into_iter()
+            .map(|layers| (layers, MoveSource::Player))
+            .collect()
+    }
+
+    #[test]
+    fn finds_first_blocked_move_in_queue() {
+        let config = BandageConfig::default();
+        assert_eq!(
+            config.first_blocked(solved_positions(), &moves("U R L' F2")),
+            None
+        );
+        let (index, bonds) = config
+            .first_blocked(solved_positions(), &moves("R U"))
+            .unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(bonds, vec![config.bonds[1]]);
+        // F转动后长条竖在R面上，R可以转动而U会拆开长条
+        assert_eq!(
+            config.first_blocked(solved_positions(), &moves("F R")),
+            None
+        );
+        let (index, _) = config
+            .first_blocked(solved_positions(), &moves("D F U"))
+            .unwrap();
+        assert_eq!(index, 2);
+        // 宽层和中层同样检查
+        assert!(config
+            .first_blocked(solved_positions(), &moves("M"))
+            .is_some());
+        assert!(config
+            .first_blocked(solved_positions(), &moves("x y Rw'"))
+            .is_none());
+    }
+
+    #[test]
+    fn drops_only_blocked_moves() {
+        let config = BandageConfig::default();
+        // R被挡住，其后的F、R按未执行R的位置检查，都可以转动
+        let mut moves = queue("R F R");
+        let bonds = config.drop_blocked(&solved_positions(), &mut moves);
+        assert_eq!(bonds, Some(vec![config.bonds[1]]));
+        assert_eq!(moves, queue("F R"));
+        // U之后长条在L面上，F也会被挡住
+        let mut moves = queue("R U F");
+        assert!(config
+            .drop_blocked(&solved_positions(), &mut moves)
+            .is_some());
+        assert_eq!(moves, queue("U"));
+        let mut moves = queue("U R L'");
+        assert_eq!(config.drop_blocked(&solved_positions(), &mut moves), None);
+        assert_eq!(moves.len(), 3);
+    }
+
+    #[test]
+    fn toggle_only_bonds_adjacent_pieces() {
+        let mut config = BandageConfig { bonds: Vec::new() };
+        config.toggle(IVec3::new(1, 1, 1), IVec3::new(-1, 1, 1));
+        assert!(config.bonds.is_empty());
+        config.toggle(IVec3::new(1, 1, 1), IVec3::new(1, 1, 0));
+        assert!(config.contains(IVec3::new(1, 1, 0), IVec3::new(1, 1, 1)));
+        config.toggle(IVec3::new(1, 1, 0), IVec3::new(1, 1, 1));
+        assert!(config.bonds.is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rubiks_cube::facelet::{cube_state_from_facelets, facelet_string, FACELET_COUNT};
+use rubiks_cube::notation::{format_algorithm, parse_algorithm};
+use rubiks_cube::scramble;
+use rubiks_cube::solver;
+use rubiks_cube::state::{CubeState, Face};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage:
+  rubiks-cli scramble [--seed N] [--order N]
+  rubiks-cli solve \"<scramble or facelets>\"
+  rubiks-cli apply \"<alg>\" [--order N]
+  rubiks-cli verify \"<scramble or facelets>\" \"<solution>\" [--order N]";
+
+struct Args {
+    positional: Vec<String>,
+    seed: Option<u64>,
+    order: u8,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        positional: Vec::new(),
+        seed: None,
+        order: 3,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let value = args.next().ok_or("--seed needs a value")?;
+                parsed.seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid seed '{}'", value))?,
+                );
+            }
+            "--order" => {
+                let value = args.next().ok_or("--order needs a value")?;
+                parsed.order = value
+                    .parse()
+                    .ok()
+                    .filter(|order| *order >= 2)
+                    .ok_or(format!("invalid order '{}'", value))?;
+            }
+            _ => parsed.positional.push(arg),
+        }
+    }
+    Ok(parsed)
+}
+
+fn positional(args: &Args, count: usize) -> Result<&[String], String> {
+    if args.positional.len() != count {
+        return Err(format!(
+            "expected {} argument(s), found {}",
+            count,
+            args.positional.len()
+        ));
+    }
+    Ok(&args.positional)
+}
+
+// 恰好54个URFDLB字符且不含空白视为facelet字符串，否则视为公式，
+// 避免如`U R F D ...`这样由54个单层转动组成的公式被当作facelet
+fn is_facelet_string(input: &str) -> bool {
+    input.chars().count() == FACELET_COUNT
+        && input
+            .chars()
+            .all(|c| Face::from_char(c.to_ascii_uppercase()).is_some())
+}
+
+fn apply_algorithm(state: &mut CubeState, algorithm: &str) -> Result<(), String> {
+    let order = state.order();
+    for m in parse_algorithm(algorithm, order).map_err(|err| err.to_string())? {
+        for event in m.to_side_moves(order) {
+            state.apply_side_move(&event);
+        }
+    }
+    Ok(())
+}
+
+// 从还原状态执行打乱公式，或直接读取facelet字符串
+fn state_from_input(input: &str, order: u8) -> Result<CubeState, String> {
+    if order == 3 && is_facelet_string(input) {
+        return cube_state_from_facelets(input).map_err(|err| err.to_string());
+    }
+    let mut state = CubeState::solved(order);
+    apply_algorithm(&mut state, input)?;
+    Ok(state)
+}
+
+fn scramble_command(args: &Args) -> Result<bool, String> {
+    positional(args, 0)?;
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    println!(
+        "{}",
+        format_algorithm(&scramble::scramble(&mut rng, args.order))
+    );
+    Ok(true)
+}
+
+fn solve_command(args: &Args) -> Result<bool, String> {
+    let input = &positional(args, 1)?[0];
+    let state = state_from_input(input, 3)?;
+    let solution = solver::solve_state(&state).map_err(|err| err.to_string())?;
+    println!("{}", format_algorithm(&solution));
+    Ok(true)
+}
+
+fn apply_command(args: &Args) -> Result<bool, String> {
+    let algorithm = &positional(args, 1)?[0];
+    let state = state_from_input(algorithm, args.order)?;
+    // 3阶按中心块输出标准facelet字符串，可以再次导入
+    println!(
+        "{}",
+        facelet_string(&state).unwrap_or_else(|| state.facelet_string())
+    );
+    Ok(true)
+}
+
+fn verify_command(args: &Args) -> Result<bool, String> {
+    let inputs = positional(args, 2)?;
+    let mut state = state_from_input(&inputs[0], args.order)?;
+    apply_algorithm(&mut state, &inputs[1])?;
+    if state.is_solved() {
+        println!("solved");
+        Ok(true)
+    } else {
+        println!("not solved: {}", state.facelet_string());
+        Ok(false)
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(command) = args.next() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+    let result = parse_args(args).and_then(|args| match command.as_str() {
+        "scramble" => scramble_command(&args),
+        "solve" => solve_command(&args),
+        "apply" => apply_command(&args),
+        "verify" => verify_command(&args),
+        _ => Err(format!("unknown command '{}'", command)),
+    });
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        // 校验失败
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {}\n{}", err, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaced_algorithms_are_not_facelets() {
+        let solved = CubeState::solved(3).facelet_string();
+        assert!(is_facelet_string(&solved));
+        let spaced: String = solved.chars().flat_map(|c| [c, ' ']).collect();
+        assert!(!is_facelet_string(&spaced));
+
+        // 54步单层转动的公式从还原状态执行
+        let state = state_from_input(&spaced, 3).unwrap();
+        assert!(!state.is_solved());
+
+        // 与facelet解析一致，小写字母也按facelet字符串读取
+        let lower = solved.to_lowercase();
+        assert!(is_facelet_string(&lower));
+        assert!(state_from_input(&lower, 3).unwrap().is_solved());
+    }
+}
@@ -0,0 +1,716 @@
+use crate::cube::*;
+use crate::notation::{NotationError, NotationErrorKind};
+use crate::puzzle::{
+    predicted_state, ActiveMove, MoveEvent, MoveQueue, MoveSource, Puzzle, PuzzleState,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::fmt;
+
+// 每面3x3个表盘，每个表盘12个小时
+const DIALS: usize = 9;
+const HOURS: i32 = 12;
+
+// 针和齿轮的编号：0 UL，1 UR，2 DL，3 DR（从正面看）
+const PIN_NAMES: [&str; 4] = ["UL", "UR", "DL", "DR"];
+
+/// WCA记号中的针组合，转动时只有这些针竖起；打乱按此顺序排列
+pub const PIN_SETS: [(&str, [bool; 4]); 9] = [
+    ("UR", [false, true, false, false]),
+    ("DR", [false, false, false, true]),
+    ("DL", [false, false, true, false]),
+    ("UL", [true, false, false, false]),
+    ("U", [true, true, false, false]),
+    ("R", [false, true, false, true]),
+    ("D", [false, false, true, true]),
+    ("L", [true, false, true, false]),
+    ("ALL", [true, true, true, true]),
+];
+
+// 打乱翻面后只转动两针以上的组合
+const BACK_SCRAMBLE_SETS: usize = 4;
+
+// 针周围的4个表盘
+fn quadrant(pin: usize) -> [usize; 4] {
+    let first = pin / 2 * 3 + pin % 2;
+    [first, first + 1, first + 3, first + 4]
+}
+
+// 齿轮所在角上的表盘
+fn corner(pin: usize) -> usize {
+    pin / 2 * 6 + pin % 2 * 2
+}
+
+// 翻面后表盘左右对调
+fn mirror_dial(dial: usize) -> usize {
+    dial / 3 * 3 + 2 - dial % 3
+}
+
+// 翻面后针和齿轮左右对调
+fn mirror_pin(pin: usize) -> usize {
+    pin ^ 1
+}
+
+/// 魔表的状态，front和back分别从各自的一面看，表盘按行排列，0表示指向12点；
+/// pins为从正面看竖起的针，从背面看则是按下的
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClockState {
+    pub front: [u8; DIALS],
+    pub back: [u8; DIALS],
+    pub pins: [bool; 4],
+}
+
+impl ClockState {
+    pub const SOLVED: Self = Self {
+        front: [0; DIALS],
+        back: [0; DIALS],
+        pins: [false; 4],
+    };
+
+    // 该面这根针是否竖起
+    pub fn pin_up(&self, back: bool, pin: usize) -> bool {
+        if back {
+            !self.pins[mirror_pin(pin)]
+        } else {
+            self.pins[pin]
+        }
+    }
+
+    /// 与正面第wheel个齿轮联动的表盘及转动方向，0为不动；
+    /// 竖起的针连接周围4个表盘，角上的表盘与背面对应的表盘是同一个齿轮，方向相反
+    pub fn connected_dials(&self, wheel: usize) -> [[i32; DIALS]; 2] {
+        let mut signs = [[0; DIALS]; 2];
+        signs[0][corner(wheel)] = 1;
+        let mut stack = vec![(false, corner(wheel))];
+        while let Some((back, dial)) = stack.pop() {
+            let sign = signs[back as usize][dial];
+            let mut neighbors = Vec::new();
+            if (0..4).any(|pin| corner(pin) == dial) {
+                neighbors.push((!back, mirror_dial(dial), -sign));
+            }
+            for pin in 0..4 {
+                if self.pin_up(back, pin) && quadrant(pin).contains(&dial) {
+                    neighbors.extend(quadrant(pin).map(|d| (back, d, sign)));
+                }
+            }
+            for (back, dial, sign) in neighbors {
+                if signs[back as usize][dial] == 0 {
+                    signs[back as usize][dial] = sign;
+                    stack.push((back, dial));
+                }
+            }
+        }
+        signs
+    }
+
+    /// 从正面看顺时针转动第wheel个齿轮amount小时
+    pub fn turn_wheel(&mut self, wheel: usize, amount: i32) {
+        let signs = self.connected_dials(wheel);
+        for (dials, signs) in [&mut self.front, &mut self.back].into_iter().zip(signs) {
+            for (dial, sign) in dials.iter_mut().zip(signs) {
+                *dial = (*dial as i32 + sign * amount).rem_euclid(HOURS) as u8;
+            }
+        }
+    }
+
+    // 左右翻面，原来按下的针从新的正面看竖起
+    pub fn flip(&mut self) {
+        std::mem::swap(&mut self.front, &mut self.back);
+        let pins = self.pins;
+        for (pin, up) in pins.into_iter().enumerate() {
+            self.pins[mirror_pin(pin)] = !up;
+        }
+    }
+
+    pub fn apply(&mut self, m: ClockMove) {
+        match m {
+            ClockMove::Turn { set, amount } => {
+                let pins = PIN_SETS[set].1;
+                self.pins = pins;
+                // 竖起的针都连着中心表盘，转动其中任意一个齿轮效果相同
+                let wheel = pins.iter().position(|&up| up).unwrap();
+                self.turn_wheel(wheel, amount);
+            }
+            ClockMove::Wheel { wheel, amount } => self.turn_wheel(wheel, amount),
+            ClockMove::Flip => self.flip(),
+            ClockMove::Pins(pins) => self.pins = pins,
+        }
+    }
+
+    /// 两面的表盘都指向12点，针的位置不影响
+    pub fn is_solved(&self) -> bool {
+        self.front.iter().chain(&self.back).all(|&hour| hour == 0)
+    }
+}
+
+/// 一步操作：按WCA记号竖起一组针并转动，按当前的针转动一个齿轮，翻面，或设置针
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockMove {
+    // set为PIN_SETS的下标，amount从正面看顺时针为正
+    Turn { set: usize, amount: i32 },
+    Wheel { wheel: usize, amount: i32 },
+    Flip,
+    Pins([bool; 4]),
+}
+
+impl ClockMove {
+    /// 转动表盘的操作，计入步数
+    pub fn is_turn(self) -> bool {
+        matches!(self, ClockMove::Turn { .. } | ClockMove::Wheel { .. })
+    }
+
+    /// 针不变时接着上一步转动同一个齿轮，两步合起来算一步，如拖动齿轮时每小时一次的转动
+    pub fn continues(self, previous: ClockMove) -> bool {
+        match (self, previous) {
+            (ClockMove::Turn { set, .. }, ClockMove::Turn { set: previous, .. }) => set == previous,
+            (
+                ClockMove::Wheel { wheel, .. },
+                ClockMove::Wheel {
+                    wheel: previous, ..
+                },
+            ) => wheel == previous,
+            _ => false,
+        }
+    }
+}
+
+fn write_amount(f: &mut fmt::Formatter<'_>, amount: i32) -> fmt::Result {
+    let sign = if amount < 0 { '-' } else { '+' };
+    write!(f, "{}{}", amount.abs(), sign)
+}
+
+impl fmt::Display for ClockMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ClockMove::Turn { set, amount } => {
+                write!(f, "{}", PIN_SETS[set].0)?;
+                write_amount(f, amount)
+            }
+            ClockMove::Wheel { wheel, amount } => {
+                write!(f, "{} wheel ", PIN_NAMES[wheel])?;
+                write_amount(f, amount)
+            }
+            ClockMove::Flip => write!(f, "y2"),
+            ClockMove::Pins(pins) => {
+                let names: Vec<&str> = PIN_SETS[..4]
+                    .iter()
+                    .filter(|(_, set)| set.iter().zip(pins).any(|(&a, b)| a && b))
+                    .map(|(name, _)| *name)
+                    .collect();
+                write!(f, "{}", names.join(" "))
+            }
+        }
+    }
+}
+
+/// 解析WCA记号，如`UR3+ DL2- ALL0+ y2 U4- UR DL`，
+/// 不带小时数的针名表示最后竖起的针
+pub fn parse_algorithm(input: &str) -> Result<Vec<ClockMove>, NotationError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut moves = Vec::new();
+    let mut pos = 0;
+    let error = |position: usize| NotationError {
+        position,
+        kind: match chars.get(position) {
+            Some(&c) => NotationErrorKind::UnexpectedChar(c),
+            None => NotationErrorKind::UnexpectedEnd,
+        },
+    };
+    while let Some(&c) = chars.get(pos) {
+        if c.is_whitespace() || c == ',' {
+            pos += 1;
+            continue;
+        }
+        if chars[pos..].starts_with(&['y', '2']) {
+            pos += 2;
+            moves.push(ClockMove::Flip);
+            continue;
+        }
+        let Some(set) = (0..PIN_SETS.len())
+            .filter(|&set| {
+                let name: Vec<char> = PIN_SETS[set].0.chars().collect();
+                chars[pos..].starts_with(&name)
+            })
+            .max_by_key(|&set| PIN_SETS[set].0.len())
+        else {
+            return Err(error(pos));
+        };
+        pos += PIN_SETS[set].0.len();
+        let start = pos;
+        while chars.get(pos).is_some_and(|c| c.is_ascii_digit()) {
+            pos += 1;
+        }
+        if pos == start {
+            // 相邻的针名合并为一次设置
+            let pins = PIN_SETS[set].1;
+            match moves.last_mut() {
+                Some(ClockMove::Pins(up)) => {
+                    for (up, pin) in up.iter_mut().zip(pins) {
+                        *up |= pin;
+                    }
+                }
+                _ => moves.push(ClockMove::Pins(pins)),
+            }
+            continue;
+        }
+        let text: String = chars[start..pos].iter().collect();
+        let Some(hours) = text.parse::<i32>().ok().filter(|&hours| hours < HOURS) else {
+            return Err(NotationError {
+                position: start,
+                kind: NotationErrorKind::InvalidCount(text),
+            });
+        };
+        let amount = match chars.get(pos) {
+            Some('+') => hours,
+            Some('-') => -hours,
+            _ => return Err(error(pos)),
+        };
+        pos += 1;
+        moves.push(ClockMove::Turn { set, amount });
+    }
+    Ok(moves)
+}
+
+pub fn format_algorithm(moves: &[ClockMove]) -> String {
+    moves
+        .iter()
+        .map(|m| m.to_string())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// WCA打乱：正面依次转动全部9组针，翻面后再转动U R D L ALL，每次-5..=6小时
+pub fn scramble<R: Rng>(rng: &mut R) -> Vec<ClockMove> {
+    let mut turn = |set| ClockMove::Turn {
+        set,
+        amount: rng.gen_range(-5..=6),
+    };
+    let mut moves: Vec<ClockMove> = (0..PIN_SETS.len()).map(&mut turn).collect();
+    moves.push(ClockMove::Flip);
+    moves.extend((BACK_SCRAMBLE_SETS..PIN_SETS.len()).map(turn));
+    moves
+}
+
+/// 魔表前端的谜题，只在当前谜题为魔表时存在。
+/// 只有转动表盘计入步数，针不变时连续转动同一个齿轮只算一步
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct ClockPuzzle;
+
+impl Puzzle for ClockPuzzle {
+    type State = ClockState;
+    type Move = ClockMove;
+
+    fn solved_state(&self) -> ClockState {
+        ClockState::SOLVED
+    }
+
+    fn apply(&self, state: &ClockState, m: &ClockMove) -> Option<ClockState> {
+        let mut state = *state;
+        state.apply(*m);
+        Some(state)
+    }
+
+    // 设置针和按WCA记号转动都会改变针，撤销时还要恢复原来的针
+    fn invert(&self, state: &ClockState, m: &ClockMove) -> Vec<ClockMove> {
+        match *m {
+            ClockMove::Turn { set, amount } => vec![
+                ClockMove::Turn {
+                    set,
+                    amount: -amount,
+                },
+                ClockMove::Pins(state.pins),
+            ],
+            ClockMove::Wheel { wheel, amount } => vec![ClockMove::Wheel {
+                wheel,
+                amount: -amount,
+            }],
+            ClockMove::Flip => vec![ClockMove::Flip],
+            ClockMove::Pins(_) => vec![ClockMove::Pins(state.pins)],
+        }
+    }
+
+    fn is_solved(&self, state: &ClockState) -> bool {
+        state.is_solved()
+    }
+
+    fn counts(&self, m: &ClockMove, previous: Option<&ClockMove>) -> bool {
+        m.is_turn() && !previous.is_some_and(|&previous| m.continues(previous))
+    }
+
+    fn is_turn(&self, m: &ClockMove) -> bool {
+        m.is_turn()
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<ClockMove>, NotationError> {
+        parse_algorithm(input)
+    }
+
+    fn scramble(&self, rng: &mut StdRng) -> (String, Vec<ClockMove>) {
+        let moves = scramble(rng);
+        (format_algorithm(&moves), moves)
+    }
+}
+
+// 魔表面板中正在拖动的齿轮：(是否在背面, 该面的齿轮编号)及累计的拖动距离
+#[derive(Debug, Default, Resource)]
+pub struct ClockView {
+    pub dragging: Option<(bool, usize)>,
+    pub drag_distance: f32,
+}
+
+// 魔表没有需要重建的块，只按当前谜题插入或移除谜题资源；离开魔表时丢弃尚未执行的操作
+pub fn reset_clock(
+    mut commands: Commands,
+    mut events: EventReader<ResetEvent>,
+    cube_settings: Res<CubeSettings>,
+    puzzle: Option<Res<ClockPuzzle>>,
+    mut queue: ResMut<MoveQueue<ClockPuzzle>>,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+    if cube_settings.puzzle == PuzzleKind::Clock {
+        commands.insert_resource(ClockPuzzle);
+    } else if puzzle.is_some() {
+        queue.0.clear();
+        commands.remove_resource::<ClockPuzzle>();
+    }
+}
+
+// 魔表没有动画，每帧完成上一次操作并取出下一次
+pub fn choose_clock_move(
+    mut queue: ResMut<MoveQueue<ClockPuzzle>>,
+    mut active_move: ResMut<ActiveMove<ClockPuzzle>>,
+    mut events: EventWriter<MoveEvent<ClockPuzzle>>,
+) {
+    if let Some((m, source)) = active_move.0.take() {
+        events.send(MoveEvent(m, source));
+    }
+    active_move.0 = queue.0.pop_front();
+}
+
+// 每面的大小，两面左右并排，左边为正面
+const FACE_SIZE: f32 = 200.0;
+const FACE_GAP: f32 = 20.0;
+// 以表盘间距为单位的各部件半径
+const DIAL_RADIUS: f32 = 0.38;
+const PIN_RADIUS: f32 = 0.1;
+const WHEEL_RADIUS: f32 = 0.22;
+// 拖动齿轮时每转动1小时的距离
+const WHEEL_DRAG_STEP: f32 = 20.0;
+
+// 面板上可以操作的部件，编号从所在的一面看
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClockPart {
+    Pin(usize),
+    Wheel(usize),
+}
+
+// 一面的布局：表盘中心位于(列+0.5, 行+0.5)，针位于表盘之间，齿轮位于四角外侧
+struct FaceLayout {
+    origin: egui::Pos2,
+    unit: f32,
+}
+
+impl FaceLayout {
+    fn new(panel: egui::Rect, back: bool) -> Self {
+        let offset = if back { FACE_SIZE + FACE_GAP } else { 0.0 };
+        // 表盘区域两侧各留半个单位给齿轮
+        let unit = FACE_SIZE / 4.0;
+        Self {
+            origin: panel.min + egui::vec2(offset + unit / 2.0, unit / 2.0),
+            unit,
+        }
+    }
+
+    fn point(&self, x: f32, y: f32) -> egui::Pos2 {
+        self.origin + egui::vec2(x, y) * self.unit
+    }
+
+    fn dial(&self, dial: usize) -> egui::Pos2 {
+        self.point((dial % 3) as f32 + 0.5, (dial / 3) as f32 + 0.5)
+    }
+
+    fn pin(&self, pin: usize) -> egui::Pos2 {
+        self.point((1 + pin % 2) as f32, (1 + pin / 2) as f32)
+    }
+
+    fn wheel(&self, wheel: usize) -> egui::Pos2 {
+        self.point(
+            -0.2 + 3.4 * (wheel % 2) as f32,
+            -0.2 + 3.4 * (wheel / 2) as f32,
+        )
+    }
+
+    fn part_at(&self, pos: egui::Pos2) -> Option<ClockPart> {
+        let near = |center: egui::Pos2, radius: f32| center.distance(pos) <= radius * self.unit;
+        (0..4)
+            .find(|&pin| near(self.pin(pin), PIN_RADIUS * 1.5))
+            .map(ClockPart::Pin)
+            .or_else(|| {
+                (0..4)
+                    .find(|&wheel| near(self.wheel(wheel), WHEEL_RADIUS))
+                    .map(ClockPart::Wheel)
+            })
+    }
+
+    fn paint(&self, painter: &egui::Painter, state: &ClockState, back: bool) {
+        // 正面深色表盘底、白色表盘，背面反过来
+        let (body, dial_color, hand_color) = if back {
+            (
+                egui::Color32::from_rgb(170, 200, 230),
+                egui::Color32::from_rgb(40, 60, 90),
+                egui::Color32::WHITE,
+            )
+        } else {
+            (
+                egui::Color32::from_rgb(40, 60, 90),
+                egui::Color32::WHITE,
+                egui::Color32::BLACK,
+            )
+        };
+        let rect = egui::Rect::from_min_max(self.point(-0.1, -0.1), self.point(3.1, 3.1));
+        painter.rect_filled(rect, self.unit * 0.5, body);
+        let dials = if back { &state.back } else { &state.front };
+        for (dial, &hour) in dials.iter().enumerate() {
+            let center = self.dial(dial);
+            painter.circle_filled(center, DIAL_RADIUS * self.unit, dial_color);
+            // 12点处的标记
+            painter.circle_filled(
+                center - egui::vec2(0.0, DIAL_RADIUS * 0.8 * self.unit),
+                0.04 * self.unit,
+                egui::Color32::RED,
+            );
+            let angle = hour as f32 * std::f32::consts::TAU / HOURS as f32;
+            let tip =
+                center + egui::vec2(angle.sin(), -angle.cos()) * DIAL_RADIUS * 0.7 * self.unit;
+            painter.line_segment([center, tip], egui::Stroke::new(3.0, hand_color));
+        }
+        for pin in 0..4 {
+            let color = if state.pin_up(back, pin) {
+                egui::Color32::YELLOW
+            } else {
+                egui::Color32::DARK_GRAY
+            };
+            painter.circle_filled(self.pin(pin), PIN_RADIUS * self.unit, color);
+        }
+        for wheel in 0..4 {
+            painter.circle(
+                self.wheel(wheel),
+                WHEEL_RADIUS * self.unit,
+                egui::Color32::GRAY,
+                egui::Stroke::new(2.0, egui::Color32::DARK_GRAY),
+            );
+        }
+    }
+}
+
+// 从该面看顺时针转动齿轮，换算为正面的齿轮
+fn wheel_move(back: bool, wheel: usize, amount: i32) -> ClockMove {
+    if back {
+        ClockMove::Wheel {
+            wheel: mirror_pin(wheel),
+            amount: -amount,
+        }
+    } else {
+        ClockMove::Wheel { wheel, amount }
+    }
+}
+
+// 魔表面板：点击针按下或竖起，点击齿轮顺时针转1小时（右键逆时针），上下拖动齿轮连续转动
+pub fn clock_ui(
+    mut egui_context: EguiContexts,
+    puzzle: Option<Res<ClockPuzzle>>,
+    state: Res<PuzzleState<ClockPuzzle>>,
+    active_move: Res<ActiveMove<ClockPuzzle>>,
+    mut queue: ResMut<MoveQueue<ClockPuzzle>>,
+    mut view: ResMut<ClockView>,
+) {
+    let (Some(puzzle), Some(state)) = (puzzle, &state.0) else {
+        return;
+    };
+    egui::Window::new("Clock")
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            let (response, painter) = ui.allocate_painter(
+                egui::vec2(FACE_SIZE * 2.0 + FACE_GAP, FACE_SIZE),
+                egui::Sense::click_and_drag(),
+            );
+            let faces = [false, true].map(|back| (back, FaceLayout::new(response.rect, back)));
+            for (back, layout) in &faces {
+                layout.paint(&painter, state, *back);
+            }
+            let part_at = |pos: egui::Pos2| {
+                faces
+                    .iter()
+                    .find_map(|(back, layout)| layout.part_at(pos).map(|part| (*back, part)))
+            };
+
+            let clicked = if response.clicked() {
+                Some(1)
+            } else if response.secondary_clicked() {
+                Some(-1)
+            } else {
+                None
+            };
+            if let (Some(amount), Some(pos)) = (clicked, response.interact_pointer_pos()) {
+                match part_at(pos) {
+                    Some((back, ClockPart::Pin(pin))) => {
+                        // 魔表的操作不会被挡住
+                        let mut pins = predicted_state(&*puzzle, state, &active_move, &queue)
+                            .unwrap()
+                            .pins;
+                        let pin = if back { mirror_pin(pin) } else { pin };
+                        pins[pin] = !pins[pin];
+                        queue.push_moves([ClockMove::Pins(pins)], MoveSource::Player);
+                    }
+                    Some((back, ClockPart::Wheel(wheel))) => {
+                        queue.push_moves([wheel_move(back, wheel, amount)], MoveSource::Player);
+                    }
+                    None => {}
+                }
+            }
+
+            if response.drag_started() {
+                let origin = ui.input(|input| input.pointer.press_origin());
+                view.dragging = origin
+                    .and_then(part_at)
+                    .and_then(|(back, part)| match part {
+                        ClockPart::Wheel(wheel) => Some((back, wheel)),
+                        ClockPart::Pin(_) => None,
+                    });
+                view.drag_distance = 0.0;
+            }
+            if let Some((back, wheel)) = view.dragging {
+                // 向上拖动顺时针
+                view.drag_distance -= response.drag_delta().y;
+                while view.drag_distance.abs() >= WHEEL_DRAG_STEP {
+                    let amount = view.drag_distance.signum() as i32;
+                    view.drag_distance -= amount as f32 * WHEEL_DRAG_STEP;
+                    queue.push_moves([wheel_move(back, wheel, amount)], MoveSource::Player);
+                }
+            }
+            if response.drag_stopped() {
+                view.dragging = None;
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Flip (y2)").clicked() {
+                    queue.push_moves([ClockMove::Flip], MoveSource::Player);
+                }
+                ui.label("Left: front, right: back");
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::move_history::MoveHistory;
+    use crate::puzzle::PuzzlePlugin;
+
+    fn tracking_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(PuzzlePlugin::<ClockPuzzle>::default())
+            .insert_resource(ClockPuzzle);
+        app.world_mut().send_event(ResetEvent);
+        app.update();
+        app
+    }
+
+    fn send_moves(app: &mut App, moves: &[ClockMove], source: MoveSource) {
+        for &m in moves {
+            app.world_mut()
+                .send_event(MoveEvent::<ClockPuzzle>(m, source));
+        }
+        app.update();
+    }
+
+    fn move_count(app: &App) -> u32 {
+        app.world().resource::<SolveProgress>().move_count
+    }
+
+    #[test]
+    fn dragging_a_wheel_counts_once() {
+        let mut app = tracking_app();
+        let step = wheel_move(false, 0, 1);
+        // 拖动5小时分为5次转动
+        send_moves(&mut app, &[step; 5], MoveSource::Player);
+        assert_eq!(move_count(&app), 1);
+        // 分在不同帧完成的转动同样合并
+        send_moves(&mut app, &[step], MoveSource::Player);
+        assert_eq!(move_count(&app), 1);
+
+        send_moves(
+            &mut app,
+            &[wheel_move(false, 1, -1), step],
+            MoveSource::Player,
+        );
+        assert_eq!(move_count(&app), 3);
+        // 改变针之后再转同一个齿轮是新的一步
+        send_moves(
+            &mut app,
+            &[ClockMove::Pins([true; 4]), step],
+            MoveSource::Player,
+        );
+        assert_eq!(move_count(&app), 4);
+    }
+
+    #[test]
+    fn scramble_turns_are_not_counted_or_merged() {
+        let mut app = tracking_app();
+        let turn = ClockMove::Turn { set: 0, amount: 3 };
+        send_moves(&mut app, &[turn], MoveSource::Scramble);
+        assert_eq!(move_count(&app), 0);
+        send_moves(&mut app, &[turn], MoveSource::Player);
+        assert_eq!(move_count(&app), 1);
+    }
+
+    #[test]
+    fn undo_restores_dials_and_pins() {
+        let mut app = tracking_app();
+        let moves = parse_algorithm("UR3+ y2 DL2- ALL0+").unwrap();
+        send_moves(&mut app, &moves, MoveSource::Player);
+        send_moves(
+            &mut app,
+            &[ClockMove::Pins([true, false, true, false])],
+            MoveSource::Player,
+        );
+        assert_eq!(move_count(&app), 3);
+
+        // 按撤销的顺序执行逆操作，回到还原状态且针恢复原样
+        while let Some(undo) = app
+            .world_mut()
+            .resource_mut::<MoveHistory<ClockMove>>()
+            .undo()
+        {
+            send_moves(&mut app, &undo, MoveSource::History);
+        }
+        let state = app.world().resource::<PuzzleState<ClockPuzzle>>();
+        assert_eq!(state.0, Some(ClockState::SOLVED));
+
+        let mut history = app.world_mut().resource_mut::<MoveHistory<ClockMove>>();
+        assert_eq!(history.redo(), Some(moves[0]));
+        assert!(history.can_redo());
+    }
+
+    #[test]
+    fn parses_and_formats_wca_notation() {
+        let moves = parse_algorithm("UR3+ DL2- ALL0+ y2 U4- UR DL").unwrap();
+        assert_eq!(moves.len(), 6);
+        assert_eq!(moves[3], ClockMove::Flip);
+        assert_eq!(format_algorithm(&moves), "UR3+ DL2- ALL0+ y2 U4- UR DL");
+        assert!(parse_algorithm("UR12+").is_err());
+        assert!(parse_algorithm("UR3").is_err());
+
+        let mut state = ClockState::SOLVED;
+        for m in parse_algorithm("ALL3+ ALL3-").unwrap() {
+            state.apply(m);
+        }
+        assert!(state.is_solved());
+    }
+}
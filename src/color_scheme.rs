@@ -0,0 +1,143 @@
+use crate::cube::*;
+use crate::save_data::SaveError;
+use crate::state::Face;
+use bevy::color::palettes;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+// 配色文件格式版本
+pub const COLOR_SCHEME_VERSION: u32 = 1;
+pub const COLOR_SCHEME_FILE: &str = "rubiks-cube-colors.ron";
+
+/// 六个面的配色，顺序为URFDLB
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorScheme(pub [Color; 6]);
+
+impl ColorScheme {
+    // 西方配色：白顶绿前，黄白、蓝绿、橙红相对
+    pub fn western() -> Self {
+        Self([
+            palettes::css::WHITE.into(),
+            palettes::css::RED.into(),
+            palettes::css::GREEN.into(),
+            palettes::css::YELLOW.into(),
+            palettes::css::ORANGE.into(),
+            palettes::css::BLUE.into(),
+        ])
+    }
+
+    // 日本配色：蓝白、黄绿、橙红相对
+    pub fn japanese() -> Self {
+        Self([
+            palettes::css::WHITE.into(),
+            palettes::css::RED.into(),
+            palettes::css::GREEN.into(),
+            palettes::css::BLUE.into(),
+            palettes::css::ORANGE.into(),
+            palettes::css::YELLOW.into(),
+        ])
+    }
+
+    // 高对比度配色，橙色换成紫色以便与红色区分
+    pub fn high_contrast() -> Self {
+        Self([
+            palettes::css::WHITE.into(),
+            palettes::css::RED.into(),
+            palettes::css::LIME.into(),
+            palettes::css::YELLOW.into(),
+            palettes::css::PURPLE.into(),
+            palettes::css::BLUE.into(),
+        ])
+    }
+
+    pub fn presets() -> [(&'static str, ColorScheme); 3] {
+        [
+            ("Western", Self::western()),
+            ("Japanese", Self::japanese()),
+            ("High Contrast", Self::high_contrast()),
+        ]
+    }
+
+    pub fn color(&self, face: Face) -> Color {
+        self.0[face as usize]
+    }
+}
+
+impl CubeSettings {
+    pub fn color_scheme(&self) -> ColorScheme {
+        ColorScheme(Face::ALL.map(|face| self.face_color(face)))
+    }
+
+    pub fn set_color_scheme(&mut self, scheme: ColorScheme) {
+        self.up_color = scheme.color(Face::U);
+        self.right_color = scheme.color(Face::R);
+        self.front_color = scheme.color(Face::F);
+        self.down_color = scheme.color(Face::D);
+        self.left_color = scheme.color(Face::L);
+        self.back_color = scheme.color(Face::B);
+    }
+}
+
+// 配色文件，颜色按URFDLB顺序保存为sRGB分量
+#[derive(Debug, Serialize, Deserialize)]
+struct ColorSchemeFile {
+    version: u32,
+    colors: [[u8; 3]; 6],
+}
+
+pub fn save_color_scheme(path: &str, scheme: &ColorScheme) -> Result<(), SaveError> {
+    let file = ColorSchemeFile {
+        version: COLOR_SCHEME_VERSION,
+        colors: scheme.0.map(|color| {
+            let [r, g, b, _] = color.to_srgba().to_u8_array();
+            [r, g, b]
+        }),
+    };
+    let text = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+        .map_err(SaveError::Serialize)?;
+    fs::write(path, text).map_err(SaveError::Io)
+}
+
+// 读取保存的配色，文件不存在时返回None
+pub fn load_color_scheme(path: &str) -> Result<Option<ColorScheme>, SaveError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(SaveError::Io(err)),
+    };
+    let file: ColorSchemeFile = ron::from_str(&text).map_err(SaveError::Deserialize)?;
+    if file.version != COLOR_SCHEME_VERSION {
+        return Err(SaveError::UnsupportedVersion(file.version));
+    }
+    Ok(Some(ColorScheme(
+        file.colors.map(|[r, g, b]| Color::srgb_u8(r, g, b)),
+    )))
+}
+
+pub fn setup_color_scheme(mut cube_settings: ResMut<CubeSettings>) {
+    match load_color_scheme(COLOR_SCHEME_FILE) {
+        Ok(Some(scheme)) => cube_settings.set_color_scheme(scheme),
+        Ok(None) => {}
+        Err(err) => warn!("failed to load {}: {}", COLOR_SCHEME_FILE, err),
+    }
+}
+
+// 配色变化时直接修改现有贴纸的材质，无需重建魔方
+pub fn update_sticker_colors(
+    cube_settings: Res<CubeSettings>,
+    mut applied: Local<Option<ColorScheme>>,
+    q_stickers: Query<(&Sticker, &MeshMaterial3d<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let scheme = cube_settings.color_scheme();
+    if *applied == Some(scheme) {
+        return;
+    }
+    for (sticker, material) in &q_stickers {
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.base_color = scheme.color(sticker.face);
+        }
+    }
+    *applied = Some(scheme);
+}
@@ -1,47 +1,83 @@
-use crate::moving::{self, *};
+use crate::bandage::*;
+use crate::custom;
+use crate::keyboard::KeyScheme;
+use crate::megaminx;
+use crate::moving::*;
+use crate::notation::{format_algorithm, parse_cuboid_side_moves, NotationError};
+use crate::puzzle::{MoveQueue, Puzzle};
+use crate::pyraminx;
+use crate::scramble;
+use crate::skewb;
+use crate::solver;
+use crate::state::{CubeState, Face, SideMoveEvent};
+use crate::twisty::DynTwisty;
 use bevy::color::palettes;
 use bevy::prelude::*;
-use rand::seq::SliceRandom;
-use rand::Rng;
+use bevy::tasks::AsyncComputeTaskPool;
+use rand::rngs::StdRng;
 use std::f32::consts::FRAC_PI_2;
 use std::f32::consts::PI;
+use std::sync::Arc;
 
 #[derive(Debug, Component)]
 pub struct MovablePiece {
-    pub axis: moving::Axis,
-    pub rotate: SideRotation,
+    // 绕过中心的转轴转动
+    pub axis: Vec3,
+    // 剩余的转动角度，按右手定则为正
     pub left_angle: f32,
 }
 
+// 贴纸，记录还原状态下所在的面，用于更新配色
+#[derive(Debug, Component, Clone, Copy)]
+pub struct Sticker {
+    pub face: Face,
+}
+
 #[derive(Debug, Component, Reflect, Default, Clone, Copy)]
 #[reflect(Component)]
 pub struct Piece {
     pub init_pos: Vec3,
     pub size: f32,
+    // 所属魔方x/y/z方向的层数，正阶魔方三者相同
+    pub dims: [u8; 3],
 }
 
 impl Piece {
+    // 各方向最外层的坐标值
+    fn outer(&self) -> Vec3 {
+        Vec3::from_array(self.dims.map(outer_layer))
+    }
     pub fn has_up_face(&self) -> bool {
-        self.init_pos.y == 1.0
+        self.init_pos.y == self.outer().y
     }
     pub fn has_down_face(&self) -> bool {
-        self.init_pos.y == -1.0
+        self.init_pos.y == -self.outer().y
     }
     pub fn has_left_face(&self) -> bool {
-        self.init_pos.x == -1.0
+        self.init_pos.x == -self.outer().x
     }
     pub fn has_right_face(&self) -> bool {
-        self.init_pos.x == 1.0
+        self.init_pos.x == self.outer().x
     }
     pub fn has_front_face(&self) -> bool {
-        self.init_pos.z == 1.0
+        self.init_pos.z == self.outer().z
     }
     pub fn has_back_face(&self) -> bool {
-        self.init_pos.z == -1.0
+        self.init_pos.z == -self.outer().z
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+// N阶魔方最外层的坐标值
+pub fn outer_layer(order: u8) -> f32 {
+    (order as f32 - 1.0) / 2.0
+}
+
+// N阶魔方各层的坐标值，如3阶为-1、0、1，4阶为-1.5、-0.5、0.5、1.5
+pub fn layer_values(order: u8) -> Vec<f32> {
+    (0..order).map(|i| i as f32 - outer_layer(order)).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PlayMode {
     // 练习模式
     Practice,
@@ -49,9 +85,90 @@ pub enum PlayMode {
     Timekeeping,
 }
 
+/// 可选的谜题
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PuzzleKind {
+    Cube,
+    Pyraminx,
+    Megaminx,
+    Skewb,
+    Square1,
+    Clock,
+    Cuboid,
+    // 从文件加载的扭转类谜题
+    Custom,
+    // 部分块粘在一起的三阶魔方
+    Bandaged,
+}
+
+impl PuzzleKind {
+    pub const ALL: [PuzzleKind; 9] = [
+        PuzzleKind::Cube,
+        PuzzleKind::Pyraminx,
+        PuzzleKind::Megaminx,
+        PuzzleKind::Skewb,
+        PuzzleKind::Square1,
+        PuzzleKind::Clock,
+        PuzzleKind::Cuboid,
+        PuzzleKind::Custom,
+        PuzzleKind::Bandaged,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PuzzleKind::Cube => "Cube",
+            PuzzleKind::Pyraminx => "Pyraminx",
+            PuzzleKind::Megaminx => "Megaminx",
+            PuzzleKind::Skewb => "Skewb",
+            PuzzleKind::Square1 => "Square-1",
+            PuzzleKind::Clock => "Clock",
+            PuzzleKind::Cuboid => "Cuboid",
+            PuzzleKind::Custom => "Custom",
+            PuzzleKind::Bandaged => "Bandaged",
+        }
+    }
+
+    /// 由Piece组成、按层转动的谜题，共用魔方的生成、拖动和转动逻辑
+    pub fn uses_pieces(self) -> bool {
+        matches!(
+            self,
+            PuzzleKind::Cube | PuzzleKind::Cuboid | PuzzleKind::Bandaged
+        )
+    }
+
+    /// 由扭转类谜题前端按几何定义驱动的谜题，其他谜题有各自的前端
+    pub fn twisty(self) -> Option<Arc<dyn DynTwisty>> {
+        match self {
+            PuzzleKind::Cube
+            | PuzzleKind::Cuboid
+            | PuzzleKind::Bandaged
+            | PuzzleKind::Square1
+            | PuzzleKind::Clock => None,
+            PuzzleKind::Pyraminx => Some(Arc::new(pyraminx::Pyraminx)),
+            PuzzleKind::Megaminx => Some(Arc::new(megaminx::Megaminx)),
+            PuzzleKind::Skewb => Some(Arc::new(skewb::Skewb)),
+            PuzzleKind::Custom => custom::loaded().map(|puzzle| puzzle as Arc<dyn DynTwisty>),
+        }
+    }
+}
+
+/// 可选的长方体魔方，层数按x/y/z方向排列，名称中最后一个数为高度
+pub const CUBOID_PRESETS: [(&str, [u8; 3]); 4] = [
+    ("2x2x3", [2, 3, 2]),
+    ("3x3x2", [3, 2, 3]),
+    ("1x3x3", [3, 1, 3]),
+    ("3x3x4", [3, 4, 3]),
+];
+
 /// 魔方设置
 #[derive(Debug, Resource)]
 pub struct CubeSettings {
+    // 当前谜题
+    pub puzzle: PuzzleKind,
+    // 阶数
+    pub cube_order: u8,
+    // 长方体魔方x/y/z方向的层数
+    pub cuboid: [u8; 3],
     // 块大小
     pub piece_size: f32,
     // 旋转速度
@@ -64,6 +181,8 @@ pub struct CubeSettings {
     pub down_color: Color,
     // 游玩模式
     pub play_mode: PlayMode,
+    // 键盘键位方案
+    pub key_scheme: KeyScheme,
     // 相机缩放速度
     pub camera_zoom_speed: f32,
 }
@@ -71,6 +190,9 @@ pub struct CubeSettings {
 impl Default for CubeSettings {
     fn default() -> Self {
         Self {
+            puzzle: PuzzleKind::Cube,
+            cube_order: 3,
+            cuboid: CUBOID_PRESETS[0].1,
             piece_size: 1.0,
             rotate_speed: 1.0,
             front_color: palettes::css::GREEN.into(),
@@ -80,26 +202,184 @@ impl Default for CubeSettings {
             up_color: palettes::css::WHITE.into(),
             down_color: palettes::css::YELLOW.into(),
             play_mode: PlayMode::Practice,
+            key_scheme: KeyScheme::Plain,
             camera_zoom_speed: 1.01,
         }
     }
 }
 
+impl CubeSettings {
+    /// 当前魔方x/y/z方向的层数
+    pub fn dims(&self) -> [u8; 3] {
+        match self.puzzle {
+            PuzzleKind::Cuboid => self.cuboid,
+            PuzzleKind::Bandaged => [3; 3],
+            _ => [self.cube_order; 3],
+        }
+    }
+
+    // 最长方向的层数，决定相机距离
+    pub fn max_layers(&self) -> u8 {
+        self.dims().into_iter().max().unwrap()
+    }
+
+    // 还原状态下该面的颜色
+    pub fn face_color(&self, face: Face) -> Color {
+        match face {
+            Face::U => self.up_color,
+            Face::R => self.right_color,
+            Face::F => self.front_color,
+            Face::D => self.down_color,
+            Face::L => self.left_color,
+            Face::B => self.back_color,
+        }
+    }
+}
+
+/// 由Piece组成、按层转动的魔方：正阶魔方、长方体魔方和粘连魔方，一步转动为同轴同向一起转动的所有层
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct CubePuzzle {
+    // x/y/z方向的层数
+    pub dims: [u8; 3],
+    // 长方体魔方，非正方形的面只能转动180度
+    pub cuboid: bool,
+    // 粘连魔方的粘连，转动开始前由choose_movable_pieces检查
+    pub bandages: Option<BandageConfig>,
+}
+
+impl CubePuzzle {
+    /// 当前设置下的魔方，其他谜题返回None
+    pub fn new(settings: &CubeSettings, bandages: &BandageConfig) -> Option<Self> {
+        settings.puzzle.uses_pieces().then(|| Self {
+            dims: settings.dims(),
+            cuboid: settings.puzzle == PuzzleKind::Cuboid,
+            bandages: (settings.puzzle == PuzzleKind::Bandaged).then(|| bandages.clone()),
+        })
+    }
+}
+
+impl Puzzle for CubePuzzle {
+    type State = CubeState;
+    type Move = Vec<SideMoveEvent>;
+
+    fn solved_state(&self) -> CubeState {
+        if self.cuboid {
+            CubeState::cuboid(self.dims)
+        } else {
+            CubeState::solved(self.dims[0])
+        }
+    }
+
+    // 长方体魔方非正方形的面转动90度会卡住
+    fn apply(&self, state: &CubeState, layers: &Vec<SideMoveEvent>) -> Option<CubeState> {
+        if !layers.iter().all(|event| event.is_allowed(self.dims)) {
+            return None;
+        }
+        let mut state = state.clone();
+        for event in layers {
+            state.apply_side_move(event);
+        }
+        Some(state)
+    }
+
+    // 各层反向转动
+    fn invert(&self, _state: &CubeState, layers: &Vec<SideMoveEvent>) -> Vec<Vec<SideMoveEvent>> {
+        vec![layers
+            .iter()
+            .map(|event| SideMoveEvent {
+                side: event.side,
+                rotate: event.rotate.inverse(),
+            })
+            .collect()]
+    }
+
+    fn is_solved(&self, state: &CubeState) -> bool {
+        state.is_solved()
+    }
+
+    // 整体转动（x/y/z）转动某个轴上的所有层，观察时可以用来查看各面，不开始计时
+    fn is_turn(&self, layers: &Vec<SideMoveEvent>) -> bool {
+        let Some(axis) = layers.first().map(|event| event.side.0) else {
+            return false;
+        };
+        layers.len() != self.dims[axis.index()] as usize
+            || layers.iter().any(|event| event.side.0 != axis)
+    }
+
+    // 没有层的转动（如R4）忽略
+    fn parse(&self, input: &str) -> Result<Vec<Vec<SideMoveEvent>>, NotationError> {
+        let mut moves = parse_cuboid_side_moves(input, self.dims)?;
+        moves.retain(|layers| !layers.is_empty());
+        Ok(moves)
+    }
+
+    fn scramble(&self, rng: &mut StdRng) -> (String, Vec<Vec<SideMoveEvent>>) {
+        let moves = match &self.bandages {
+            Some(bandages) => bandaged_scramble(rng, bandages),
+            None if self.cuboid => scramble::cuboid_scramble(rng, self.dims),
+            None => scramble::scramble(rng, self.dims[0]),
+        };
+        (
+            format_algorithm(&moves),
+            moves
+                .iter()
+                .map(|m| m.to_cuboid_side_moves(self.dims))
+                .collect(),
+        )
+    }
+
+    // 求解器只支持三阶魔方
+    fn can_solve(&self) -> bool {
+        self.dims == [3; 3] && !self.cuboid && self.bandages.is_none()
+    }
+
+    fn solve(&self, state: &CubeState) -> Result<(String, Vec<Vec<SideMoveEvent>>), String> {
+        let moves = solver::solve_state(state).map_err(|err| err.to_string())?;
+        Ok((
+            format_algorithm(&moves),
+            moves.iter().map(|m| m.to_side_moves(3)).collect(),
+        ))
+    }
+}
+
 // 重置魔方
 #[derive(Debug, Default, Event)]
 pub struct ResetEvent;
 
-// 打乱魔方
+// 打乱魔方，指定种子时可复现
 #[derive(Debug, Default, Event)]
-pub struct ScrambleEvent;
+pub struct ScrambleEvent {
+    pub seed: Option<u64>,
+}
 
-pub fn setup_cube(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    cube_settings: Res<CubeSettings>,
-) {
-    create_cube(&mut commands, &mut meshes, &mut materials, &cube_settings);
+// 最近一次的打乱公式
+#[derive(Debug, Default, Resource)]
+pub struct CurrentScramble(pub Option<String>);
+
+// 打乱后本次还原的进度，打乱本身的转动不计入步数
+#[derive(Debug, Default, Resource)]
+pub struct SolveProgress {
+    // 打乱完成后的转动数
+    pub move_count: u32,
+}
+
+// 转动完成后魔方回到还原状态（任意整体朝向）
+#[derive(Debug, Event)]
+pub struct CubeSolvedEvent {
+    pub move_count: u32,
+}
+
+// 求解魔方
+#[derive(Debug, Default, Event)]
+pub struct SolveEvent;
+
+// 最近一次求解的结果，用于UI展示
+#[derive(Debug, Default, Resource)]
+pub struct LatestSolution(pub Option<Result<String, String>>);
+
+// 启动时由各前端按当前设置生成谜题
+pub fn setup_cube(mut reset_event: EventWriter<ResetEvent>) {
+    reset_event.send_default();
 }
 
 fn create_cube(
@@ -107,14 +387,27 @@ fn create_cube(
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
     cube_settings: &Res<CubeSettings>,
+    bandages: &BandageConfig,
 ) {
+    // 只有粘连魔方显示粘连
+    let bonds: &[Bond] = match cube_settings.puzzle {
+        PuzzleKind::Bandaged => &bandages.bonds,
+        _ => &[],
+    };
     // cubes
-    for x in [-1.0, 0.0, 1.0] {
-        for y in [-1.0, 0.0, 1.0] {
-            for z in [-1.0, 0.0, 1.0] {
+    let dims = cube_settings.dims();
+    let outer = dims.map(outer_layer);
+    for x in layer_values(dims[0]) {
+        for y in layer_values(dims[1]) {
+            for z in layer_values(dims[2]) {
+                // 内部的块不可见，无需生成
+                if x.abs() != outer[0] && y.abs() != outer[1] && z.abs() != outer[2] {
+                    continue;
+                }
                 let piece = Piece {
                     init_pos: Vec3::new(x, y, z),
                     size: cube_settings.piece_size,
+                    dims,
                 };
                 commands
                     .spawn((
@@ -127,9 +420,11 @@ fn create_cube(
                     .observe(handle_drag_start)
                     .observe(handle_move)
                     .observe(handle_drag_end)
+                    .observe(handle_piece_click)
                     .with_children(|parent| {
                         // 外部贴纸
                         spawn_stickers(parent, piece, meshes, materials, cube_settings);
+                        spawn_bridges(parent, piece, bonds, meshes, materials, cube_settings);
                     });
             }
         }
@@ -156,6 +451,7 @@ fn spawn_stickers(
                 ..default()
             })),
             transform,
+            Sticker { face: Face::U },
         ));
     }
 
@@ -174,6 +470,7 @@ fn spawn_stickers(
                 ..default()
             })),
             transform,
+            Sticker { face: Face::D },
         ));
     }
     if piece.has_left_face() {
@@ -191,6 +488,7 @@ fn spawn_stickers(
                 ..default()
             })),
             transform,
+            Sticker { face: Face::L },
         ));
     }
 
@@ -206,6 +504,7 @@ fn spawn_stickers(
                 ..default()
             })),
             transform,
+            Sticker { face: Face::R },
         ));
     }
 
@@ -221,6 +520,7 @@ fn spawn_stickers(
                 ..default()
             })),
             transform,
+            Sticker { face: Face::F },
         ));
     }
 
@@ -239,10 +539,14 @@ fn spawn_stickers(
                 ..default()
             })),
             transform,
+            Sticker { face: Face::B },
         ));
     }
 }
 
+// 重置时按当前设置重建魔方并更换谜题资源，切换到其他谜题时只移除；
+// 阶数、形状或粘连改变后，队列中的转动属于旧魔方，一并丢弃
+#[allow(clippy::too_many_arguments)]
 pub fn reset_cube(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -250,44 +554,157 @@ pub fn reset_cube(
     cube_settings: Res<CubeSettings>,
     mut events: EventReader<ResetEvent>,
     q_pieces: Query<Entity, With<Piece>>,
+    bandages: Res<BandageConfig>,
+    puzzle: Option<Res<CubePuzzle>>,
+    mut queue: ResMut<MoveQueue<CubePuzzle>>,
 ) {
-    for _ in events.read() {
-        // 移除原有魔方
-        for piece in &q_pieces {
-            commands.entity(piece).despawn_recursive();
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+    // 移除原有魔方
+    for piece in &q_pieces {
+        commands.entity(piece).despawn_recursive();
+    }
+    let next = CubePuzzle::new(&cube_settings, &bandages);
+    if puzzle.as_deref() != next.as_ref() {
+        queue.0.clear();
+    }
+    match next {
+        Some(next) => {
+            create_cube(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &cube_settings,
+                &bandages,
+            );
+            commands.insert_resource(next);
         }
-        // 重建魔方
-        create_cube(&mut commands, &mut meshes, &mut materials, &cube_settings);
+        None => commands.remove_resource::<CubePuzzle>(),
     }
 }
 
-pub fn scramble_cube(
-    mut events: EventReader<ScrambleEvent>,
-    mut side_move_queue: ResMut<SideMoveQueue>,
-) {
-    for _ in events.read() {
-        for _ in 0..5 {
-            let axis_value = vec![-1.0f32, 0.0, 1.0]
-                .choose(&mut rand::thread_rng())
-                .unwrap()
-                .clone();
-            let axis = match rand::thread_rng().gen_range(0..3) {
-                0 => moving::Axis::X,
-                1 => moving::Axis::Y,
-                2 => moving::Axis::Z,
-                _ => moving::Axis::X,
-            };
-            // let axis = moving::Axis::Y;
-            let rotate = match rand::thread_rng().gen_range(0..3) {
-                0 => SideRotation::Clockwise90,
-                1 => SideRotation::Clockwise180,
-                2 => SideRotation::Counterclockwise90,
-                _ => SideRotation::Clockwise90,
+// 后台生成求解器的转动表和剪枝表
+pub fn warm_up_solver() {
+    AsyncComputeTaskPool::get()
+        .spawn(async {
+            solver::warm_up();
+            for twisty in PuzzleKind::ALL.into_iter().filter_map(PuzzleKind::twisty) {
+                twisty.warm_up();
+            }
+        })
+        .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::move_history::MoveHistory;
+    use crate::notation::parse_side_moves;
+    use crate::puzzle::*;
+    use crate::ui::AlgorithmInput;
+
+    fn tracking_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(PuzzlePlugin::<CubePuzzle>::default())
+            .insert_resource(
+                CubePuzzle::new(&CubeSettings::default(), &BandageConfig::default()).unwrap(),
+            );
+        app.world_mut().send_event(ResetEvent);
+        app.update();
+        app
+    }
+
+    fn send_moves(app: &mut App, algorithm: &str, source: MoveSource) {
+        for layers in parse_side_moves(algorithm, 3).unwrap() {
+            app.world_mut()
+                .send_event(MoveEvent::<CubePuzzle>(layers, source));
+        }
+        app.update();
+    }
+
+    fn solved_events(app: &App) -> usize {
+        app.world().resource::<Events<CubeSolvedEvent>>().len()
+    }
+
+    #[test]
+    fn multi_layer_moves_count_once() {
+        let mut app = tracking_app();
+        send_moves(&mut app, "x Rw M", MoveSource::Player);
+        assert_eq!(app.world().resource::<SolveProgress>().move_count, 3);
+        // 整体转动前后都是还原状态，不算完成还原
+        assert_eq!(solved_events(&app), 0);
+
+        let mut history = app.world_mut().resource_mut::<MoveHistory>();
+        assert_eq!(history.undo().map(|moves| moves[0].len()), Some(1));
+        assert_eq!(history.undo().map(|moves| moves[0].len()), Some(2));
+        assert_eq!(history.undo().map(|moves| moves[0].len()), Some(3));
+    }
+
+    #[test]
+    fn scramble_and_history_moves_are_not_recorded() {
+        let mut app = tracking_app();
+        send_moves(&mut app, "R U", MoveSource::Scramble);
+        assert_eq!(app.world().resource::<SolveProgress>().move_count, 0);
+        assert!(!app.world().resource::<MoveHistory>().can_undo());
+
+        send_moves(&mut app, "U' R'", MoveSource::History);
+        assert_eq!(app.world().resource::<SolveProgress>().move_count, 2);
+        assert!(!app.world().resource::<MoveHistory>().can_undo());
+        assert_eq!(solved_events(&app), 1);
+    }
+
+    #[test]
+    fn reset_discards_moves_in_flight() {
+        let mut app = tracking_app();
+        send_moves(&mut app, "R", MoveSource::Player);
+        app.world_mut().send_event(ResetEvent);
+        send_moves(&mut app, "U", MoveSource::Player);
+        assert_eq!(app.world().resource::<SolveProgress>().move_count, 0);
+        let state = app.world().resource::<PuzzleState<CubePuzzle>>();
+        assert!(state.0.as_ref().unwrap().is_solved());
+    }
+
+    #[test]
+    fn applies_algorithms_and_reports_errors() {
+        let mut app = tracking_app();
+        app.world_mut()
+            .send_event(ApplyAlgorithmEvent("R U R4".to_string()));
+        app.update();
+        // R4没有转动的层，不加入队列
+        assert_eq!(app.world().resource::<MoveQueue<CubePuzzle>>().0.len(), 2);
+        assert!(app.world().resource::<AlgorithmInput>().error.is_none());
+        assert!(app.world().resource::<PuzzleStatus>().turning);
+
+        app.world_mut()
+            .send_event(ApplyAlgorithmEvent("R Q".to_string()));
+        app.update();
+        assert_eq!(app.world().resource::<MoveQueue<CubePuzzle>>().0.len(), 2);
+        assert!(app.world().resource::<AlgorithmInput>().error.is_some());
+    }
+
+    #[test]
+    fn only_the_three_by_three_can_be_solved() {
+        let settings = CubeSettings::default();
+        let bandages = BandageConfig::default();
+        assert!(CubePuzzle::new(&settings, &bandages).unwrap().can_solve());
+        for (puzzle, cube_order) in [
+            (PuzzleKind::Cube, 4),
+            (PuzzleKind::Cuboid, 3),
+            (PuzzleKind::Bandaged, 3),
+        ] {
+            let settings = CubeSettings {
+                puzzle,
+                cube_order,
+                ..default()
             };
-            side_move_queue.0.push_back(SideMoveEvent {
-                side: (axis, axis_value),
-                rotate,
-            })
+            assert!(!CubePuzzle::new(&settings, &bandages).unwrap().can_solve());
         }
+        let settings = CubeSettings {
+            puzzle: PuzzleKind::Clock,
+            ..default()
+        };
+        assert!(CubePuzzle::new(&settings, &bandages).is_none());
     }
 }
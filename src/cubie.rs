@@ -0,0 +1,430 @@
+use crate::state::{CubeState, Cubie, Face};
+use std::fmt;
+
+// 角块位置，顺序与Kociemba一致
+pub const URF: usize = 0;
+pub const UFL: usize = 1;
+pub const ULB: usize = 2;
+pub const UBR: usize = 3;
+pub const DFR: usize = 4;
+pub const DLF: usize = 5;
+pub const DBL: usize = 6;
+pub const DRB: usize = 7;
+
+// 棱块位置
+pub const UR: usize = 0;
+pub const UF: usize = 1;
+pub const UL: usize = 2;
+pub const UB: usize = 3;
+pub const DR: usize = 4;
+pub const DF: usize = 5;
+pub const DL: usize = 6;
+pub const DB: usize = 7;
+pub const FR: usize = 8;
+pub const FL: usize = 9;
+pub const BL: usize = 10;
+pub const BR: usize = 11;
+
+// facelet下标，U1..U9为0..8，之后依次为R、F、D、L、B
+const U1: usize = 0;
+const U2: usize = 1;
+const U3: usize = 2;
+const U4: usize = 3;
+const U6: usize = 5;
+const U7: usize = 6;
+const U8: usize = 7;
+const U9: usize = 8;
+const R1: usize = 9;
+const R2: usize = 10;
+const R3: usize = 11;
+const R4: usize = 12;
+const R6: usize = 14;
+const R7: usize = 15;
+const R8: usize = 16;
+const R9: usize = 17;
+const F1: usize = 18;
+const F2: usize = 19;
+const F3: usize = 20;
+const F4: usize = 21;
+const F6: usize = 23;
+const F7: usize = 24;
+const F8: usize = 25;
+const F9: usize = 26;
+const D1: usize = 27;
+const D2: usize = 28;
+const D3: usize = 29;
+const D4: usize = 30;
+const D6: usize = 32;
+const D7: usize = 33;
+const D8: usize = 34;
+const D9: usize = 35;
+const L1: usize = 36;
+const L2: usize = 37;
+const L3: usize = 38;
+const L4: usize = 39;
+const L6: usize = 41;
+const L7: usize = 42;
+const L8: usize = 43;
+const L9: usize = 44;
+const B1: usize = 45;
+const B2: usize = 46;
+const B3: usize = 47;
+const B4: usize = 48;
+const B6: usize = 50;
+const B7: usize = 51;
+const B8: usize = 52;
+const B9: usize = 53;
+
+// 每个角块位置上的三个facelet，从U/D面开始顺时针
+const CORNER_FACELET: [[usize; 3]; 8] = [
+    [U9, R1, F3],
+    [U7, F1, L3],
+    [U1, L1, B3],
+    [U3, B1, R3],
+    [D3, F9, R7],
+    [D1, L9, F7],
+    [D7, B9, L7],
+    [D9, R9, B7],
+];
+
+// 每个棱块位置上的两个facelet
+const EDGE_FACELET: [[usize; 2]; 12] = [
+    [U6, R2],
+    [U8, F2],
+    [U4, L2],
+    [U2, B2],
+    [D6, R8],
+    [D2, F8],
+    [D4, L8],
+    [D8, B8],
+    [F6, R4],
+    [F4, L6],
+    [B6, L4],
+    [B4, R6],
+];
+
+const CORNER_COLOR: [[Face; 3]; 8] = [
+    [Face::U, Face::R, Face::F],
+    [Face::U, Face::F, Face::L],
+    [Face::U, Face::L, Face::B],
+    [Face::U, Face::B, Face::R],
+    [Face::D, Face::F, Face::R],
+    [Face::D, Face::L, Face::F],
+    [Face::D, Face::B, Face::L],
+    [Face::D, Face::R, Face::B],
+];
+
+const EDGE_COLOR: [[Face; 2]; 12] = [
+    [Face::U, Face::R],
+    [Face::U, Face::F],
+    [Face::U, Face::L],
+    [Face::U, Face::B],
+    [Face::D, Face::R],
+    [Face::D, Face::F],
+    [Face::D, Face::L],
+    [Face::D, Face::B],
+    [Face::F, Face::R],
+    [Face::F, Face::L],
+    [Face::B, Face::L],
+    [Face::B, Face::R],
+];
+
+/// 3阶魔方在角块、棱块层面的表示（中心块固定），用于求解和合法性检查
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CubieCube {
+    // 角块排列，cp[i]为位置i上的角块
+    pub cp: [u8; 8],
+    // 角块朝向
+    pub co: [u8; 8],
+    // 棱块排列
+    pub ep: [u8; 12],
+    // 棱块朝向
+    pub eo: [u8; 12],
+}
+
+impl Default for CubieCube {
+    fn default() -> Self {
+        Self::SOLVED
+    }
+}
+
+/// 从facelet转换或检查状态时遇到的错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubieError {
+    // 角块位置上的颜色组合不存在
+    InvalidCorner(usize),
+    // 棱块位置上的颜色组合不存在
+    InvalidEdge(usize),
+    DuplicateCorner,
+    DuplicateEdge,
+    // 角块朝向之和不是3的倍数
+    TwistedCorner,
+    // 棱块朝向之和不是偶数
+    FlippedEdge,
+    // 角块和棱块排列的奇偶性不一致
+    Parity,
+}
+
+impl fmt::Display for CubieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CubieError::InvalidCorner(i) => write!(f, "invalid colors on corner {}", i),
+            CubieError::InvalidEdge(i) => write!(f, "invalid colors on edge {}", i),
+            CubieError::DuplicateCorner => write!(f, "some corner appears twice"),
+            CubieError::DuplicateEdge => write!(f, "some edge appears twice"),
+            CubieError::TwistedCorner => write!(f, "a corner is twisted"),
+            CubieError::FlippedEdge => write!(f, "an edge is flipped"),
+            CubieError::Parity => write!(f, "corner and edge permutation parity differ"),
+        }
+    }
+}
+
+impl std::error::Error for CubieError {}
+
+impl CubieCube {
+    pub const SOLVED: CubieCube = CubieCube {
+        cp: [0, 1, 2, 3, 4, 5, 6, 7],
+        co: [0; 8],
+        ep: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+        eo: [0; 12],
+    };
+
+    /// 从URFDLB顺序的54个facelet构造，要求中心块处于标准位置
+    pub fn from_facelets(facelets: &[Face]) -> Result<Self, CubieError> {
+        let mut cube = CubieCube::SOLVED;
+        for (i, facelet) in CORNER_FACELET.iter().enumerate() {
+            // 找到U/D颜色所在的facelet，即为朝向
+            let ori = (0..3)
+                .find(|&ori| matches!(facelets[facelet[ori]], Face::U | Face::D))
+                .ok_or(CubieError::InvalidCorner(i))?;
+            let col1 = facelets[facelet[(ori + 1) % 3]];
+            let col2 = facelets[facelet[(ori + 2) % 3]];
+            let j = CORNER_COLOR
+                .iter()
+                .position(|color| color[1] == col1 && color[2] == col2)
+                .ok_or(CubieError::InvalidCorner(i))?;
+            cube.cp[i] = j as u8;
+            cube.co[i] = ori as u8;
+        }
+        for (i, facelet) in EDGE_FACELET.iter().enumerate() {
+            let col0 = facelets[facelet[0]];
+            let col1 = facelets[facelet[1]];
+            if let Some(j) = EDGE_COLOR
+                .iter()
+                .position(|color| color[0] == col0 && color[1] == col1)
+            {
+                cube.ep[i] = j as u8;
+                cube.eo[i] = 0;
+            } else if let Some(j) = EDGE_COLOR
+                .iter()
+                .position(|color| color[0] == col1 && color[1] == col0)
+            {
+                cube.ep[i] = j as u8;
+                cube.eo[i] = 1;
+            } else {
+                return Err(CubieError::InvalidEdge(i));
+            }
+        }
+        Ok(cube)
+    }
+
+    /// 转换为3阶逻辑魔方，中心块处于标准位置
+    pub fn to_cube_state(&self) -> CubeState {
+        let mut cubies: Vec<Cubie> = Face::ALL
+            .iter()
+            .map(|face| {
+                let home = face.normal().map(|v| 2 * v);
+                Cubie {
+                    home,
+                    pos: home,
+                    orient: [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+                }
+            })
+            .collect();
+        for (i, faces) in CORNER_COLOR.iter().enumerate() {
+            let corner = self.cp[i] as usize;
+            cubies.push(placed_cubie(
+                &CORNER_COLOR[corner],
+                faces,
+                self.co[i] as usize,
+            ));
+        }
+        for (i, faces) in EDGE_COLOR.iter().enumerate() {
+            let edge = self.ep[i] as usize;
+            cubies.push(placed_cubie(&EDGE_COLOR[edge], faces, self.eo[i] as usize));
+        }
+        CubeState::from_cubies(3, cubies)
+    }
+
+    /// 依次执行self与other（other的排列作用在self之后）
+    pub fn multiply(&self, other: &CubieCube) -> CubieCube {
+        let mut result = CubieCube::SOLVED;
+        for i in 0..8 {
+            let from = other.cp[i] as usize;
+            result.cp[i] = self.cp[from];
+            result.co[i] = (self.co[from] + other.co[i]) % 3;
+        }
+        for i in 0..12 {
+            let from = other.ep[i] as usize;
+            result.ep[i] = self.ep[from];
+            result.eo[i] = (self.eo[from] + other.eo[i]) % 2;
+        }
+        result
+    }
+
+    /// 检查是否为可还原的状态
+    pub fn verify(&self) -> Result<(), CubieError> {
+        let mut corners = self.cp;
+        corners.sort();
+        if corners != CubieCube::SOLVED.cp {
+            return Err(CubieError::DuplicateCorner);
+        }
+        let mut edges = self.ep;
+        edges.sort();
+        if edges != CubieCube::SOLVED.ep {
+            return Err(CubieError::DuplicateEdge);
+        }
+        if self.co.iter().map(|&o| o as u32).sum::<u32>() % 3 != 0 {
+            return Err(CubieError::TwistedCorner);
+        }
+        if self.eo.iter().map(|&o| o as u32).sum::<u32>() % 2 != 0 {
+            return Err(CubieError::FlippedEdge);
+        }
+        if self.corner_parity() != self.edge_parity() {
+            return Err(CubieError::Parity);
+        }
+        Ok(())
+    }
+
+    pub fn corner_parity(&self) -> u8 {
+        permutation_parity(&self.cp)
+    }
+
+    pub fn edge_parity(&self) -> u8 {
+        permutation_parity(&self.ep)
+    }
+}
+
+// 排列的奇偶性，0为偶排列
+pub fn permutation_parity(perm: &[u8]) -> u8 {
+    let mut parity = 0;
+    for i in 0..perm.len() {
+        for j in i + 1..perm.len() {
+            if perm[i] > perm[j] {
+                parity ^= 1;
+            }
+        }
+    }
+    parity
+}
+
+/// 六个面顺时针转动90度，顺序为U、R、F、D、L、B
+pub const BASIC_MOVES: [CubieCube; 6] = [
+    // U
+    CubieCube {
+        cp: [
+            UBR as u8, URF as u8, UFL as u8, ULB as u8, DFR as u8, DLF as u8, DBL as u8, DRB as u8,
+        ],
+        co: [0; 8],
+        ep: [
+            UB as u8, UR as u8, UF as u8, UL as u8, DR as u8, DF as u8, DL as u8, DB as u8,
+            FR as u8, FL as u8, BL as u8, BR as u8,
+        ],
+        eo: [0; 12],
+    },
+    // R
+    CubieCube {
+        cp: [
+            DFR as u8, UFL as u8, ULB as u8, URF as u8, DRB as u8, DLF as u8, DBL as u8, UBR as u8,
+        ],
+        co: [2, 0, 0, 1, 1, 0, 0, 2],
+        ep: [
+            FR as u8, UF as u8, UL as u8, UB as u8, BR as u8, DF as u8, DL as u8, DB as u8,
+            DR as u8, FL as u8, BL as u8, UR as u8,
+        ],
+        eo: [0; 12],
+    },
+    // F
+    CubieCube {
+        cp: [
+            UFL as u8, DLF as u8, ULB as u8, UBR as u8, URF as u8, DFR as u8, DBL as u8, DRB as u8,
+        ],
+        co: [1, 2, 0, 0, 2, 1, 0, 0],
+        ep: [
+            UR as u8, FL as u8, UL as u8, UB as u8, DR as u8, FR as u8, DL as u8, DB as u8,
+            UF as u8, DF as u8, BL as u8, BR as u8,
+        ],
+        eo: [0, 1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 0],
+    },
+    // D
+    CubieCube {
+        cp: [
+            URF as u8, UFL as u8, ULB as u8, UBR as u8, DLF as u8, DBL as u8, DRB as u8, DFR as u8,
+        ],
+        co: [0; 8],
+        ep: [
+            UR as u8, UF as u8, UL as u8, UB as u8, DF as u8, DL as u8, DB as u8, DR as u8,
+            FR as u8, FL as u8, BL as u8, BR as u8,
+        ],
+        eo: [0; 12],
+    },
+    // L
+    CubieCube {
+        cp: [
+            URF as u8, ULB as u8, DBL as u8, UBR as u8, DFR as u8, UFL as u8, DLF as u8, DRB as u8,
+        ],
+        co: [0, 1, 2, 0, 0, 2, 1, 0],
+        ep: [
+            UR as u8, UF as u8, BL as u8, UB as u8, DR as u8, DF as u8, FL as u8, DB as u8,
+            FR as u8, UL as u8, DL as u8, BR as u8,
+        ],
+        eo: [0; 12],
+    },
+    // B
+    CubieCube {
+        cp: [
+            URF as u8, UFL as u8, UBR as u8, DRB as u8, DFR as u8, DLF as u8, ULB as u8, DBL as u8,
+        ],
+        co: [0, 0, 1, 2, 0, 0, 2, 1],
+        ep: [
+            UR as u8, UF as u8, UL as u8, BR as u8, DR as u8, DF as u8, DL as u8, BL as u8,
+            FR as u8, FL as u8, UB as u8, DB as u8,
+        ],
+        eo: [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1],
+    },
+];
+
+// 还原状态下在home_faces上的块放到faces所在的位置，第k个贴纸转到faces的第(k + ori) % n个面
+fn placed_cubie(home_faces: &[Face], faces: &[Face], ori: usize) -> Cubie {
+    let position = |faces: &[Face]| {
+        faces.iter().fold([0; 3], |pos, face| {
+            let normal = face.normal();
+            std::array::from_fn(|i| pos[i] + 2 * normal[i])
+        })
+    };
+    let mut orient = [[0; 3]; 3];
+    let mut known = [false; 3];
+    for (k, face) in home_faces.iter().enumerate() {
+        let home_normal = face.normal();
+        let axis = home_normal.iter().position(|&v| v != 0).unwrap();
+        let normal = faces[(k + ori) % faces.len()].normal();
+        orient[axis] = normal.map(|v| v * home_normal[axis]);
+        known[axis] = true;
+    }
+    // 棱块只有两个贴纸，第三个轴按右手定则由另外两个轴得到
+    for axis in 0..3 {
+        if !known[axis] {
+            let (a, b) = (orient[(axis + 1) % 3], orient[(axis + 2) % 3]);
+            orient[axis] = [
+                a[1] * b[2] - a[2] * b[1],
+                a[2] * b[0] - a[0] * b[2],
+                a[0] * b[1] - a[1] * b[0],
+            ];
+        }
+    }
+    Cubie {
+        home: position(home_faces),
+        pos: position(faces),
+        orient,
+    }
+}
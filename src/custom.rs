@@ -0,0 +1,408 @@
+use crate::color_scheme::ColorScheme;
+use crate::notation::{NotationError, NotationErrorKind};
+use crate::save_data::SaveError;
+use crate::twisty::{Twist, TwistAxis, Twisty, TwistyDef, TwistyError, TwistyModel};
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::sync::{Arc, RwLock};
+
+// 自定义谜题文件格式版本
+pub const CUSTOM_PUZZLE_FILE_VERSION: u32 = 1;
+pub const CUSTOM_PUZZLE_FILE: &str = "puzzles/dino_cube.ron";
+
+const DEFAULT_SCRAMBLE_LENGTH: usize = 25;
+
+// 外形多面体的一个面，法向量不需要是单位向量，distance为面到中心的距离
+#[derive(Debug, Deserialize)]
+struct FaceDef {
+    normal: [f32; 3],
+    distance: f32,
+    // 形如"#ff8000"的颜色
+    color: String,
+}
+
+// 切割面，depth为沿轴方向到中心的距离，外侧的块随name对应的转动一起转动
+#[derive(Debug, Deserialize)]
+struct CutDef {
+    name: String,
+    depth: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct AxisDef {
+    normal: [f32; 3],
+    // 转一整圈需要的次数
+    order: u32,
+    cuts: Vec<CutDef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CustomPuzzleFile {
+    version: u32,
+    name: String,
+    faces: Vec<FaceDef>,
+    axes: Vec<AxisDef>,
+    #[serde(default = "default_scramble_length")]
+    scramble_length: usize,
+}
+
+fn default_scramble_length() -> usize {
+    DEFAULT_SCRAMBLE_LENGTH
+}
+
+#[derive(Debug)]
+pub enum CustomPuzzleError {
+    Load(SaveError),
+    // 转动名称为空、以数字开头或包含空白和'
+    InvalidName(String),
+    DuplicateName(String),
+    InvalidOrder(u32),
+    InvalidColor(String),
+    // 没有任何有名称的转动，无法打乱和输入公式
+    NoTurns,
+    Geometry(TwistyError),
+}
+
+impl fmt::Display for CustomPuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CustomPuzzleError::Load(err) => write!(f, "{}", err),
+            CustomPuzzleError::InvalidName(name) => write!(f, "invalid turn name '{}'", name),
+            CustomPuzzleError::DuplicateName(name) => write!(f, "duplicate turn name '{}'", name),
+            CustomPuzzleError::InvalidOrder(order) => {
+                write!(f, "axis order must be at least 2, got {}", order)
+            }
+            CustomPuzzleError::InvalidColor(color) => write!(f, "invalid color '{}'", color),
+            CustomPuzzleError::NoTurns => write!(f, "the puzzle defines no turns"),
+            CustomPuzzleError::Geometry(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CustomPuzzleError {}
+
+// 一个有名称的转动，对应某个轴上的某个切割面
+#[derive(Debug, Clone)]
+struct CustomTurn {
+    name: String,
+    axis: usize,
+    cut: usize,
+}
+
+/// 自定义谜题的一步转动，amount为从轴的外侧看顺时针转动的次数，负数为逆时针
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomMove {
+    pub name: String,
+    pub axis: usize,
+    pub cut: usize,
+    pub amount: i32,
+}
+
+impl fmt::Display for CustomMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.amount.abs() > 1 {
+            write!(f, "{}", self.amount.abs())?;
+        }
+        if self.amount < 0 {
+            write!(f, "'")?;
+        }
+        Ok(())
+    }
+}
+
+/// 从文件读取的谜题，几何定义已经验证可以生成模型
+#[derive(Debug)]
+pub struct CustomPuzzle {
+    pub name: String,
+    def: TwistyDef,
+    turns: Vec<CustomTurn>,
+    scramble_length: usize,
+}
+
+impl CustomPuzzle {
+    pub fn from_ron(text: &str) -> Result<Self, CustomPuzzleError> {
+        let file: CustomPuzzleFile = ron::from_str(text)
+            .map_err(|err| CustomPuzzleError::Load(SaveError::Deserialize(err)))?;
+        if file.version != CUSTOM_PUZZLE_FILE_VERSION {
+            return Err(CustomPuzzleError::Load(SaveError::UnsupportedVersion(
+                file.version,
+            )));
+        }
+
+        let colors = file
+            .faces
+            .iter()
+            .map(|face| {
+                Srgba::hex(&face.color)
+                    .map(Color::from)
+                    .map_err(|_| CustomPuzzleError::InvalidColor(face.color.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut turns: Vec<CustomTurn> = Vec::new();
+        for (axis, axis_def) in file.axes.iter().enumerate() {
+            if axis_def.order < 2 {
+                return Err(CustomPuzzleError::InvalidOrder(axis_def.order));
+            }
+            for (cut, cut_def) in axis_def.cuts.iter().enumerate() {
+                let name = cut_def.name.as_str();
+                if name.is_empty()
+                    || name.starts_with(|c: char| c.is_ascii_digit())
+                    || name.contains(|c: char| c.is_whitespace() || c == '\'' || c == ',')
+                {
+                    return Err(CustomPuzzleError::InvalidName(name.to_string()));
+                }
+                if turns.iter().any(|turn| turn.name == name) {
+                    return Err(CustomPuzzleError::DuplicateName(name.to_string()));
+                }
+                turns.push(CustomTurn {
+                    name: name.to_string(),
+                    axis,
+                    cut,
+                });
+            }
+        }
+        if turns.is_empty() {
+            return Err(CustomPuzzleError::NoTurns);
+        }
+        let def = TwistyDef {
+            faces: file
+                .faces
+                .iter()
+                .map(|face| (Vec3::from(face.normal), face.distance))
+                .collect(),
+            colors,
+            axes: file
+                .axes
+                .iter()
+                .map(|axis| TwistAxis {
+                    normal: Vec3::from(axis.normal),
+                    order: axis.order,
+                    cuts: axis.cuts.iter().map(|cut| cut.depth).collect(),
+                })
+                .collect(),
+        };
+        // 切割并检查每个转动都能使谜题与自身重合
+        TwistyModel::new(&def).map_err(CustomPuzzleError::Geometry)?;
+
+        Ok(Self {
+            name: file.name,
+            def,
+            turns,
+            scramble_length: file.scramble_length,
+        })
+    }
+
+    fn order(&self, axis: usize) -> i32 {
+        self.def.axes[axis].order as i32
+    }
+}
+
+impl Twisty for CustomPuzzle {
+    type State = ();
+    type Move = CustomMove;
+
+    fn definition(&self, _colors: &ColorScheme) -> TwistyDef {
+        self.def.clone()
+    }
+
+    fn to_twist(&self, m: CustomMove) -> Twist {
+        Twist {
+            axis: m.axis,
+            cut: m.cut,
+            amount: m.amount,
+        }
+    }
+
+    /// 解析如`UFR UFR' R2`的公式，转动名称取最长匹配，数字为转动次数
+    fn parse(&self, input: &str) -> Result<Vec<CustomMove>, NotationError> {
+        let chars: Vec<char> = input.chars().collect();
+        let mut moves = Vec::new();
+        let mut pos = 0;
+        while let Some(&c) = chars.get(pos) {
+            if c.is_whitespace() || c == ',' {
+                pos += 1;
+                continue;
+            }
+            let Some(turn) = self
+                .turns
+                .iter()
+                .filter(|turn| {
+                    let name: Vec<char> = turn.name.chars().collect();
+                    chars[pos..].starts_with(&name)
+                })
+                .max_by_key(|turn| turn.name.chars().count())
+            else {
+                return Err(NotationError {
+                    position: pos,
+                    kind: NotationErrorKind::UnexpectedChar(c),
+                });
+            };
+            pos += turn.name.chars().count();
+            let start = pos;
+            while chars.get(pos).is_some_and(char::is_ascii_digit) {
+                pos += 1;
+            }
+            let mut amount = 1;
+            if pos > start {
+                let count: String = chars[start..pos].iter().collect();
+                amount = match count.parse::<i32>() {
+                    Ok(count) if (1..self.order(turn.axis)).contains(&count) => count,
+                    _ => {
+                        return Err(NotationError {
+                            position: start,
+                            kind: NotationErrorKind::InvalidCount(count),
+                        })
+                    }
+                };
+            }
+            if chars.get(pos) == Some(&'\'') {
+                pos += 1;
+                amount = -amount;
+            }
+            moves.push(CustomMove {
+                name: turn.name.clone(),
+                axis: turn.axis,
+                cut: turn.cut,
+                amount,
+            });
+        }
+        Ok(moves)
+    }
+
+    // 随机转动，相邻两步不转同一个轴，转动次数取绝对值较小的方向
+    fn scramble(&self, rng: &mut StdRng) -> Vec<CustomMove> {
+        let mut moves: Vec<CustomMove> = Vec::with_capacity(self.scramble_length);
+        while moves.len() < self.scramble_length {
+            let turn = &self.turns[rng.gen_range(0..self.turns.len())];
+            // 只有一个轴时无法避开上一步的轴
+            let multi_axis = self.turns.iter().any(|other| other.axis != turn.axis);
+            if multi_axis && moves.last().is_some_and(|last| last.axis == turn.axis) {
+                continue;
+            }
+            let order = self.order(turn.axis);
+            let amount = rng.gen_range(1..order);
+            moves.push(CustomMove {
+                name: turn.name.clone(),
+                axis: turn.axis,
+                cut: turn.cut,
+                amount: if amount > order / 2 {
+                    amount - order
+                } else {
+                    amount
+                },
+            });
+        }
+        moves
+    }
+}
+
+// 当前加载的自定义谜题；重新加载时替换，旧的定义在最后一个引用释放后回收
+static LOADED: RwLock<Option<Arc<CustomPuzzle>>> = RwLock::new(None);
+
+pub fn loaded() -> Option<Arc<CustomPuzzle>> {
+    LOADED
+        .read()
+        .expect("custom puzzle lock is not poisoned")
+        .clone()
+}
+
+/// 读取谜题文件并替换当前的自定义谜题
+pub fn load_custom_puzzle(path: &str) -> Result<Arc<CustomPuzzle>, CustomPuzzleError> {
+    let text =
+        fs::read_to_string(path).map_err(|err| CustomPuzzleError::Load(SaveError::Io(err)))?;
+    let puzzle = Arc::new(CustomPuzzle::from_ron(&text)?);
+    *LOADED.write().expect("custom puzzle lock is not poisoned") = Some(puzzle.clone());
+    Ok(puzzle)
+}
+
+// 谜题文件路径输入框
+#[derive(Debug, Resource)]
+pub struct CustomPuzzleInput {
+    pub path: String,
+    pub error: Option<String>,
+}
+
+impl Default for CustomPuzzleInput {
+    fn default() -> Self {
+        Self {
+            path: CUSTOM_PUZZLE_FILE.to_string(),
+            error: None,
+        }
+    }
+}
+
+// 启动时加载默认的谜题文件，文件不存在时不加载
+pub fn setup_custom_puzzle(mut input: ResMut<CustomPuzzleInput>) {
+    match load_custom_puzzle(&input.path) {
+        Ok(puzzle) => info!("loaded custom puzzle {}", puzzle.name),
+        Err(CustomPuzzleError::Load(SaveError::Io(err)))
+            if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => {
+            warn!("failed to load {}: {}", input.path, err);
+            input.error = Some(err.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const POCKET_CUBE: &str = include_str!("../puzzles/pocket_cube.ron");
+
+    #[test]
+    fn sample_puzzles_load() {
+        for text in [POCKET_CUBE, include_str!("../puzzles/dino_cube.ron")] {
+            let puzzle = CustomPuzzle::from_ron(text).unwrap();
+            assert!(!puzzle.turns.is_empty());
+        }
+    }
+
+    #[test]
+    fn rejects_puzzle_without_turns() {
+        let text = POCKET_CUBE.replace(r#"cuts: [(name: "R", depth: 0.0)]"#, "cuts: []");
+        let text = ["U", "F", "L", "D", "B"].iter().fold(text, |text, name| {
+            text.replace(
+                &format!(r#"cuts: [(name: "{}", depth: 0.0)]"#, name),
+                "cuts: []",
+            )
+        });
+        assert!(matches!(
+            CustomPuzzle::from_ron(&text),
+            Err(CustomPuzzleError::NoTurns)
+        ));
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let text = POCKET_CUBE.replace(r#"(name: "L""#, r#"(name: "R""#);
+        assert!(matches!(
+            CustomPuzzle::from_ron(&text),
+            Err(CustomPuzzleError::DuplicateName(name)) if name == "R"
+        ));
+    }
+
+    #[test]
+    fn parse_and_format_round_trip() {
+        let puzzle = CustomPuzzle::from_ron(POCKET_CUBE).unwrap();
+        let moves = puzzle.parse("R U2 F' L2'").unwrap();
+        assert_eq!(
+            moves.iter().map(|m| m.amount).collect::<Vec<_>>(),
+            [1, 2, -1, -2]
+        );
+        assert_eq!(puzzle.format(&moves), "R U2 F' L2'");
+        assert!(puzzle.parse("R4").is_err());
+        assert!(puzzle.parse("X").is_err());
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let scramble = puzzle.scramble(&mut rng);
+        assert_eq!(scramble.len(), DEFAULT_SCRAMBLE_LENGTH);
+        assert_eq!(puzzle.parse(&puzzle.format(&scramble)).unwrap(), scramble);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::cubie::{CubieCube, CubieError};
+use crate::state::{CubeState, Face};
+use std::fmt;
+
+// 3阶facelet字符串长度
+pub const FACELET_COUNT: usize = 54;
+
+/// 解析facelet字符串时遇到的错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceletError {
+    WrongLength(usize),
+    InvalidChar { position: usize, found: char },
+    // 某个面的颜色数量不是9
+    WrongCount(Face, usize),
+    // 中心块不在标准位置
+    MisplacedCenter(Face),
+    Cubie(CubieError),
+}
+
+impl fmt::Display for FaceletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaceletError::WrongLength(len) => {
+                write!(f, "expected {} facelets, found {}", FACELET_COUNT, len)
+            }
+            FaceletError::InvalidChar { position, found } => {
+                write!(f, "invalid facelet '{}' at position {}", found, position)
+            }
+            FaceletError::WrongCount(face, count) => {
+                write!(
+                    f,
+                    "expected 9 '{}' facelets, found {}",
+                    face.to_char(),
+                    count
+                )
+            }
+            FaceletError::MisplacedCenter(face) => {
+                write!(
+                    f,
+                    "center of face {} is not '{}'",
+                    face.to_char(),
+                    face.to_char()
+                )
+            }
+            FaceletError::Cubie(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for FaceletError {}
+
+impl From<CubieError> for FaceletError {
+    fn from(err: CubieError) -> Self {
+        FaceletError::Cubie(err)
+    }
+}
+
+/// 解析URFDLB顺序的54个字符，并检查是否为可还原的状态，忽略空白字符；
+/// 错误位置为字符在原始输入中的位置
+pub fn parse_facelet_string(text: &str) -> Result<CubieCube, FaceletError> {
+    let chars: Vec<(usize, char)> = text
+        .chars()
+        .enumerate()
+        .filter(|(_, c)| !c.is_whitespace())
+        .collect();
+    if chars.len() != FACELET_COUNT {
+        return Err(FaceletError::WrongLength(chars.len()));
+    }
+    let facelets = chars
+        .iter()
+        .map(|&(position, c)| {
+            Face::from_char(c.to_ascii_uppercase())
+                .ok_or(FaceletError::InvalidChar { position, found: c })
+        })
+        .collect::<Result<Vec<Face>, _>>()?;
+    for face in Face::ALL {
+        let count = facelets.iter().filter(|&&f| f == face).count();
+        if count != 9 {
+            return Err(FaceletError::WrongCount(face, count));
+        }
+        if facelets[face as usize * 9 + 4] != face {
+            return Err(FaceletError::MisplacedCenter(face));
+        }
+    }
+    let cube = CubieCube::from_facelets(&facelets)?;
+    cube.verify()?;
+    Ok(cube)
+}
+
+/// 由facelet字符串得到3阶逻辑魔方，直接由角块和棱块构造
+pub fn cube_state_from_facelets(text: &str) -> Result<CubeState, FaceletError> {
+    Ok(parse_facelet_string(text)?.to_cube_state())
+}
+
+/// 导出3阶魔方的facelet字符串，按中心块重新标记，整体转动后仍为标准格式
+pub fn facelet_string(state: &CubeState) -> Option<String> {
+    if state.order() != 3 {
+        return None;
+    }
+    state
+        .oriented_facelets()
+        .map(|facelets| facelets.into_iter().map(Face::to_char).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_side_moves;
+
+    const SOLVED: &str = "UUUUUUUUURRRRRRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB";
+
+    fn scrambled(algorithm: &str) -> CubeState {
+        let mut state = CubeState::solved(3);
+        for event in parse_side_moves(algorithm, 3).unwrap().iter().flatten() {
+            state.apply_side_move(event);
+        }
+        state
+    }
+
+    #[test]
+    fn rejects_invalid_facelets() {
+        assert_eq!(
+            parse_facelet_string("UUU").unwrap_err(),
+            FaceletError::WrongLength(3)
+        );
+        let invalid = SOLVED.replacen('R', "X", 1);
+        assert_eq!(
+            parse_facelet_string(&invalid).unwrap_err(),
+            FaceletError::InvalidChar {
+                position: 9,
+                found: 'X'
+            }
+        );
+        // 位置按原始输入计算，包括空白字符
+        let spaced = format!("UUU UUU UUU {}", &invalid[9..]);
+        assert_eq!(
+            parse_facelet_string(&spaced).unwrap_err(),
+            FaceletError::InvalidChar {
+                position: 12,
+                found: 'X'
+            }
+        );
+        let wrong_count = SOLVED.replacen('R', "U", 1);
+        assert_eq!(
+            parse_facelet_string(&wrong_count).unwrap_err(),
+            FaceletError::WrongCount(Face::U, 10)
+        );
+        // 交换U面和R面的中心
+        let mut centers: Vec<char> = SOLVED.chars().collect();
+        centers.swap(4, 13);
+        let centers: String = centers.into_iter().collect();
+        assert_eq!(
+            parse_facelet_string(&centers).unwrap_err(),
+            FaceletError::MisplacedCenter(Face::U)
+        );
+        // 翻转UF棱块的两个贴纸
+        let mut flipped: Vec<char> = scrambled("R U").facelet_string().chars().collect();
+        let (up, front) = (7, 19);
+        flipped.swap(up, front);
+        let flipped: String = flipped.into_iter().collect();
+        assert_eq!(
+            parse_facelet_string(&flipped).unwrap_err(),
+            FaceletError::Cubie(CubieError::FlippedEdge)
+        );
+    }
+
+    #[test]
+    fn imports_what_it_exports() {
+        let state = scrambled("R U2 F' L D B2 x");
+        let text = facelet_string(&state).unwrap();
+        assert_eq!(text.len(), FACELET_COUNT);
+        let imported = cube_state_from_facelets(&text).unwrap();
+        assert_eq!(facelet_string(&imported).unwrap(), text);
+        assert!(imported.is_valid());
+        // 直接由块构造的状态与执行转动得到的状态一致
+        let state = scrambled("R U2 F' L D B2");
+        let imported = cube_state_from_facelets(&state.facelet_string()).unwrap();
+        assert_eq!(imported.facelets(), state.facelets());
+        assert!(cube_state_from_facelets(SOLVED).unwrap().is_solved());
+        // 导出时按中心块重新标记，整体转动不影响
+        assert_eq!(facelet_string(&scrambled("x y")).unwrap(), SOLVED);
+        // 解析时忽略空白和大小写
+        let spaced = SOLVED.to_lowercase().replace("rrr", "rrr\n");
+        assert!(parse_facelet_string(&spaced).is_ok());
+        assert_eq!(facelet_string(&CubeState::solved(4)), None);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::cube::CubeSettings;
+use crate::move_history::MoveHistory;
+use crate::puzzle::{ActiveMove, MoveQueue, MoveSource, Puzzle};
+use crate::timekeeping::TimekeepingTimer;
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+// 撤销上一次转动
+#[derive(Debug, Default, Event)]
+pub struct UndoEvent;
+
+// 重做上一次撤销的转动
+#[derive(Debug, Default, Event)]
+pub struct RedoEvent;
+
+// Ctrl+Z撤销，Ctrl+Y或Ctrl+Shift+Z重做
+pub fn undo_redo_shortcuts(
+    mut egui_context: EguiContexts,
+    keys: Res<ButtonInput<KeyCode>>,
+    cube_settings: Res<CubeSettings>,
+    timekeeping_timer: Res<TimekeepingTimer>,
+    mut undo_event: EventWriter<UndoEvent>,
+    mut redo_event: EventWriter<RedoEvent>,
+) {
+    // 输入框中的快捷键交给egui处理
+    if egui_context.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    // 与撤销按钮相同，计时中不能撤销
+    if timekeeping_timer.is_running(cube_settings.play_mode) {
+        return;
+    }
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keys.just_pressed(KeyCode::KeyY) || (shift && keys.just_pressed(KeyCode::KeyZ)) {
+        redo_event.send_default();
+    } else if keys.just_pressed(KeyCode::KeyZ) {
+        undo_event.send_default();
+    }
+}
+
+pub fn undo_redo_moves<P: Puzzle>(
+    mut undo_events: EventReader<UndoEvent>,
+    mut redo_events: EventReader<RedoEvent>,
+    mut history: ResMut<MoveHistory<P::Move>>,
+    mut queue: ResMut<MoveQueue<P>>,
+    active: Res<ActiveMove<P>>,
+) {
+    // 等待转动完成后再撤销或重做，保证历史记录与谜题状态一致
+    if !queue.0.is_empty() || active.0.is_some() {
+        undo_events.clear();
+        redo_events.clear();
+        return;
+    }
+    for _ in undo_events.read() {
+        if let Some(moves) = history.undo() {
+            queue.push_moves(moves, MoveSource::History);
+        }
+    }
+    for _ in redo_events.read() {
+        if let Some(m) = history.redo() {
+            queue.push_moves([m], MoveSource::History);
+        }
+    }
+}
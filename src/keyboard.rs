@@ -0,0 +1,163 @@
+use crate::cube::{CubePuzzle, CubeSettings, PuzzleKind};
+use crate::notation::parse_side_moves;
+use crate::puzzle::{MoveQueue, MoveSource};
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyScheme {
+    // 按键即面名，Shift为逆时针
+    Plain,
+    // csTimer的双手键位
+    CsTimer,
+}
+
+/// 一个按键绑定，按下后执行对应的公式
+#[derive(Debug, Clone)]
+pub struct KeyBinding {
+    pub key: KeyCode,
+    pub shift: bool,
+    pub algorithm: String,
+}
+
+impl KeyBinding {
+    pub fn new(key: KeyCode, shift: bool, algorithm: &str) -> Self {
+        Self {
+            key,
+            shift,
+            algorithm: algorithm.to_string(),
+        }
+    }
+}
+
+/// 各键位方案的按键绑定表
+#[derive(Debug, Resource)]
+pub struct KeyBindings {
+    pub plain: Vec<KeyBinding>,
+    pub cstimer: Vec<KeyBinding>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            plain: plain_bindings(),
+            cstimer: cstimer_bindings(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn get(&self, scheme: KeyScheme) -> &[KeyBinding] {
+        match scheme {
+            KeyScheme::Plain => &self.plain,
+            KeyScheme::CsTimer => &self.cstimer,
+        }
+    }
+}
+
+fn plain_bindings() -> Vec<KeyBinding> {
+    let keys = [
+        (KeyCode::KeyR, "R"),
+        (KeyCode::KeyU, "U"),
+        (KeyCode::KeyF, "F"),
+        (KeyCode::KeyL, "L"),
+        (KeyCode::KeyD, "D"),
+        (KeyCode::KeyB, "B"),
+        (KeyCode::KeyM, "M"),
+        (KeyCode::KeyE, "E"),
+        (KeyCode::KeyS, "S"),
+        (KeyCode::KeyX, "x"),
+        (KeyCode::KeyY, "y"),
+        (KeyCode::KeyZ, "z"),
+    ];
+    keys.into_iter()
+        .flat_map(|(key, algorithm)| {
+            [
+                KeyBinding::new(key, false, algorithm),
+                KeyBinding::new(key, true, &format!("{}'", algorithm)),
+            ]
+        })
+        .collect()
+}
+
+fn cstimer_bindings() -> Vec<KeyBinding> {
+    [
+        (KeyCode::KeyI, "R"),
+        (KeyCode::KeyK, "R'"),
+        (KeyCode::KeyD, "L"),
+        (KeyCode::KeyE, "L'"),
+        (KeyCode::KeyJ, "U"),
+        (KeyCode::KeyF, "U'"),
+        (KeyCode::KeyS, "D"),
+        (KeyCode::KeyL, "D'"),
+        (KeyCode::KeyH, "F"),
+        (KeyCode::KeyG, "F'"),
+        (KeyCode::KeyW, "B"),
+        (KeyCode::KeyO, "B'"),
+        (KeyCode::KeyU, "r"),
+        (KeyCode::KeyM, "r'"),
+        (KeyCode::KeyV, "l"),
+        (KeyCode::KeyR, "l'"),
+        (KeyCode::Digit5, "M"),
+        (KeyCode::Digit6, "M"),
+        (KeyCode::KeyX, "M'"),
+        (KeyCode::Period, "M'"),
+        (KeyCode::KeyT, "x"),
+        (KeyCode::KeyY, "x"),
+        (KeyCode::KeyB, "x'"),
+        (KeyCode::KeyN, "x'"),
+        (KeyCode::Semicolon, "y"),
+        (KeyCode::KeyA, "y'"),
+        (KeyCode::KeyP, "z"),
+        (KeyCode::KeyQ, "z'"),
+    ]
+    .into_iter()
+    .map(|(key, algorithm)| KeyBinding::new(key, false, algorithm))
+    .collect()
+}
+
+// 按键转动魔方
+pub fn keyboard_moves(
+    mut egui_context: EguiContexts,
+    keys: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    cube_settings: Res<CubeSettings>,
+    mut side_move_queue: ResMut<MoveQueue<CubePuzzle>>,
+) {
+    // 其他谜题使用各自的键位，粘连魔方与三阶魔方相同
+    if !matches!(
+        cube_settings.puzzle,
+        PuzzleKind::Cube | PuzzleKind::Bandaged
+    ) {
+        return;
+    }
+    // 正在输入公式
+    if egui_context.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    // Ctrl、Alt组合键留给快捷键
+    if keys.any_pressed([
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::AltLeft,
+        KeyCode::AltRight,
+    ]) {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let order = cube_settings.dims()[0];
+    for key in keys.get_just_pressed() {
+        let Some(binding) = key_bindings
+            .get(cube_settings.key_scheme)
+            .iter()
+            .find(|binding| binding.key == *key && binding.shift == shift)
+        else {
+            continue;
+        };
+        // 当前阶数不支持的转动（如2阶的M）直接忽略
+        match parse_side_moves(&binding.algorithm, order) {
+            Ok(moves) => side_move_queue.push_moves(moves, MoveSource::Player),
+            Err(err) => info!("ignore key binding {}: {}", binding.algorithm, err),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+#[cfg(feature = "gui")]
+pub mod bandage;
+#[cfg(feature = "gui")]
+pub mod camera;
+#[cfg(feature = "gui")]
+pub mod clock;
+#[cfg(feature = "gui")]
+pub mod color_scheme;
+#[cfg(feature = "gui")]
+pub mod cube;
+pub mod cubie;
+#[cfg(feature = "gui")]
+pub mod custom;
+pub mod facelet;
+#[cfg(feature = "gui")]
+pub mod history;
+#[cfg(feature = "gui")]
+pub mod keyboard;
+#[cfg(feature = "gui")]
+pub mod megaminx;
+pub mod move_history;
+#[cfg(feature = "gui")]
+pub mod moving;
+#[cfg(feature = "gui")]
+pub mod net;
+pub mod notation;
+#[cfg(feature = "gui")]
+pub mod pattern;
+#[cfg(feature = "gui")]
+pub mod puzzle;
+#[cfg(feature = "gui")]
+pub mod pyraminx;
+pub mod pyraminx_solver;
+#[cfg(feature = "gui")]
+pub mod save;
+pub mod save_data;
+pub mod scramble;
+#[cfg(feature = "gui")]
+pub mod session;
+#[cfg(feature = "gui")]
+pub mod skewb;
+pub mod skewb_solver;
+pub mod solver;
+#[cfg(feature = "gui")]
+pub mod square1;
+pub mod state;
+pub mod stats;
+#[cfg(feature = "gui")]
+pub mod timekeeping;
+#[cfg(feature = "gui")]
+pub mod twisty;
+#[cfg(feature = "gui")]
+pub mod ui;
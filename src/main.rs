@@ -1,51 +1,123 @@
 use bevy::prelude::*;
 use bevy::transform::TransformSystem;
-use bevy::utils::Instant;
 use bevy_egui::EguiPlugin;
-use std::collections::VecDeque;
 
-use camera::*;
-use cube::*;
-use moving::*;
-use ui::*;
-
-mod camera;
-mod cube;
-mod moving;
-mod ui;
+use rubiks_cube::bandage::{
+    bandage_ui, highlight_bandaged_pieces, setup_bandages, BandageConfig, BandageEditor,
+    BlockedTurn,
+};
+use rubiks_cube::camera::*;
+use rubiks_cube::clock::{choose_clock_move, clock_ui, reset_clock, ClockPuzzle, ClockView};
+use rubiks_cube::color_scheme::*;
+use rubiks_cube::cube::*;
+use rubiks_cube::custom::{setup_custom_puzzle, CustomPuzzleInput};
+use rubiks_cube::history::*;
+use rubiks_cube::keyboard::*;
+use rubiks_cube::moving::*;
+use rubiks_cube::net::*;
+use rubiks_cube::pattern::*;
+use rubiks_cube::puzzle::{PuzzlePlugin, PuzzleSystems};
+use rubiks_cube::save::*;
+use rubiks_cube::session::*;
+use rubiks_cube::square1::{choose_square1_pieces, reset_square1, Square1Puzzle};
+use rubiks_cube::timekeeping::*;
+use rubiks_cube::twisty::*;
+use rubiks_cube::ui::*;
 
 fn main() {
     App::new()
         .add_plugins((DefaultPlugins, MeshPickingPlugin))
         .add_plugins(EguiPlugin)
+        .add_plugins((
+            PuzzlePlugin::<CubePuzzle>::default(),
+            PuzzlePlugin::<TwistyPuzzle>::default(),
+            PuzzlePlugin::<Square1Puzzle>::default(),
+            PuzzlePlugin::<ClockPuzzle>::default(),
+        ))
         .insert_resource(MeshPickingSettings {
             require_markers: true,
             ..Default::default()
         })
-        .add_systems(Startup, (setup_camera, setup_cube))
+        .add_systems(
+            Startup,
+            (
+                setup_camera,
+                // 先读取保存的配色再生成魔方
+                setup_color_scheme.before(setup_cube),
+                setup_bandages.before(setup_cube),
+                setup_cube,
+                warm_up_solver,
+                setup_pattern_library,
+                setup_custom_puzzle,
+            ),
+        )
         .insert_resource(CubeSettings::default())
-        .insert_resource(SideMoveQueue(VecDeque::new()))
+        .init_resource::<KeyBindings>()
+        .init_resource::<Session>()
+        .init_resource::<SaveStatus>()
+        .init_resource::<PendingLoad>()
         .insert_resource(MouseDraggingRecorder {
             start_pos: None,
             piece: None,
             triggered: false,
         })
-        .insert_resource(TimekeepingTimer(Instant::now()))
+        .init_resource::<TimekeepingTimer>()
+        .init_resource::<CompletedSolve>()
+        .init_resource::<FaceletInput>()
+        .init_resource::<NetView>()
+        .init_resource::<PatternLibrary>()
+        .init_resource::<PatternInput>()
+        .init_resource::<ScrambleSeedInput>()
+        .init_resource::<CustomPuzzleInput>()
+        .init_resource::<BandageConfig>()
+        .init_resource::<BandageEditor>()
+        .init_resource::<BlockedTurn>()
+        .init_resource::<ClockView>()
         .register_type::<Piece>()
-        .add_event::<ScrambleEvent>()
-        .add_event::<ResetEvent>()
-        .add_systems(PreUpdate, (choose_movable_pieces,))
+        .add_event::<SaveEvent>()
+        .add_event::<LoadEvent>()
+        .add_systems(
+            PreUpdate,
+            (
+                choose_movable_pieces,
+                choose_twisting_pieces,
+                choose_square1_pieces,
+                choose_clock_move,
+            ),
+        )
         .add_systems(
             Update,
             (
                 rotate_cube,
                 game_ui,
-                scramble_cube,
-                reset_cube,
+                update_sticker_colors.after(game_ui),
+                completion_panel,
+                session_ui,
+                net_view_ui,
+                clock_ui,
+                bandage_ui,
+                highlight_bandaged_pieces,
+                // 先清空队列并重置魔方，再开始花样的转动
+                pattern_ui.before(PuzzleSystems::Reset),
+                load_cube.in_set(PuzzleSystems::Prepare),
+                (reset_cube, reset_twisty, reset_square1, reset_clock).in_set(PuzzleSystems::Reset),
+                (finish_solve, update_attempt)
+                    .chain()
+                    .after(PuzzleSystems::Track),
+                save_cube,
+                undo_redo_shortcuts,
+                keyboard_moves,
+                twisty_keyboard_moves,
                 zoom_camera,
                 move_camera,
+                fit_camera_to_cube,
             ),
         )
+        // 魔方在Update中重建，下一个schedule才能查询到新的块
+        .add_systems(
+            PostUpdate,
+            apply_loaded_cube.before(TransformSystem::TransformPropagate),
+        )
         .add_systems(
             PostUpdate,
             ((
@@ -0,0 +1,306 @@
+use crate::color_scheme::ColorScheme;
+use crate::notation::{NotationError, NotationErrorKind};
+use crate::twisty::{Twist, TwistAxis, Twisty, TwistyDef};
+use bevy::color::palettes;
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::fmt;
+
+// 各面到中心的距离
+const INRADIUS: f32 = 1.8;
+// 面转动的切割面到中心的距离，决定角块、棱块的大小
+const FACE_CUT_DEPTH: f32 = INRADIUS * 0.8;
+
+// 面名称，下标即面编号：U，上层一圈F R BR BL L，D，下层一圈B DBL DL DR DBR（依次与上层相对）
+pub const FACE_NAMES: [&str; 12] = [
+    "U", "F", "R", "BR", "BL", "L", "D", "B", "DBL", "DL", "DR", "DBR",
+];
+
+// 每个轴上的切割面：面转动只转该面一层，宽层转动转除相对面以外的所有块
+pub const FACE_CUT: usize = 0;
+pub const WIDE_CUT: usize = 1;
+
+// Pochmann打乱：7行，每行10次R、D交替的宽层转动，最后转U
+const SCRAMBLE_LINES: usize = 7;
+const SCRAMBLE_LINE_LENGTH: usize = 10;
+
+const U_FACE: usize = 0;
+const R_FACE: usize = 2;
+const D_FACE: usize = 6;
+
+/// 第face个面的外法向量：U朝上，F朝前，上层一圈从F开始经R向后排列
+pub fn normal(face: usize) -> Vec3 {
+    let (y, azimuth) = match face {
+        0 => return Vec3::Y,
+        6 => return Vec3::NEG_Y,
+        1..=5 => (1.0, (face - 1) as f32 * 72.0),
+        // 下层的面与上层相对
+        _ => (-1.0, (face - 7) as f32 * 72.0 + 180.0),
+    };
+    let azimuth = azimuth.to_radians();
+    Vec3::new(2.0 * azimuth.sin(), y, 2.0 * azimuth.cos()) / 5f32.sqrt()
+}
+
+/// 五魔方的几何定义
+pub fn definition() -> TwistyDef {
+    TwistyDef {
+        faces: (0..12).map(|face| (normal(face), INRADIUS)).collect(),
+        colors: vec![
+            palettes::css::WHITE.into(),
+            palettes::css::GREEN.into(),
+            palettes::css::RED.into(),
+            palettes::css::BLUE.into(),
+            palettes::css::YELLOW.into(),
+            palettes::css::PURPLE.into(),
+            palettes::css::GRAY.into(),
+            palettes::css::HOT_PINK.into(),
+            palettes::css::ORANGE.into(),
+            palettes::css::LIGHT_SKY_BLUE.into(),
+            palettes::css::KHAKI.into(),
+            palettes::css::LIME.into(),
+        ],
+        axes: (0..12)
+            .map(|face| TwistAxis {
+                normal: normal(face),
+                order: 5,
+                cuts: vec![FACE_CUT_DEPTH, -FACE_CUT_DEPTH],
+            })
+            .collect(),
+    }
+}
+
+/// 五魔方的一步转动，amount为从该面看顺时针转动的五分之一圈数，负数为逆时针；
+/// 宽层转动记为`R++`、`D--`，转动两个五分之一圈
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MegaminxMove {
+    pub face: usize,
+    pub wide: bool,
+    pub amount: i32,
+}
+
+impl MegaminxMove {
+    pub fn to_twist(self) -> Twist {
+        Twist {
+            axis: self.face,
+            cut: if self.wide { WIDE_CUT } else { FACE_CUT },
+            amount: self.amount,
+        }
+    }
+}
+
+impl fmt::Display for MegaminxMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", FACE_NAMES[self.face])?;
+        if self.wide {
+            let suffix = if self.amount > 0 { "+" } else { "-" };
+            return write!(f, "{}", suffix.repeat(self.amount.unsigned_abs() as usize));
+        }
+        if self.amount.abs() == 2 {
+            write!(f, "2")?;
+        }
+        if self.amount < 0 {
+            write!(f, "'")?;
+        }
+        Ok(())
+    }
+}
+
+/// 解析如`R++ D-- U' F2 DBR`的公式，面名称取最长匹配
+pub fn parse_algorithm(input: &str) -> Result<Vec<MegaminxMove>, NotationError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut moves = Vec::new();
+    let mut pos = 0;
+    while let Some(&c) = chars.get(pos) {
+        if c.is_whitespace() || c == ',' {
+            pos += 1;
+            continue;
+        }
+        let Some(face) = (0..FACE_NAMES.len())
+            .filter(|&face| {
+                let name: Vec<char> = FACE_NAMES[face].chars().collect();
+                chars[pos..].starts_with(&name)
+            })
+            .max_by_key(|&face| FACE_NAMES[face].len())
+        else {
+            return Err(NotationError {
+                position: pos,
+                kind: NotationErrorKind::UnexpectedChar(c),
+            });
+        };
+        pos += FACE_NAMES[face].len();
+        let mut m = MegaminxMove {
+            face,
+            wide: false,
+            amount: 1,
+        };
+        match chars.get(pos) {
+            Some(&sign @ ('+' | '-')) => {
+                // 宽层转动固定为两个五分之一圈
+                if chars.get(pos + 1) != Some(&sign) {
+                    return Err(NotationError {
+                        position: pos + 1,
+                        kind: match chars.get(pos + 1) {
+                            Some(&c) => NotationErrorKind::UnexpectedChar(c),
+                            None => NotationErrorKind::UnexpectedEnd,
+                        },
+                    });
+                }
+                pos += 2;
+                m.wide = true;
+                m.amount = if sign == '+' { 2 } else { -2 };
+            }
+            _ => {
+                if chars.get(pos) == Some(&'2') {
+                    pos += 1;
+                    m.amount = 2;
+                }
+                if chars.get(pos) == Some(&'\'') {
+                    pos += 1;
+                    m.amount = -m.amount;
+                }
+            }
+        }
+        moves.push(m);
+    }
+    Ok(moves)
+}
+
+pub fn format_algorithm(moves: &[MegaminxMove]) -> String {
+    moves
+        .iter()
+        .map(|m| m.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// WCA使用的Pochmann打乱，每行以U结束，U的方向与该行最后一次D相同
+pub fn scramble<R: Rng>(rng: &mut R) -> Vec<Vec<MegaminxMove>> {
+    (0..SCRAMBLE_LINES)
+        .map(|_| {
+            let mut line: Vec<MegaminxMove> = (0..SCRAMBLE_LINE_LENGTH)
+                .map(|i| MegaminxMove {
+                    face: if i % 2 == 0 { R_FACE } else { D_FACE },
+                    wide: true,
+                    amount: if rng.gen() { 2 } else { -2 },
+                })
+                .collect();
+            line.push(MegaminxMove {
+                face: U_FACE,
+                wide: false,
+                amount: line[SCRAMBLE_LINE_LENGTH - 1].amount.signum(),
+            });
+            line
+        })
+        .collect()
+}
+
+pub struct Megaminx;
+
+// 面太多，不提供按键绑定，只支持拖动和公式
+impl Twisty for Megaminx {
+    type State = ();
+    type Move = MegaminxMove;
+
+    fn definition(&self, _colors: &ColorScheme) -> TwistyDef {
+        definition()
+    }
+
+    fn to_twist(&self, m: MegaminxMove) -> Twist {
+        m.to_twist()
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<MegaminxMove>, NotationError> {
+        parse_algorithm(input)
+    }
+
+    fn scramble(&self, rng: &mut StdRng) -> Vec<MegaminxMove> {
+        scramble(rng).into_iter().flatten().collect()
+    }
+
+    // 每行单独显示
+    fn format_scramble(&self, moves: &[MegaminxMove]) -> String {
+        moves
+            .chunks(SCRAMBLE_LINE_LENGTH + 1)
+            .map(format_algorithm)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::twisty::TwistyModel;
+    use rand::SeedableRng;
+
+    #[test]
+    fn parses_and_formats_moves() {
+        let input = "R++ D-- U' F2 DBR BL2' DL";
+        let moves = parse_algorithm(input).unwrap();
+        assert_eq!(
+            moves[0],
+            MegaminxMove {
+                face: R_FACE,
+                wide: true,
+                amount: 2
+            }
+        );
+        // 面名称取最长匹配，DBR不会被读成D
+        assert_eq!(moves[4].face, 11);
+        assert_eq!(moves[5].amount, -2);
+        assert_eq!(format_algorithm(&moves), input);
+        assert_eq!(parse_algorithm(&format_algorithm(&moves)).unwrap(), moves);
+
+        let err = parse_algorithm("R+ D").unwrap_err();
+        assert_eq!(err.position, 2);
+        let err = parse_algorithm("U X").unwrap_err();
+        assert_eq!(err.position, 2);
+    }
+
+    #[test]
+    fn scramble_follows_pochmann_structure() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let lines = scramble(&mut rng);
+        assert_eq!(lines.len(), SCRAMBLE_LINES);
+        for line in &lines {
+            assert_eq!(line.len(), SCRAMBLE_LINE_LENGTH + 1);
+            for (i, m) in line[..SCRAMBLE_LINE_LENGTH].iter().enumerate() {
+                assert_eq!(m.face, if i % 2 == 0 { R_FACE } else { D_FACE });
+                assert!(m.wide);
+                assert_eq!(m.amount.abs(), 2);
+            }
+            let last = line[SCRAMBLE_LINE_LENGTH];
+            assert_eq!(last.face, U_FACE);
+            assert!(!last.wide);
+            assert_eq!(last.amount, line[SCRAMBLE_LINE_LENGTH - 1].amount.signum());
+        }
+
+        // 显示时每行一个
+        let moves: Vec<MegaminxMove> = lines.into_iter().flatten().collect();
+        let text = Megaminx.format_scramble(&moves);
+        assert_eq!(text.lines().count(), SCRAMBLE_LINES);
+        assert!(text
+            .lines()
+            .all(|line| line.ends_with('U') || line.ends_with("U'")));
+    }
+
+    #[test]
+    fn inverse_moves_return_to_solved() {
+        let model = TwistyModel::new(&definition()).unwrap();
+        for face in 0..FACE_NAMES.len() {
+            for (wide, amount) in [(false, 1), (false, 2), (false, -1), (true, 2), (true, -2)] {
+                let m = MegaminxMove { face, wide, amount };
+                let mut stickers = model.solved_state();
+                model.apply(&mut stickers, m.to_twist());
+                assert!(!model.is_solved(&stickers), "{m}");
+                let inverse = MegaminxMove {
+                    amount: -amount,
+                    ..m
+                };
+                model.apply(&mut stickers, inverse.to_twist());
+                assert!(model.is_solved(&stickers), "{m}");
+            }
+        }
+    }
+}
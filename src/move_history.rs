@@ -0,0 +1,99 @@
+use crate::state::SideMoveEvent;
+#[cfg(feature = "gui")]
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+/// 历史中的一步：完成的转动，以及撤销时依次执行的转动
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct HistoryStep<M> {
+    done: M,
+    undo: Vec<M>,
+}
+
+/// 转动历史，记录已完成的转动，用于撤销和重做。
+/// 撤销的转动在记录时由`Puzzle::invert`算好，如魔表撤销按WCA记号的转动时还要恢复原来的针
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "gui", derive(Resource))]
+pub struct MoveHistory<M = Vec<SideMoveEvent>> {
+    done: Vec<HistoryStep<M>>,
+    undone: Vec<HistoryStep<M>>,
+}
+
+impl<M> Default for MoveHistory<M> {
+    fn default() -> Self {
+        Self {
+            done: Vec::new(),
+            undone: Vec::new(),
+        }
+    }
+}
+
+impl<M: Clone> MoveHistory<M> {
+    // 记录一次完成的转动，新的转动会清空重做记录；撤销、重做的转动在加入队列时已经更新历史，不再记录
+    pub fn record(&mut self, done: M, undo: Vec<M>) {
+        self.done.push(HistoryStep { done, undo });
+        self.undone.clear();
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    // 所有撤销和重做时可能执行的转动
+    pub fn moves(&self) -> impl Iterator<Item = &M> {
+        self.done
+            .iter()
+            .chain(&self.undone)
+            .flat_map(|step| std::iter::once(&step.done).chain(&step.undo))
+    }
+
+    // 返回需要依次执行的逆向转动
+    pub fn undo(&mut self) -> Option<Vec<M>> {
+        let step = self.done.pop()?;
+        let undo = step.undo.clone();
+        self.undone.push(step);
+        Some(undo)
+    }
+
+    // 返回需要重新执行的转动
+    pub fn redo(&mut self) -> Option<M> {
+        let step = self.undone.pop()?;
+        let done = step.done.clone();
+        self.done.push(step);
+        Some(done)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_side_moves;
+
+    #[test]
+    fn undo_and_redo_whole_moves() {
+        let mut history = MoveHistory::default();
+        let moves = parse_side_moves("R Rw", 3).unwrap();
+        let inverses = parse_side_moves("R' Rw'", 3).unwrap();
+        for (layers, inverse) in moves.iter().zip(&inverses) {
+            history.record(layers.clone(), vec![inverse.clone()]);
+        }
+        assert_eq!(history.undo(), Some(vec![inverses[1].clone()]));
+        assert!(history.can_redo());
+        assert_eq!(history.redo(), Some(moves[1].clone()));
+        assert!(!history.can_redo());
+        assert_eq!(history.moves().count(), 4);
+
+        // 新的转动清空重做记录
+        history.undo();
+        history.record(moves[1].clone(), vec![inverses[1].clone()]);
+        assert!(!history.can_redo());
+    }
+}
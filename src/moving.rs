@@ -1,35 +1,20 @@
+use crate::bandage::*;
 use crate::cube::*;
+use crate::puzzle::{ActiveMove, MoveEvent, MoveQueue, MoveSource};
+use crate::state::{Axis, SideMoveEvent, SideRotation};
 use bevy::prelude::*;
-use std::collections::VecDeque;
-use std::f32::consts::FRAC_PI_2;
-use std::f32::consts::PI;
 use std::f32::consts::TAU;
 
-#[derive(Debug, Clone, Copy)]
-pub enum SideRotation {
-    Clockwise90,
-    Clockwise180,
-    Counterclockwise90,
-}
-
-#[derive(Debug, Clone, Copy)]
-pub enum Axis {
-    X,
-    Y,
-    Z,
-}
-
-#[derive(Debug, Clone, Copy, Event)]
-pub struct SideMoveEvent {
-    // 旋转的面，对应固定的x/y/z坐标值
-    pub side: (Axis, f32),
-    // 旋转
-    pub rotate: SideRotation,
+impl Axis {
+    pub fn vector(self) -> Vec3 {
+        match self {
+            Axis::X => Vec3::X,
+            Axis::Y => Vec3::Y,
+            Axis::Z => Vec3::Z,
+        }
+    }
 }
 
-#[derive(Debug, Resource)]
-pub struct SideMoveQueue(pub VecDeque<SideMoveEvent>);
-
 #[derive(Debug, Resource)]
 pub struct MouseDraggingRecorder {
     pub start_pos: Option<Vec3>,
@@ -45,69 +30,63 @@ impl MouseDraggingRecorder {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn choose_movable_pieces(
     mut commands: Commands,
-    mut q_pieces: Query<(Entity, &Transform), With<Piece>>,
+    q_pieces: Query<(Entity, &Piece, &Transform)>,
     q_movable_pieces: Query<Entity, With<MovablePiece>>,
-    mut side_move_queue: ResMut<SideMoveQueue>,
+    mut side_move_queue: ResMut<MoveQueue<CubePuzzle>>,
+    mut active_side_moves: ResMut<ActiveMove<CubePuzzle>>,
+    puzzle: Option<Res<CubePuzzle>>,
+    mut blocked_turn: ResMut<BlockedTurn>,
 ) {
+    let Some(puzzle) = puzzle else {
+        return;
+    };
     if q_movable_pieces.is_empty() {
-        // 从SideMoveQueue消费一个
-        if side_move_queue.0.is_empty() {
-            return;
-        }
-        let event = side_move_queue.0.pop_front().unwrap();
-        dbg!(event);
-        for (_, transform) in &q_pieces {
-            info!(
-                "before choosing, cube translation={}",
-                transform.translation
+        // 粘连魔方不能拆开粘在一起的块：没有动画时块的位置是准确的，从这里依次检查整个队列，
+        // 只丢弃被挡住的转动，并闪烁提示挡住转动的块
+        if let Some(bandages) = &puzzle.bandages {
+            let positions = piece_positions(
+                q_pieces
+                    .iter()
+                    .map(|(_, piece, transform)| (piece, transform)),
             );
+            if let Some(blocked) = bandages.drop_blocked(&positions, &mut side_move_queue.0) {
+                blocked_turn.flash(blocked);
+            }
         }
-
-        let left_angle = match event.rotate {
-            SideRotation::Clockwise90 => FRAC_PI_2,
-            SideRotation::Clockwise180 => PI,
-            SideRotation::Counterclockwise90 => -FRAC_PI_2,
+        // 从队列消费一个
+        let Some(cube_move) = side_move_queue.0.pop_front() else {
+            return;
         };
+        // 同一步的所有层同轴同向，如整体转动x、宽层转动Rw
+        let Some(&event) = cube_move.0.first() else {
+            return;
+        };
+        // 长方体魔方非正方形的面转动90度会卡住
+        if !event.is_allowed(puzzle.dims) {
+            warn!("side move is not allowed on this shape: {:?}", event);
+            return;
+        }
+        let layers: Vec<f32> = cube_move.0.iter().map(|layer| layer.side.1).collect();
+        active_side_moves.0 = Some(cube_move);
 
-        let side = event.side;
-        match side.0 {
-            Axis::X => {
-                for (entity, transform) in &mut q_pieces {
-                    if transform.translation.x == side.1 {
-                        info!("insert movable cube: translation={}", transform.translation);
-                        commands.entity(entity).insert(MovablePiece {
-                            axis: Axis::X,
-                            rotate: event.rotate,
-                            left_angle,
-                        });
-                    }
-                }
-            }
-            Axis::Y => {
-                for (entity, transform) in &mut q_pieces {
-                    if transform.translation.y == side.1 {
-                        info!("insert movable cube: translation={}", transform.translation);
-                        commands.entity(entity).insert(MovablePiece {
-                            axis: Axis::Y,
-                            rotate: event.rotate,
-                            left_angle,
-                        });
-                    }
-                }
-            }
-            Axis::Z => {
-                for (entity, transform) in &mut q_pieces {
-                    if transform.translation.z == side.1 {
-                        info!("insert movable cube: translation={}", transform.translation);
-                        commands.entity(entity).insert(MovablePiece {
-                            axis: Axis::Z,
-                            rotate: event.rotate,
-                            left_angle,
-                        });
-                    }
-                }
+        let left_angle = event.rotate.angle();
+
+        let axis = event.side.0;
+        for (entity, _, transform) in &q_pieces {
+            let value = match axis {
+                Axis::X => transform.translation.x,
+                Axis::Y => transform.translation.y,
+                Axis::Z => transform.translation.z,
+            };
+            if layers.contains(&value) {
+                debug!("insert movable cube: translation={}", transform.translation);
+                commands.entity(entity).insert(MovablePiece {
+                    axis: axis.vector(),
+                    left_angle,
+                });
             }
         }
     }
@@ -116,21 +95,38 @@ pub fn choose_movable_pieces(
 pub fn cleanup_movable_pieces(
     mut commands: Commands,
     movable_pieces: Query<(Entity, &MovablePiece)>,
+    mut active_side_moves: ResMut<ActiveMove<CubePuzzle>>,
+    mut cube_move_events: EventWriter<MoveEvent<CubePuzzle>>,
 ) {
     for (entity, movable_piece) in &movable_pieces {
         if movable_piece.left_angle == 0.0 {
             commands.entity(entity).remove::<MovablePiece>();
         }
     }
+    // 所有块都转动完毕，通知逻辑魔方
+    if !movable_pieces.is_empty()
+        && movable_pieces
+            .iter()
+            .all(|(_, movable_piece)| movable_piece.left_angle == 0.0)
+    {
+        if let Some((layers, source)) = active_side_moves.0.take() {
+            cube_move_events.send(MoveEvent(layers, source));
+        }
+    }
+}
+
+// 层坐标为整数（奇数阶）或半整数（偶数阶）
+fn round_to_layer(value: f32) -> f32 {
+    (value * 2.0).round() / 2.0
 }
 
 // 纠正旋转后的坐标值误差
 pub fn piece_translation_round(mut movable_pieces: Query<(&mut Transform, &MovablePiece)>) {
     for (mut transform, movable_piece) in &mut movable_pieces {
         if movable_piece.left_angle == 0.0 {
-            transform.translation.x = transform.translation.x.round();
-            transform.translation.y = transform.translation.y.round();
-            transform.translation.z = transform.translation.z.round();
+            transform.translation.x = round_to_layer(transform.translation.x);
+            transform.translation.y = round_to_layer(transform.translation.y);
+            transform.translation.z = round_to_layer(transform.translation.z);
         }
     }
 }
@@ -145,38 +141,15 @@ pub fn rotate_cube(
         //     "rotate - movable cube={:?}, transform={}",
         //     &movable_piece, transform.translation
         // );
-        let axis = match movable_piece.axis {
-            Axis::X => Vec3::X,
-            Axis::Y => Vec3::Y,
-            Axis::Z => Vec3::Z,
-        };
-        let mut angle = match movable_piece.rotate {
-            SideRotation::Clockwise90 | SideRotation::Clockwise180 => {
-                cube_settings.rotate_speed * TAU * time.delta_secs()
-            }
-            SideRotation::Counterclockwise90 => {
-                -cube_settings.rotate_speed * TAU * time.delta_secs()
-            }
-        };
+        // 最后一帧只转剩余的角度，保证恰好转到位
+        let step = cube_settings.rotate_speed * TAU * time.delta_secs();
+        let angle = movable_piece.left_angle.clamp(-step, step);
 
-        let mut new_left_angle = movable_piece.left_angle - angle;
-        match movable_piece.rotate {
-            SideRotation::Clockwise90 | SideRotation::Clockwise180 => {
-                if new_left_angle < 0.0 {
-                    angle = movable_piece.left_angle;
-                    new_left_angle = 0.0;
-                }
-            }
-            SideRotation::Counterclockwise90 => {
-                if new_left_angle > 0.0 {
-                    angle = movable_piece.left_angle;
-                    new_left_angle = 0.0;
-                }
-            }
-        };
-
-        transform.rotate_around(Vec3::new(0.0, 0.0, 0.0), Quat::from_axis_angle(axis, angle));
-        movable_piece.left_angle = new_left_angle;
+        transform.rotate_around(
+            Vec3::new(0.0, 0.0, 0.0),
+            Quat::from_axis_angle(movable_piece.axis, angle),
+        );
+        movable_piece.left_angle -= angle;
     }
 }
 
@@ -187,32 +160,44 @@ pub fn handle_drag_start(
     // recorder开始记录
     info!("drag start event: {:?}", drag_start);
     let piece_entity = drag_start.target;
-    recorder.piece = Some(piece_entity.clone());
+    recorder.piece = Some(piece_entity);
     recorder.start_pos = drag_start.hit.position;
 
     info!("MouseDraggingRecorder started {:?}", recorder);
 }
 
-// 监测鼠标拖动距离，当鼠标拖动距离超过临界值时，触发一个面旋转
+// 监测鼠标拖动距离，当鼠标拖动距离超过临界值时，触发一个面旋转；
+// 粘连魔方的转动在开始执行前由choose_movable_pieces检查
 pub fn handle_move(
     pointer_move: Trigger<Pointer<Move>>,
     mut recorder: ResMut<MouseDraggingRecorder>,
-    mut side_move_queue: ResMut<SideMoveQueue>,
-    q_pieces: Query<&Transform, With<Piece>>,
+    mut side_move_queue: ResMut<MoveQueue<CubePuzzle>>,
+    q_pieces: Query<(&Piece, &Transform)>,
+    cube_settings: Res<CubeSettings>,
+    editor: Res<BandageEditor>,
 ) {
-    if pointer_move.event.hit.position.is_some() && recorder.start_pos.is_some() {
-        let start_pos = recorder.start_pos.unwrap();
-        let current_pos = pointer_move.event.hit.position.unwrap();
-
+    // 编辑粘连时点击块用于选择，不转动
+    if cube_settings.puzzle == PuzzleKind::Bandaged && editor.editing {
+        return;
+    }
+    if let (Some(current_pos), Some(start_pos)) =
+        (pointer_move.event.hit.position, recorder.start_pos)
+    {
         // 鼠标拽动距离超过临界值
         if start_pos.distance(current_pos) > 0.5 && !recorder.triggered {
             // 触发旋转
             info!("Trigger side move event, end_pos: {:?}", current_pos);
-            let translation = q_pieces.get(recorder.piece.unwrap()).unwrap().translation;
-            let event = gen_side_move_event(translation, recorder.start_pos.unwrap(), current_pos);
+            let translation = q_pieces.get(recorder.piece.unwrap()).unwrap().1.translation;
+            let dims = cube_settings.dims();
+            let half_size = Vec3::from_array(dims.map(|n| n as f32)) / 2.0;
+            let event = gen_side_move_event(translation, start_pos, current_pos, half_size);
             info!("gen event: {:?}", event);
-            if event.is_some() {
-                side_move_queue.0.push_back(event.unwrap());
+            if let Some(mut event) = event {
+                // 非正方形的面拖动时转动180度
+                if !event.is_allowed(dims) {
+                    event.rotate = SideRotation::Clockwise180;
+                }
+                side_move_queue.push_moves([vec![event]], MoveSource::Player);
             }
 
             // 更新recorder
@@ -225,16 +210,17 @@ pub fn handle_drag_end(
     _drag_end: Trigger<Pointer<DragEnd>>,
     mut recorder: ResMut<MouseDraggingRecorder>,
 ) {
-    println!("drag end event");
     recorder.clear();
 }
 
+// half_size为魔方各方向的表面到中心的距离
 fn gen_side_move_event(
     piece_translation: Vec3,
     start_pos: Vec3,
     end_pos: Vec3,
+    half_size: Vec3,
 ) -> Option<SideMoveEvent> {
-    if (start_pos.x.abs() - 1.5).abs() < 0.001 {
+    if (start_pos.x.abs() - half_size.x).abs() < 0.001 {
         let delta_y = end_pos.y - start_pos.y;
         let delta_z = end_pos.z - start_pos.z;
         if delta_y.abs() > delta_z.abs() {
@@ -248,18 +234,16 @@ fn gen_side_move_event(
                         SideRotation::Counterclockwise90
                     }
                 // 左面
+                } else if delta_y > 0.0 {
+                    SideRotation::Counterclockwise90
                 } else {
-                    if delta_y > 0.0 {
-                        SideRotation::Counterclockwise90
-                    } else {
-                        SideRotation::Clockwise90
-                    }
+                    SideRotation::Clockwise90
                 }
             };
-            return Some(SideMoveEvent {
-                side: (Axis::Z, piece_translation.z.round()),
+            Some(SideMoveEvent {
+                side: (Axis::Z, round_to_layer(piece_translation.z)),
                 rotate,
-            });
+            })
         } else {
             // z轴变化大，沿y轴旋转
             let rotate = {
@@ -271,20 +255,18 @@ fn gen_side_move_event(
                         SideRotation::Clockwise90
                     }
                 // 左面
+                } else if delta_z > 0.0 {
+                    SideRotation::Clockwise90
                 } else {
-                    if delta_z > 0.0 {
-                        SideRotation::Clockwise90
-                    } else {
-                        SideRotation::Counterclockwise90
-                    }
+                    SideRotation::Counterclockwise90
                 }
             };
-            return Some(SideMoveEvent {
-                side: (Axis::Y, piece_translation.y.round()),
+            Some(SideMoveEvent {
+                side: (Axis::Y, round_to_layer(piece_translation.y)),
                 rotate,
-            });
+            })
         }
-    } else if (start_pos.y.abs() - 1.5).abs() < 0.001 {
+    } else if (start_pos.y.abs() - half_size.y).abs() < 0.001 {
         let delta_x = end_pos.x - start_pos.x;
         let delta_z = end_pos.z - start_pos.z;
         if delta_x.abs() > delta_z.abs() {
@@ -298,18 +280,16 @@ fn gen_side_move_event(
                         SideRotation::Clockwise90
                     }
                 // 下面
+                } else if delta_x > 0.0 {
+                    SideRotation::Clockwise90
                 } else {
-                    if delta_x > 0.0 {
-                        SideRotation::Clockwise90
-                    } else {
-                        SideRotation::Counterclockwise90
-                    }
+                    SideRotation::Counterclockwise90
                 }
             };
-            return Some(SideMoveEvent {
-                side: (Axis::Z, piece_translation.z.round()),
+            Some(SideMoveEvent {
+                side: (Axis::Z, round_to_layer(piece_translation.z)),
                 rotate,
-            });
+            })
         } else {
             // z轴变化大，沿x轴旋转
             let rotate = {
@@ -321,18 +301,16 @@ fn gen_side_move_event(
                         SideRotation::Counterclockwise90
                     }
                 // 下面
+                } else if delta_z > 0.0 {
+                    SideRotation::Counterclockwise90
                 } else {
-                    if delta_z > 0.0 {
-                        SideRotation::Counterclockwise90
-                    } else {
-                        SideRotation::Clockwise90
-                    }
+                    SideRotation::Clockwise90
                 }
             };
-            return Some(SideMoveEvent {
-                side: (Axis::X, piece_translation.x.round()),
+            Some(SideMoveEvent {
+                side: (Axis::X, round_to_layer(piece_translation.x)),
                 rotate,
-            });
+            })
         }
     } else {
         let delta_x = end_pos.x - start_pos.x;
@@ -348,18 +326,16 @@ fn gen_side_move_event(
                         SideRotation::Counterclockwise90
                     }
                 // 后面
+                } else if delta_x > 0.0 {
+                    SideRotation::Counterclockwise90
                 } else {
-                    if delta_x > 0.0 {
-                        SideRotation::Counterclockwise90
-                    } else {
-                        SideRotation::Clockwise90
-                    }
+                    SideRotation::Clockwise90
                 }
             };
-            return Some(SideMoveEvent {
-                side: (Axis::Y, piece_translation.y.round()),
+            Some(SideMoveEvent {
+                side: (Axis::Y, round_to_layer(piece_translation.y)),
                 rotate,
-            });
+            })
         } else {
             // y轴变化大，沿x轴旋转
             let rotate = {
@@ -371,18 +347,16 @@ fn gen_side_move_event(
                         SideRotation::Clockwise90
                     }
                 // 后面
+                } else if delta_y > 0.0 {
+                    SideRotation::Clockwise90
                 } else {
-                    if delta_y > 0.0 {
-                        SideRotation::Clockwise90
-                    } else {
-                        SideRotation::Counterclockwise90
-                    }
+                    SideRotation::Counterclockwise90
                 }
             };
-            return Some(SideMoveEvent {
-                side: (Axis::X, piece_translation.x.round()),
+            Some(SideMoveEvent {
+                side: (Axis::X, round_to_layer(piece_translation.x)),
                 rotate,
-            });
+            })
         }
     }
 }
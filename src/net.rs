@@ -0,0 +1,116 @@
+use crate::cube::*;
+use crate::notation::{Move, MoveKind};
+use crate::puzzle::{MoveQueue, MoveSource, PuzzleState};
+use crate::state::Face;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+/// 魔方展开图面板
+#[derive(Debug, Default, Resource)]
+pub struct NetView {
+    pub open: bool,
+    // 点击选中的面
+    pub selected: Option<Face>,
+}
+
+// 十字展开图中各面的位置（以面为单位）：U在上，L F R B在中间一行，D在下
+fn face_offset(face: Face) -> (f32, f32) {
+    match face {
+        Face::U => (1.0, 0.0),
+        Face::L => (0.0, 1.0),
+        Face::F => (1.0, 1.0),
+        Face::R => (2.0, 1.0),
+        Face::B => (3.0, 1.0),
+        Face::D => (1.0, 2.0),
+    }
+}
+
+fn to_color32(color: Color) -> egui::Color32 {
+    let [r, g, b, a] = color.to_srgba().to_u8_array();
+    egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+// 展开图宽度
+const NET_WIDTH: f32 = 240.0;
+
+pub fn net_view_ui(
+    mut egui_context: EguiContexts,
+    mut net_view: ResMut<NetView>,
+    cube_state: Res<PuzzleState<CubePuzzle>>,
+    cube_settings: Res<CubeSettings>,
+    mut side_move_queue: ResMut<MoveQueue<CubePuzzle>>,
+) {
+    let Some(cube) = cube_state.0.as_ref() else {
+        return;
+    };
+    if !net_view.open || cube_settings.puzzle != PuzzleKind::Cube {
+        return;
+    }
+    let mut open = true;
+    egui::Window::new("Net")
+        .open(&mut open)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            let order = cube.order() as usize;
+            let face_size = NET_WIDTH / 4.0;
+            let sticker_size = face_size / order as f32;
+            let (response, painter) =
+                ui.allocate_painter(egui::vec2(NET_WIDTH, face_size * 3.0), egui::Sense::click());
+            let origin = response.rect.min;
+            let face_rect = |face: Face| {
+                let (x, y) = face_offset(face);
+                egui::Rect::from_min_size(
+                    origin + egui::vec2(x, y) * face_size,
+                    egui::Vec2::splat(face_size),
+                )
+            };
+
+            let facelets = cube.facelets();
+            for (face, stickers) in Face::ALL.into_iter().zip(facelets.chunks(order * order)) {
+                let min = face_rect(face).min;
+                for (i, sticker) in stickers.iter().enumerate() {
+                    let (row, col) = (i / order, i % order);
+                    let rect = egui::Rect::from_min_size(
+                        min + egui::vec2(col as f32, row as f32) * sticker_size,
+                        egui::Vec2::splat(sticker_size),
+                    )
+                    .shrink(1.0);
+                    painter.rect_filled(rect, 1.0, to_color32(cube_settings.face_color(*sticker)));
+                }
+            }
+            if let Some(face) = net_view.selected {
+                painter.rect_stroke(
+                    face_rect(face),
+                    2.0,
+                    egui::Stroke::new(2.0, egui::Color32::LIGHT_BLUE),
+                );
+            }
+
+            if response.clicked() {
+                if let Some(pos) = response.interact_pointer_pos() {
+                    net_view.selected = Face::ALL
+                        .into_iter()
+                        .find(|&face| face_rect(face).contains(pos));
+                }
+            }
+
+            // 转动选中的面
+            if let Some(face) = net_view.selected {
+                ui.horizontal(|ui| {
+                    for amount in [1, 3, 2] {
+                        let m = Move {
+                            kind: MoveKind::Layer(face, 1),
+                            amount,
+                        };
+                        if ui.button(m.to_string()).clicked() {
+                            side_move_queue
+                                .push_moves([m.to_side_moves(order as u8)], MoveSource::Player);
+                        }
+                    }
+                });
+            }
+        });
+    if !open {
+        net_view.open = false;
+    }
+}
@@ -0,0 +1,657 @@
+use crate::state::{layer_coords, quarter_turn_allowed, Axis, Face, SideMoveEvent, SideRotation};
+use std::fmt;
+
+// 括号重复次数和展开后步数的上限，避免(R)4000000000这类输入耗尽内存
+pub const MAX_GROUP_REPEAT: u32 = 100;
+pub const MAX_ALGORITHM_MOVES: usize = 10_000;
+// 括号嵌套层数上限，避免大量'('递归解析时栈溢出
+pub const MAX_GROUP_DEPTH: usize = 32;
+
+/// 转动的层
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveKind {
+    // 从面数起的第n层（1为外层），如R、2R
+    Layer(Face, u8),
+    // 从面数起的外侧n层，如Rw、3Rw，r等同于Rw
+    Wide(Face, u8),
+    // 中层M/E/S，方向分别跟随L/D/F，偶数阶为中间两层
+    Slice(Axis),
+    // 整体转动x/y/z，方向分别跟随R/U/F
+    Rotation(Axis),
+}
+
+/// 一步转动，amount为从对应面外部看顺时针转动的90度次数（1、2、3）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub kind: MoveKind,
+    pub amount: u8,
+}
+
+impl Move {
+    pub fn inverse(self) -> Self {
+        Self {
+            kind: self.kind,
+            amount: (4 - self.amount % 4) % 4,
+        }
+    }
+
+    pub fn to_side_moves(self, order: u8) -> Vec<SideMoveEvent> {
+        self.to_cuboid_side_moves([order; 3])
+    }
+
+    /// 展开为长方体魔方的面旋转事件，dims为x/y/z方向的层数
+    pub fn to_cuboid_side_moves(self, dims: [u8; 3]) -> Vec<SideMoveEvent> {
+        let layers_of = |axis: Axis| dims[axis.index()];
+        let outer = |axis: Axis| layers_of(axis) as i32 - 1;
+        let (axis, layers, clockwise): (Axis, Vec<i32>, SideRotation) = match self.kind {
+            MoveKind::Layer(face, n) => {
+                let (axis, sign) = face_axis(face);
+                let layer = sign as i32 * (outer(axis) - 2 * (n as i32 - 1));
+                (axis, vec![layer], face_clockwise(sign))
+            }
+            MoveKind::Wide(face, n) => {
+                let (axis, sign) = face_axis(face);
+                let layers = (0..n as i32)
+                    .map(|i| sign as i32 * (outer(axis) - 2 * i))
+                    .collect();
+                (axis, layers, face_clockwise(sign))
+            }
+            MoveKind::Slice(axis) => (
+                axis,
+                middle_layers(layers_of(axis)),
+                face_clockwise(slice_sign(axis)),
+            ),
+            MoveKind::Rotation(axis) => (
+                axis,
+                layer_coords(layers_of(axis)).collect(),
+                face_clockwise(1.0),
+            ),
+        };
+        let rotate = match self.amount % 4 {
+            1 => clockwise,
+            2 => SideRotation::Clockwise180,
+            3 => clockwise.inverse(),
+            _ => return Vec::new(),
+        };
+        layers
+            .into_iter()
+            .map(|layer| SideMoveEvent {
+                side: (axis, layer as f32 / 2.0),
+                rotate,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            MoveKind::Layer(face, 1) => write!(f, "{}", face.to_char())?,
+            MoveKind::Layer(face, n) => write!(f, "{}{}", n, face.to_char())?,
+            MoveKind::Wide(face, 2) => write!(f, "{}w", face.to_char())?,
+            MoveKind::Wide(face, n) => write!(f, "{}{}w", n, face.to_char())?,
+            MoveKind::Slice(axis) => write!(
+                f,
+                "{}",
+                match axis {
+                    Axis::X => 'M',
+                    Axis::Y => 'E',
+                    Axis::Z => 'S',
+                }
+            )?,
+            MoveKind::Rotation(axis) => write!(
+                f,
+                "{}",
+                match axis {
+                    Axis::X => 'x',
+                    Axis::Y => 'y',
+                    Axis::Z => 'z',
+                }
+            )?,
+        }
+        match self.amount % 4 {
+            2 => write!(f, "2"),
+            3 => write!(f, "'"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotationErrorKind {
+    UnexpectedChar(char),
+    UnexpectedEnd,
+    UnclosedParen,
+    UnmatchedCloseParen,
+    InvalidCount(String),
+    LayerOutOfRange(u32),
+    // 长方体魔方非正方形的面只能转动180度
+    QuarterTurn,
+    // 展开后超过MAX_ALGORITHM_MOVES步
+    TooManyMoves,
+    // 括号嵌套超过MAX_GROUP_DEPTH层
+    TooDeeplyNested,
+}
+
+/// 解析错误，position为出错字符在输入中的位置（按字符计）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotationError {
+    pub position: usize,
+    pub kind: NotationErrorKind,
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            NotationErrorKind::UnexpectedChar(c) => {
+                write!(f, "unexpected '{}' at position {}", c, self.position)
+            }
+            NotationErrorKind::UnexpectedEnd => {
+                write!(f, "unexpected end after position {}", self.position)
+            }
+            NotationErrorKind::UnclosedParen => {
+                write!(f, "unclosed '(' at position {}", self.position)
+            }
+            NotationErrorKind::UnmatchedCloseParen => {
+                write!(f, "unmatched ')' at position {}", self.position)
+            }
+            NotationErrorKind::InvalidCount(count) => {
+                write!(f, "invalid count '{}' at position {}", count, self.position)
+            }
+            NotationErrorKind::LayerOutOfRange(layer) => {
+                write!(
+                    f,
+                    "layer {} out of range at position {}",
+                    layer, self.position
+                )
+            }
+            NotationErrorKind::QuarterTurn => {
+                write!(
+                    f,
+                    "only half turns are allowed on a non-square face at position {}",
+                    self.position
+                )
+            }
+            NotationErrorKind::TooManyMoves => {
+                write!(
+                    f,
+                    "algorithm expands to more than {} moves at position {}",
+                    MAX_ALGORITHM_MOVES, self.position
+                )
+            }
+            NotationErrorKind::TooDeeplyNested => {
+                write!(
+                    f,
+                    "groups nested deeper than {} at position {}",
+                    MAX_GROUP_DEPTH, self.position
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+/// 解析如`R U2 F' x M' Rw2 (R U R' U')2`的公式，order为魔方阶数，用于检查`3Rw`等转动的层数
+pub fn parse_algorithm(input: &str, order: u8) -> Result<Vec<Move>, NotationError> {
+    parse_cuboid_algorithm(input, [order; 3])
+}
+
+/// 按长方体魔方x/y/z方向的层数解析公式，非正方形的面只能转动180度
+pub fn parse_cuboid_algorithm(input: &str, dims: [u8; 3]) -> Result<Vec<Move>, NotationError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut parser = Parser {
+        chars,
+        pos: 0,
+        dims,
+    };
+    let moves = parser.parse_sequence(0)?;
+    Ok(moves)
+}
+
+/// 解析公式并展开为面旋转事件，每一步转动为一组同时转动的层
+pub fn parse_side_moves(input: &str, order: u8) -> Result<Vec<Vec<SideMoveEvent>>, NotationError> {
+    parse_cuboid_side_moves(input, [order; 3])
+}
+
+pub fn parse_cuboid_side_moves(
+    input: &str,
+    dims: [u8; 3],
+) -> Result<Vec<Vec<SideMoveEvent>>, NotationError> {
+    Ok(parse_cuboid_algorithm(input, dims)?
+        .into_iter()
+        .map(|m| m.to_cuboid_side_moves(dims))
+        .collect())
+}
+
+pub fn format_algorithm(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .map(|m| m.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 解析每步为一个字母加可选`'`的公式，如斜转和金字塔的`R U' l`。
+/// letter将字母转为转动的对象，返回的bool表示是否带`'`
+pub fn parse_letter_moves<T>(
+    input: &str,
+    letter: impl Fn(char) -> Option<T>,
+) -> Result<Vec<(T, bool)>, NotationError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut moves = Vec::new();
+    let mut pos = 0;
+    while let Some(&c) = chars.get(pos) {
+        if c.is_whitespace() || c == ',' {
+            pos += 1;
+            continue;
+        }
+        let Some(target) = letter(c) else {
+            return Err(NotationError {
+                position: pos,
+                kind: NotationErrorKind::UnexpectedChar(c),
+            });
+        };
+        pos += 1;
+        let prime = chars.get(pos) == Some(&'\'');
+        if prime {
+            pos += 1;
+        }
+        moves.push((target, prime));
+    }
+    Ok(moves)
+}
+
+/// 将面旋转事件还原为公式，同轴同向的相邻层会合并为宽层转动或整体转动；dims为x/y/z方向的层数
+pub fn format_side_moves(events: &[SideMoveEvent], dims: [u8; 3]) -> String {
+    format_algorithm(&side_moves_to_algorithm(events, dims))
+}
+
+pub fn side_moves_to_algorithm(events: &[SideMoveEvent], dims: [u8; 3]) -> Vec<Move> {
+    let mut moves = Vec::new();
+    let mut i = 0;
+    while i < events.len() {
+        let first = events[i];
+        let mut layers = vec![layer_coord(first.side.1)];
+        let mut j = i + 1;
+        while j < events.len()
+            && events[j].side.0 == first.side.0
+            && events[j].rotate == first.rotate
+            && !layers.contains(&layer_coord(events[j].side.1))
+        {
+            layers.push(layer_coord(events[j].side.1));
+            j += 1;
+        }
+        layers.sort();
+        let axis = first.side.0;
+        let order = dims[axis.index()];
+        let outer = order as i32 - 1;
+        // 层是否从最大（或最小）坐标开始连续
+        let contiguous = layers.windows(2).all(|w| w[1] - w[0] == 2);
+        let depth = layers.len() as u8;
+        if depth == order {
+            moves.push(oriented_move(MoveKind::Rotation(axis), 1.0, first.rotate));
+        } else if order >= 3 && layers == middle_layers(order) {
+            moves.push(oriented_move(
+                MoveKind::Slice(axis),
+                slice_sign(axis),
+                first.rotate,
+            ));
+        } else if depth > 1 && contiguous && layers[depth as usize - 1] == outer {
+            let kind = MoveKind::Wide(axis_face(axis, 1.0), depth);
+            moves.push(oriented_move(kind, 1.0, first.rotate));
+        } else if depth > 1 && contiguous && layers[0] == -outer {
+            let kind = MoveKind::Wide(axis_face(axis, -1.0), depth);
+            moves.push(oriented_move(kind, -1.0, first.rotate));
+        } else {
+            for event in &events[i..j] {
+                moves.push(single_layer_move(*event, dims));
+            }
+        }
+        i = j;
+    }
+    moves
+}
+
+fn single_layer_move(event: SideMoveEvent, dims: [u8; 3]) -> Move {
+    let (axis, value) = event.side;
+    let outer = dims[axis.index()] as i32 - 1;
+    match layer_coord(value) {
+        0 => oriented_move(MoveKind::Slice(axis), slice_sign(axis), event.rotate),
+        layer => {
+            let sign = layer.signum() as f32;
+            let n = ((outer - layer.abs()) / 2 + 1) as u8;
+            let kind = MoveKind::Layer(axis_face(axis, sign), n);
+            oriented_move(kind, sign, event.rotate)
+        }
+    }
+}
+
+// 根据转动方向所跟随的面（正向或负向）计算amount
+fn oriented_move(kind: MoveKind, sign: f32, rotate: SideRotation) -> Move {
+    let amount = if rotate == SideRotation::Clockwise180 {
+        2
+    } else if rotate == face_clockwise(sign) {
+        1
+    } else {
+        3
+    };
+    Move { kind, amount }
+}
+
+// 层坐标的两倍，与CubeState一致
+fn layer_coord(value: f32) -> i32 {
+    (value * 2.0).round() as i32
+}
+
+// 中间层，奇数阶为中心层，偶数阶为中间两层
+fn middle_layers(order: u8) -> Vec<i32> {
+    if order % 2 == 1 {
+        vec![0]
+    } else {
+        vec![-1, 1]
+    }
+}
+
+// M跟随L，E跟随D，S跟随F
+fn slice_sign(axis: Axis) -> f32 {
+    match axis {
+        Axis::X | Axis::Y => -1.0,
+        Axis::Z => 1.0,
+    }
+}
+
+// 面所在的轴及外法线的正负方向
+pub fn face_axis(face: Face) -> (Axis, f32) {
+    match face {
+        Face::R => (Axis::X, 1.0),
+        Face::L => (Axis::X, -1.0),
+        Face::U => (Axis::Y, 1.0),
+        Face::D => (Axis::Y, -1.0),
+        Face::F => (Axis::Z, 1.0),
+        Face::B => (Axis::Z, -1.0),
+    }
+}
+
+fn axis_face(axis: Axis, sign: f32) -> Face {
+    match (axis, sign > 0.0) {
+        (Axis::X, true) => Face::R,
+        (Axis::X, false) => Face::L,
+        (Axis::Y, true) => Face::U,
+        (Axis::Y, false) => Face::D,
+        (Axis::Z, true) => Face::F,
+        (Axis::Z, false) => Face::B,
+    }
+}
+
+// 从外部看顺时针，正向的面是绕轴的负角度，负向的面则相反
+fn face_clockwise(sign: f32) -> SideRotation {
+    if sign > 0.0 {
+        SideRotation::Counterclockwise90
+    } else {
+        SideRotation::Clockwise90
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    // x/y/z方向的层数
+    dims: [u8; 3],
+}
+
+impl Parser {
+    fn parse_sequence(&mut self, depth: usize) -> Result<Vec<Move>, NotationError> {
+        let mut moves = Vec::new();
+        while let Some(c) = self.peek() {
+            match c {
+                c if c.is_whitespace() || c == ',' => self.pos += 1,
+                '(' => {
+                    let open = self.pos;
+                    if depth >= MAX_GROUP_DEPTH {
+                        return Err(self.error_at(open, NotationErrorKind::TooDeeplyNested));
+                    }
+                    self.pos += 1;
+                    let group = self.parse_sequence(depth + 1)?;
+                    if self.peek() != Some(')') {
+                        return Err(self.error_at(open, NotationErrorKind::UnclosedParen));
+                    }
+                    self.pos += 1;
+                    let count_start = self.pos;
+                    let count = self.parse_count()?.unwrap_or(1);
+                    if count > MAX_GROUP_REPEAT {
+                        let digits = self.chars[count_start..self.pos].iter().collect();
+                        return Err(
+                            self.error_at(count_start, NotationErrorKind::InvalidCount(digits))
+                        );
+                    }
+                    if moves.len() + group.len() * count as usize > MAX_ALGORITHM_MOVES {
+                        return Err(self.error_at(open, NotationErrorKind::TooManyMoves));
+                    }
+                    for _ in 0..count {
+                        moves.extend_from_slice(&group);
+                    }
+                }
+                ')' => {
+                    if depth == 0 {
+                        return Err(self.error_at(self.pos, NotationErrorKind::UnmatchedCloseParen));
+                    }
+                    return Ok(moves);
+                }
+                _ => {
+                    let start = self.pos;
+                    let m = self.parse_move()?;
+                    // R4这类转动等于没动
+                    if m.amount != 0 {
+                        if moves.len() >= MAX_ALGORITHM_MOVES {
+                            return Err(self.error_at(start, NotationErrorKind::TooManyMoves));
+                        }
+                        moves.push(m);
+                    }
+                }
+            }
+        }
+        Ok(moves)
+    }
+
+    fn parse_move(&mut self) -> Result<Move, NotationError> {
+        let start = self.pos;
+        // 大魔方的层数前缀，如3Rw、2R
+        let prefix = self.parse_count()?;
+        let Some(c) = self.peek() else {
+            return Err(self.error_at(start, NotationErrorKind::UnexpectedEnd));
+        };
+        let kind = match c {
+            'U' | 'R' | 'F' | 'D' | 'L' | 'B' => {
+                let face = Face::from_char(c).unwrap();
+                self.pos += 1;
+                if self.peek() == Some('w') {
+                    self.pos += 1;
+                    MoveKind::Wide(face, self.check_layer(start, face, prefix.unwrap_or(2))?)
+                } else {
+                    MoveKind::Layer(face, self.check_layer(start, face, prefix.unwrap_or(1))?)
+                }
+            }
+            'u' | 'r' | 'f' | 'd' | 'l' | 'b' => {
+                self.pos += 1;
+                let face = Face::from_char(c.to_ascii_uppercase()).unwrap();
+                MoveKind::Wide(face, self.check_layer(start, face, prefix.unwrap_or(2))?)
+            }
+            'M' | 'E' | 'S' if prefix.is_none() => {
+                let axis = match c {
+                    'M' => Axis::X,
+                    'E' => Axis::Y,
+                    _ => Axis::Z,
+                };
+                if self.dims[axis.index()] < 3 {
+                    return Err(self.error_at(start, NotationErrorKind::UnexpectedChar(c)));
+                }
+                self.pos += 1;
+                MoveKind::Slice(axis)
+            }
+            'x' | 'y' | 'z' if prefix.is_none() => {
+                self.pos += 1;
+                MoveKind::Rotation(match c {
+                    'x' => Axis::X,
+                    'y' => Axis::Y,
+                    _ => Axis::Z,
+                })
+            }
+            _ => return Err(self.error_at(self.pos, NotationErrorKind::UnexpectedChar(c))),
+        };
+        let count = self.parse_count()?.unwrap_or(1);
+        let mut amount = (count % 4) as u8;
+        if self.peek() == Some('\'') {
+            self.pos += 1;
+            amount = (4 - amount) % 4;
+        }
+        // 1层的宽层转动即为外层转动
+        let kind = match kind {
+            MoveKind::Wide(face, 1) => MoveKind::Layer(face, 1),
+            kind => kind,
+        };
+        let axis = match kind {
+            MoveKind::Layer(face, _) | MoveKind::Wide(face, _) => face_axis(face).0,
+            MoveKind::Slice(axis) | MoveKind::Rotation(axis) => axis,
+        };
+        if amount % 2 == 1 && !quarter_turn_allowed(axis, self.dims) {
+            return Err(self.error_at(start, NotationErrorKind::QuarterTurn));
+        }
+        Ok(Move { kind, amount })
+    }
+
+    fn check_layer(&self, start: usize, face: Face, layer: u32) -> Result<u8, NotationError> {
+        if layer == 0 || layer > self.dims[face_axis(face).0.index()] as u32 {
+            return Err(self.error_at(start, NotationErrorKind::LayerOutOfRange(layer)));
+        }
+        Ok(layer as u8)
+    }
+
+    fn parse_count(&mut self) -> Result<Option<u32>, NotationError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        match digits.parse() {
+            Ok(count) => Ok(Some(count)),
+            Err(_) => Err(self.error_at(start, NotationErrorKind::InvalidCount(digits))),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error_at(&self, position: usize, kind: NotationErrorKind) -> NotationError {
+        NotationError { position, kind }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 逐步还原为公式，相邻的步不会合并
+    fn round_trip(input: &str, order: u8) -> String {
+        parse_side_moves(input, order)
+            .unwrap()
+            .iter()
+            .map(|layers| format_side_moves(layers, [order; 3]))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn formats_what_it_parses() {
+        for algorithm in ["R U2 F' L D' B2", "x y' z2", "M E' S2", "Rw 3Lw' 2R Uw2"] {
+            assert_eq!(round_trip(algorithm, 5), algorithm);
+        }
+        assert_eq!(round_trip("r u'", 3), "Rw Uw'");
+        assert_eq!(round_trip("(R U R' U')2", 3), "R U R' U' R U R' U'");
+        // 偶数阶的中层为中间两层
+        assert_eq!(round_trip("M2", 4), "M2");
+        // 展开后的面旋转中，同轴同向的相邻层会合并
+        let events: Vec<SideMoveEvent> = parse_side_moves("Rw 3Lw'", 5)
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        assert_eq!(format_side_moves(&events, [5; 3]), "x");
+    }
+
+    #[test]
+    fn each_move_is_one_group() {
+        let moves = parse_side_moves("x Rw R M", 3).unwrap();
+        assert_eq!(
+            moves.iter().map(|layers| layers.len()).collect::<Vec<_>>(),
+            [3, 2, 1, 1]
+        );
+        assert!(moves
+            .iter()
+            .all(|layers| layers.iter().all(|event| event.side.0 == layers[0].side.0)));
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let error = |input: &str| parse_algorithm(input, 3).unwrap_err();
+        assert_eq!(
+            error("R U Q"),
+            NotationError {
+                position: 4,
+                kind: NotationErrorKind::UnexpectedChar('Q')
+            }
+        );
+        assert_eq!(error("(R U").kind, NotationErrorKind::UnclosedParen);
+        assert_eq!(error("R)").kind, NotationErrorKind::UnmatchedCloseParen);
+        assert_eq!(error("4R").kind, NotationErrorKind::LayerOutOfRange(4));
+        assert_eq!(
+            parse_cuboid_algorithm("R", [3, 2, 3]).unwrap_err().kind,
+            NotationErrorKind::QuarterTurn
+        );
+    }
+
+    #[test]
+    fn limits_repeated_groups() {
+        assert_eq!(
+            parse_algorithm("(R)4000000000", 3).unwrap_err(),
+            NotationError {
+                position: 3,
+                kind: NotationErrorKind::InvalidCount("4000000000".to_string())
+            }
+        );
+        assert_eq!(parse_algorithm("()100", 3).unwrap(), Vec::<Move>::new());
+        assert_eq!(
+            parse_algorithm("((R U)100)100", 3).unwrap_err(),
+            NotationError {
+                position: 0,
+                kind: NotationErrorKind::TooManyMoves
+            }
+        );
+        assert_eq!(parse_algorithm("(R U R' U')100", 3).unwrap().len(), 400);
+    }
+
+    #[test]
+    fn limits_group_depth() {
+        let nested = |depth: usize| format!("{}R{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(
+            parse_algorithm(&nested(MAX_GROUP_DEPTH), 3).unwrap().len(),
+            1
+        );
+        assert_eq!(
+            parse_algorithm(&nested(MAX_GROUP_DEPTH + 1), 3).unwrap_err(),
+            NotationError {
+                position: MAX_GROUP_DEPTH,
+                kind: NotationErrorKind::TooDeeplyNested
+            }
+        );
+        // 大量的'('不会导致栈溢出
+        assert_eq!(
+            parse_algorithm(&"(".repeat(100_000), 3).unwrap_err().kind,
+            NotationErrorKind::TooDeeplyNested
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use crate::cube::*;
+use crate::move_history::MoveHistory;
+use crate::notation::parse_side_moves;
+use crate::puzzle::{MoveQueue, MoveSource};
+use crate::save::LoadEvent;
+use crate::save_data::{SaveData, SaveError, SAVE_VERSION};
+use crate::state::CubeState;
+use crate::timekeeping::TimekeepingTimer;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+// 用户花样文件格式版本
+pub const PATTERN_FILE_VERSION: u32 = 1;
+pub const PATTERN_FILE: &str = "rubiks-cube-patterns.ron";
+
+/// 从还原状态执行公式得到的花样
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pattern {
+    pub name: String,
+    pub algorithm: String,
+}
+
+impl Pattern {
+    pub fn new(name: &str, algorithm: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            algorithm: algorithm.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PatternFile {
+    version: u32,
+    patterns: Vec<Pattern>,
+}
+
+pub fn builtin_patterns() -> Vec<Pattern> {
+    vec![
+        Pattern::new("Checkerboard", "R2 L2 U2 D2 F2 B2"),
+        Pattern::new(
+            "Superflip",
+            "U R2 F B R B2 R U2 L B2 R U' D' R2 F R' L B2 U2 F2",
+        ),
+        Pattern::new("Cube in Cube", "F L F U' R U F2 L2 U' L' B D' B' L2 U"),
+        Pattern::new("Six Spots", "U D' R L' F B' U D'"),
+        Pattern::new("Python", "F2 R' B' U R' L F' L F' B D' R B L2"),
+        Pattern::new("Anaconda", "L U B' U' R L' B R' F B' D R D' F'"),
+    ]
+}
+
+// 读取用户花样，文件不存在时为空
+pub fn load_user_patterns(path: &str) -> Result<Vec<Pattern>, SaveError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(SaveError::Io(err)),
+    };
+    let file: PatternFile = ron::from_str(&text).map_err(SaveError::Deserialize)?;
+    if file.version != PATTERN_FILE_VERSION {
+        return Err(SaveError::UnsupportedVersion(file.version));
+    }
+    Ok(file.patterns)
+}
+
+pub fn save_user_patterns(path: &str, patterns: &[Pattern]) -> Result<(), SaveError> {
+    let file = PatternFile {
+        version: PATTERN_FILE_VERSION,
+        patterns: patterns.to_vec(),
+    };
+    let text = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+        .map_err(SaveError::Serialize)?;
+    fs::write(path, text).map_err(SaveError::Io)
+}
+
+/// 花样库，内置花样和用户保存的花样
+#[derive(Debug, Resource)]
+pub struct PatternLibrary {
+    pub builtin: Vec<Pattern>,
+    pub user: Vec<Pattern>,
+}
+
+impl Default for PatternLibrary {
+    fn default() -> Self {
+        Self {
+            builtin: builtin_patterns(),
+            user: Vec::new(),
+        }
+    }
+}
+
+// 添加花样的输入框
+#[derive(Debug, Default, Resource)]
+pub struct PatternInput {
+    pub name: String,
+    pub algorithm: String,
+    pub error: Option<String>,
+}
+
+pub fn setup_pattern_library(mut library: ResMut<PatternLibrary>) {
+    match load_user_patterns(PATTERN_FILE) {
+        Ok(patterns) => library.user = patterns,
+        Err(err) => warn!("failed to load {}: {}", PATTERN_FILE, err),
+    }
+}
+
+// 花样的应用方式
+enum PatternAction {
+    // 从还原状态播放转动动画
+    Animate,
+    // 直接跳到花样状态
+    Jump,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn pattern_ui(
+    mut egui_context: EguiContexts,
+    mut library: ResMut<PatternLibrary>,
+    mut input: ResMut<PatternInput>,
+    cube_settings: Res<CubeSettings>,
+    timekeeping_timer: Res<TimekeepingTimer>,
+    mut side_move_queue: ResMut<MoveQueue<CubePuzzle>>,
+    mut reset_event: EventWriter<ResetEvent>,
+    mut load_event: EventWriter<LoadEvent>,
+) {
+    // 花样库只适用于正阶魔方
+    if cube_settings.puzzle != PuzzleKind::Cube {
+        return;
+    }
+    let order = cube_settings.cube_order;
+    let mut chosen = None;
+    egui::Window::new("Patterns")
+        .default_open(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            egui::Grid::new("pattern_grid")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for pattern in library.builtin.iter().chain(&library.user) {
+                        ui.label(&pattern.name).on_hover_text(&pattern.algorithm);
+                        // 计时中不能用花样转动魔方
+                        if ui
+                            .add_enabled(
+                                !timekeeping_timer.is_running(cube_settings.play_mode),
+                                egui::Button::new("Animate"),
+                            )
+                            .clicked()
+                        {
+                            chosen = Some((pattern.clone(), PatternAction::Animate));
+                        }
+                        if ui.button("Jump").clicked() {
+                            chosen = Some((pattern.clone(), PatternAction::Jump));
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut input.name);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Algorithm");
+                ui.text_edit_singleline(&mut input.algorithm);
+            });
+            if ui.button("Add Pattern").clicked() {
+                let name = input.name.trim().to_string();
+                if name.is_empty() {
+                    input.error = Some("pattern name is empty".to_string());
+                } else if let Err(err) = parse_side_moves(&input.algorithm, order) {
+                    input.error = Some(err.to_string());
+                } else {
+                    library
+                        .user
+                        .push(Pattern::new(&name, input.algorithm.trim()));
+                    input.error = save_user_patterns(PATTERN_FILE, &library.user)
+                        .err()
+                        .map(|err| err.to_string());
+                    input.name.clear();
+                    input.algorithm.clear();
+                }
+            }
+            if let Some(error) = &input.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+        });
+
+    let Some((pattern, action)) = chosen else {
+        return;
+    };
+    // 公式可能包含当前阶数不支持的转动
+    let moves = match parse_side_moves(&pattern.algorithm, order) {
+        Ok(moves) => moves,
+        Err(err) => {
+            input.error = Some(format!("{}: {}", pattern.name, err));
+            return;
+        }
+    };
+    input.error = None;
+    match action {
+        PatternAction::Animate => {
+            side_move_queue.0.clear();
+            reset_event.send_default();
+            side_move_queue.push_moves(moves, MoveSource::Player);
+        }
+        PatternAction::Jump => {
+            let mut cube = CubeState::solved(order);
+            for event in moves.iter().flatten() {
+                cube.apply_side_move(event);
+            }
+            load_event.send(LoadEvent(Some(SaveData {
+                version: SAVE_VERSION,
+                cube,
+                history: MoveHistory::default(),
+                scramble: None,
+            })));
+        }
+    }
+}
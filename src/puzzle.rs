@@ -0,0 +1,380 @@
+use crate::cube::*;
+use crate::history::{undo_redo_moves, RedoEvent, UndoEvent};
+use crate::move_history::MoveHistory;
+use crate::notation::NotationError;
+use crate::ui::AlgorithmInput;
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// 谜题前端共用的逻辑：状态、转动、还原判断、记号、打乱和求解。
+/// 各前端（魔方、扭转类谜题、Square-1、魔表）负责生成块、拖动和动画，重置时按当前设置插入或移除自己的谜题资源；
+/// 实现此trait并添加`PuzzlePlugin`后，打乱、计步、撤销、公式输入、求解和计时都由通用系统完成，UI不需要修改
+pub trait Puzzle: Resource {
+    type State: Clone + Send + Sync + 'static;
+    type Move: Clone + fmt::Debug + Send + Sync + 'static;
+
+    fn solved_state(&self) -> Self::State;
+
+    /// 执行一步转动，被挡住时返回None
+    fn apply(&self, state: &Self::State, m: &Self::Move) -> Option<Self::State>;
+
+    /// 撤销m时依次执行的转动，state为执行m之前的状态
+    fn invert(&self, state: &Self::State, m: &Self::Move) -> Vec<Self::Move>;
+
+    fn is_solved(&self, state: &Self::State) -> bool;
+
+    // 完成的转动是否计入步数，previous为上一次完成的转动
+    fn counts(&self, _m: &Self::Move, _previous: Option<&Self::Move>) -> bool {
+        true
+    }
+
+    // 观察阶段执行此转动时开始计时
+    fn is_turn(&self, _m: &Self::Move) -> bool {
+        true
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Self::Move>, NotationError>;
+
+    /// 打乱公式及对应的转动
+    fn scramble(&self, rng: &mut StdRng) -> (String, Vec<Self::Move>);
+
+    fn can_solve(&self) -> bool {
+        false
+    }
+
+    /// 解法及对应的转动
+    fn solve(&self, _state: &Self::State) -> Result<(String, Vec<Self::Move>), String> {
+        Err("this puzzle has no solver".to_string())
+    }
+}
+
+/// 转动的来源，决定转动完成后是否计入步数和转动历史
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoveSource {
+    // 拖动、按键、输入的公式和求解
+    #[default]
+    Player,
+    // 打乱，不计入步数和历史
+    Scramble,
+    // 撤销或重做，计入步数，历史已经更新
+    History,
+}
+
+/// 当前谜题的逻辑状态，每完成一步转动后更新；谜题不是当前谜题时为None
+#[derive(Resource)]
+pub struct PuzzleState<P: Puzzle>(pub Option<P::State>);
+
+impl<P: Puzzle> Default for PuzzleState<P> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+// 等待执行的转动及其来源
+#[derive(Resource)]
+pub struct MoveQueue<P: Puzzle>(pub VecDeque<(P::Move, MoveSource)>);
+
+impl<P: Puzzle> Default for MoveQueue<P> {
+    fn default() -> Self {
+        Self(VecDeque::new())
+    }
+}
+
+impl<P: Puzzle> MoveQueue<P> {
+    pub fn push_moves(&mut self, moves: impl IntoIterator<Item = P::Move>, source: MoveSource) {
+        self.0.extend(moves.into_iter().map(|m| (m, source)));
+    }
+}
+
+// 正在执行动画的转动，动画结束后作为MoveEvent发出
+#[derive(Resource)]
+pub struct ActiveMove<P: Puzzle>(pub Option<(P::Move, MoveSource)>);
+
+impl<P: Puzzle> Default for ActiveMove<P> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+// 一次转动的动画已完成
+#[derive(Event)]
+pub struct MoveEvent<P: Puzzle>(pub P::Move, pub MoveSource);
+
+// 解析公式并加入当前谜题的转动队列
+#[derive(Debug, Event)]
+pub struct ApplyAlgorithmEvent(pub String);
+
+/// 当前谜题的撤销、求解和转动情况，由update_status每帧更新，供UI和计时器使用
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct PuzzleStatus {
+    pub can_undo: bool,
+    pub can_redo: bool,
+    pub can_solve: bool,
+    // 还有打乱的转动没有完成
+    pub scrambling: bool,
+    // 有开始计时的转动在排队或执行中
+    pub turning: bool,
+}
+
+/// 重置谜题的各阶段，前一阶段发出的ResetEvent在同一帧内处理
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemSet)]
+pub enum PuzzleSystems {
+    // 打乱、读取存档等会重置谜题的操作
+    Prepare,
+    // 各前端重建块并插入或移除谜题资源
+    Reset,
+    // 同步完成的转动
+    Track,
+}
+
+/// 注册谜题的状态、转动队列、转动历史和通用系统
+pub struct PuzzlePlugin<P>(PhantomData<P>);
+
+impl<P> Default for PuzzlePlugin<P> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P: Puzzle> Plugin for PuzzlePlugin<P> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PuzzleState<P>>()
+            .init_resource::<MoveQueue<P>>()
+            .init_resource::<ActiveMove<P>>()
+            .init_resource::<MoveHistory<P::Move>>()
+            .init_resource::<PuzzleStatus>()
+            .init_resource::<SolveProgress>()
+            .init_resource::<CurrentScramble>()
+            .init_resource::<LatestSolution>()
+            .init_resource::<AlgorithmInput>()
+            .add_event::<MoveEvent<P>>()
+            .add_event::<ResetEvent>()
+            .add_event::<ScrambleEvent>()
+            .add_event::<SolveEvent>()
+            .add_event::<CubeSolvedEvent>()
+            .add_event::<UndoEvent>()
+            .add_event::<RedoEvent>()
+            .add_event::<ApplyAlgorithmEvent>()
+            .configure_sets(
+                Update,
+                (
+                    PuzzleSystems::Prepare,
+                    PuzzleSystems::Reset,
+                    PuzzleSystems::Track,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    scramble_puzzle::<P>.in_set(PuzzleSystems::Prepare),
+                    (track_puzzle::<P>, update_status::<P>)
+                        .chain()
+                        .in_set(PuzzleSystems::Track),
+                    // 在同一帧的状态摘要中反映新加入队列的转动
+                    (
+                        undo_redo_moves::<P>,
+                        apply_algorithm::<P>,
+                        solve_puzzle::<P>,
+                    )
+                        .before(PuzzleSystems::Track),
+                ),
+            );
+    }
+}
+
+/// 当前状态执行完正在进行和排队中的转动后的状态，有转动被挡住时返回None
+pub fn predicted_state<P: Puzzle>(
+    puzzle: &P,
+    state: &P::State,
+    active: &ActiveMove<P>,
+    queue: &MoveQueue<P>,
+) -> Option<P::State> {
+    active
+        .0
+        .iter()
+        .chain(&queue.0)
+        .try_fold(state.clone(), |state, (m, _)| puzzle.apply(&state, m))
+}
+
+/// 检查公式的每一步都能从state开始依次执行，返回第一个被挡住的转动在公式中的位置
+pub fn check_moves<P: Puzzle>(puzzle: &P, state: P::State, moves: &[P::Move]) -> Result<(), usize> {
+    let mut state = state;
+    for (i, m) in moves.iter().enumerate() {
+        state = puzzle.apply(&state, m).ok_or(i)?;
+    }
+    Ok(())
+}
+
+// 从还原状态开始打乱，相同的种子得到相同的打乱公式
+pub fn scramble_puzzle<P: Puzzle>(
+    puzzle: Option<Res<P>>,
+    mut events: EventReader<ScrambleEvent>,
+    mut reset_event: EventWriter<ResetEvent>,
+    mut queue: ResMut<MoveQueue<P>>,
+    mut current_scramble: ResMut<CurrentScramble>,
+) {
+    let Some(puzzle) = puzzle else {
+        events.clear();
+        return;
+    };
+    for event in events.read() {
+        let mut rng = match event.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let (scramble, moves) = puzzle.scramble(&mut rng);
+        info!("scramble: {}", scramble);
+
+        // 重置不会清空队列，打乱的转动在重建后的谜题上执行
+        queue.0.clear();
+        reset_event.send_default();
+        queue.push_moves(moves, MoveSource::Scramble);
+        current_scramble.0 = Some(scramble);
+    }
+}
+
+// 将完成的转动同步到逻辑状态，记录历史、计步并判断是否完成还原
+#[allow(clippy::too_many_arguments)]
+pub fn track_puzzle<P: Puzzle>(
+    puzzle: Option<Res<P>>,
+    mut events: EventReader<MoveEvent<P>>,
+    mut reset_events: EventReader<ResetEvent>,
+    mut state: ResMut<PuzzleState<P>>,
+    mut active: ResMut<ActiveMove<P>>,
+    mut history: ResMut<MoveHistory<P::Move>>,
+    mut solve_progress: ResMut<SolveProgress>,
+    mut solved_event: EventWriter<CubeSolvedEvent>,
+    mut last_move: Local<Option<P::Move>>,
+) {
+    if !reset_events.is_empty() {
+        reset_events.clear();
+        state.0 = puzzle.as_ref().map(|puzzle| puzzle.solved_state());
+        active.0 = None;
+        *last_move = None;
+        solve_progress.move_count = 0;
+        history.clear();
+        // 重置前完成的转动属于旧谜题
+        events.clear();
+    }
+    let (Some(puzzle), Some(state)) = (puzzle, &mut state.0) else {
+        events.clear();
+        return;
+    };
+    for MoveEvent(m, source) in events.read() {
+        let was_solved = puzzle.is_solved(state);
+        let Some(next) = puzzle.apply(state, m) else {
+            warn!("blocked move: {:?}", m);
+            continue;
+        };
+        let undo = puzzle.invert(state, m);
+        *state = next;
+        let solved = puzzle.is_solved(state);
+        debug!("move: {:?}, solved: {}", m, solved);
+        let previous = last_move.replace(m.clone());
+        match source {
+            MoveSource::Scramble => {
+                *last_move = None;
+                continue;
+            }
+            MoveSource::Player => history.record(m.clone(), undo),
+            MoveSource::History => {}
+        }
+        if puzzle.counts(m, previous.as_ref()) {
+            solve_progress.move_count += 1;
+        }
+        if solved && !was_solved {
+            solved_event.send(CubeSolvedEvent {
+                move_count: solve_progress.move_count,
+            });
+        }
+    }
+}
+
+// 当前谜题的状态摘要，不是当前谜题时不更新
+pub fn update_status<P: Puzzle>(
+    puzzle: Option<Res<P>>,
+    history: Res<MoveHistory<P::Move>>,
+    queue: Res<MoveQueue<P>>,
+    active: Res<ActiveMove<P>>,
+    mut status: ResMut<PuzzleStatus>,
+) {
+    let Some(puzzle) = puzzle else {
+        return;
+    };
+    let pending = || active.0.iter().chain(&queue.0);
+    status.set_if_neq(PuzzleStatus {
+        can_undo: history.can_undo(),
+        can_redo: history.can_redo(),
+        can_solve: puzzle.can_solve(),
+        scrambling: pending().any(|(_, source)| *source == MoveSource::Scramble),
+        turning: pending().any(|(m, _)| puzzle.is_turn(m)),
+    });
+}
+
+// 公式从排队转动后的状态开始检查，如Square-1的切片可能被角块挡住
+pub fn apply_algorithm<P: Puzzle>(
+    puzzle: Option<Res<P>>,
+    state: Res<PuzzleState<P>>,
+    mut events: EventReader<ApplyAlgorithmEvent>,
+    mut queue: ResMut<MoveQueue<P>>,
+    active: Res<ActiveMove<P>>,
+    mut algorithm_input: ResMut<AlgorithmInput>,
+) {
+    let (Some(puzzle), Some(state)) = (puzzle, &state.0) else {
+        events.clear();
+        return;
+    };
+    for ApplyAlgorithmEvent(text) in events.read() {
+        let result = puzzle
+            .parse(text)
+            .map_err(|err| err.to_string())
+            .and_then(|moves| {
+                let state = predicted_state(&*puzzle, state, &active, &queue)
+                    .ok_or("the queued moves are blocked")?;
+                check_moves(&*puzzle, state, &moves)
+                    .map_err(|index| format!("move {} is blocked", index + 1))?;
+                Ok(moves)
+            });
+        algorithm_input.error = match result {
+            Ok(moves) => {
+                queue.push_moves(moves, MoveSource::Player);
+                None
+            }
+            Err(err) => Some(err),
+        };
+    }
+}
+
+pub fn solve_puzzle<P: Puzzle>(
+    puzzle: Option<Res<P>>,
+    state: Res<PuzzleState<P>>,
+    mut events: EventReader<SolveEvent>,
+    mut queue: ResMut<MoveQueue<P>>,
+    active: Res<ActiveMove<P>>,
+    mut latest_solution: ResMut<LatestSolution>,
+) {
+    let (Some(puzzle), Some(state)) = (puzzle, &state.0) else {
+        events.clear();
+        return;
+    };
+    for _ in events.read() {
+        if !puzzle.can_solve() {
+            continue;
+        }
+        // 逻辑状态只在转动完成后更新，转动过程中求解会得到过时的结果
+        if !queue.0.is_empty() || active.0.is_some() {
+            info!("puzzle is still moving, ignore solve request");
+            continue;
+        }
+        latest_solution.0 = Some(puzzle.solve(state).map(|(solution, moves)| {
+            info!("solution ({} moves): {}", moves.len(), solution);
+            queue.push_moves(moves, MoveSource::Player);
+            solution
+        }));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::color_scheme::ColorScheme;
+use crate::keyboard::KeyBinding;
+use crate::notation::NotationError;
+use crate::pyraminx_solver::*;
+use crate::twisty::{Twist, TwistAxis, Twisty, TwistyDef};
+use bevy::color::palettes;
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+
+// 顶点到中心的距离
+const RADIUS: f32 = 2.4;
+
+// 每个轴上的切割面：层转动转动顶点一侧的三分之二，顶角转动只转顶角
+pub const LAYER_CUT: usize = 0;
+pub const TIP_CUT: usize = 1;
+
+/// 四个顶点的位置：U在正上方，D面朝下，B在后方
+pub fn vertices() -> [Vec3; 4] {
+    let r = RADIUS * 8f32.sqrt() / 3.0;
+    let y = -RADIUS / 3.0;
+    let x = r * 0.75f32.sqrt();
+    [
+        Vec3::new(0.0, RADIUS, 0.0),
+        Vec3::new(-x, y, r / 2.0),
+        Vec3::new(x, y, r / 2.0),
+        Vec3::new(0.0, y, -r),
+    ]
+}
+
+/// 金字塔的几何定义，第i个面与第i个顶点相对，依次为D、R、L、F面
+pub fn definition() -> TwistyDef {
+    let vertices = vertices();
+    TwistyDef {
+        faces: vertices
+            .iter()
+            .map(|vertex| (-vertex.normalize(), RADIUS / 3.0))
+            .collect(),
+        colors: vec![
+            palettes::css::YELLOW.into(),
+            palettes::css::BLUE.into(),
+            palettes::css::RED.into(),
+            palettes::css::GREEN.into(),
+        ],
+        axes: vertices
+            .iter()
+            .map(|vertex| TwistAxis {
+                normal: vertex.normalize(),
+                order: 3,
+                cuts: vec![RADIUS / 9.0, RADIUS * 5.0 / 9.0],
+            })
+            .collect(),
+    }
+}
+
+impl PyraminxMove {
+    pub fn to_twist(self) -> Twist {
+        Twist {
+            axis: self.vertex,
+            cut: if self.tip { TIP_CUT } else { LAYER_CUT },
+            // 转两次即逆时针转一次
+            amount: if self.amount == 2 { -1 } else { 1 },
+        }
+    }
+}
+
+/// 按键绑定：U、L、R、B转动层，1到4转动对应的顶角，Shift为逆时针
+pub fn key_bindings() -> Vec<KeyBinding> {
+    let keys = [
+        (KeyCode::KeyU, "U"),
+        (KeyCode::KeyL, "L"),
+        (KeyCode::KeyR, "R"),
+        (KeyCode::KeyB, "B"),
+        (KeyCode::Digit1, "u"),
+        (KeyCode::Digit2, "l"),
+        (KeyCode::Digit3, "r"),
+        (KeyCode::Digit4, "b"),
+    ];
+    keys.into_iter()
+        .flat_map(|(key, name)| {
+            [
+                KeyBinding::new(key, false, name),
+                KeyBinding::new(key, true, &format!("{}'", name)),
+            ]
+        })
+        .collect()
+}
+
+/// 金字塔由通用扭转前端驱动，求解器只用于生成打乱
+pub struct Pyraminx;
+
+impl Twisty for Pyraminx {
+    type State = ();
+    type Move = PyraminxMove;
+
+    fn definition(&self, _colors: &ColorScheme) -> TwistyDef {
+        definition()
+    }
+
+    fn to_twist(&self, m: PyraminxMove) -> Twist {
+        m.to_twist()
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<PyraminxMove>, NotationError> {
+        parse_algorithm(input)
+    }
+
+    fn scramble(&self, rng: &mut StdRng) -> Vec<PyraminxMove> {
+        scramble(rng)
+    }
+
+    fn key_bindings(&self) -> Vec<KeyBinding> {
+        key_bindings()
+    }
+
+    fn warm_up(&self) {
+        warm_up()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::twisty::TwistyModel;
+    use rand::SeedableRng;
+    use std::f32::consts::TAU;
+
+    #[test]
+    fn formats_moves() {
+        let moves = parse_algorithm("U L' r b'").unwrap();
+        assert_eq!(Pyraminx.format(&moves), "U L' r b'");
+    }
+
+    #[test]
+    fn vertex_cycles_match_the_geometry() {
+        let vertices = vertices();
+        for (vertex, cycle) in VERTEX_CYCLES.iter().enumerate() {
+            let rotation = Quat::from_axis_angle(vertices[vertex].normalize(), -TAU / 3.0);
+            for (v, &target) in vertices.iter().zip(cycle) {
+                assert!(vertices[target].distance(rotation * *v) < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn solution_and_tips_solve_the_stickers() {
+        let model = TwistyModel::new(&definition()).unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..5 {
+            let scramble = Pyraminx.scramble(&mut rng);
+            let state = scramble
+                .iter()
+                .fold(PyraminxState::SOLVED, |state, &m| state.apply(m));
+
+            // 解法和顶角的逆转动一起还原贴纸
+            let mut stickers = model.solved_state();
+            for &m in &scramble {
+                model.apply(&mut stickers, m.to_twist());
+            }
+            for m in solve(&state) {
+                model.apply(&mut stickers, m.to_twist());
+            }
+            for m in scramble.iter().filter(|m| m.tip) {
+                model.apply(&mut stickers, m.inverse().to_twist());
+            }
+            assert!(model.is_solved(&stickers));
+        }
+    }
+}
@@ -0,0 +1,391 @@
+use crate::cubie::permutation_parity;
+use crate::notation::{parse_letter_moves, NotationError};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::OnceLock;
+
+// 顶点名称，下标即顶点编号
+pub const VERTEX_NAMES: [char; 4] = ['U', 'L', 'R', 'B'];
+
+// 棱块位置，以两端的顶点表示
+const EDGES: [(usize, usize); 6] = [(0, 1), (0, 2), (0, 3), (1, 2), (2, 3), (1, 3)];
+
+// 不含顶角的状态数：棱块偶排列360种，朝向32种，轴心块朝向81种
+const N_PERM: usize = 720;
+const N_EO: usize = 64;
+const N_CO: usize = 81;
+const N_STATES: usize = N_PERM * N_EO * N_CO;
+
+// 打乱状态至少需要的步数（不含顶角），排除过于简单的状态
+const MIN_SCRAMBLE_LENGTH: usize = 6;
+
+// 顶点层从顶点看顺时针转动一次后各顶点的去向，由pyraminx.rs中的几何定义得到
+pub const VERTEX_CYCLES: [[usize; 4]; 4] = [[0, 3, 1, 2], [2, 1, 3, 0], [3, 0, 2, 1], [1, 2, 0, 3]];
+
+/// 金字塔的一步转动，大写为层转动，小写为顶角转动，amount为从顶点看顺时针转动的次数（1、2）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PyraminxMove {
+    pub vertex: usize,
+    pub tip: bool,
+    pub amount: u8,
+}
+
+impl PyraminxMove {
+    pub fn inverse(self) -> Self {
+        Self {
+            amount: (3 - self.amount % 3) % 3,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for PyraminxMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = VERTEX_NAMES[self.vertex];
+        if self.tip {
+            write!(f, "{}", name.to_ascii_lowercase())?;
+        } else {
+            write!(f, "{}", name)?;
+        }
+        if self.amount == 2 {
+            write!(f, "'")?;
+        }
+        Ok(())
+    }
+}
+
+/// 解析如`U L' R B' u l'`的公式
+pub fn parse_algorithm(input: &str) -> Result<Vec<PyraminxMove>, NotationError> {
+    let moves = parse_letter_moves(input, |c| {
+        let vertex = VERTEX_NAMES
+            .iter()
+            .position(|&name| name == c.to_ascii_uppercase())?;
+        Some((vertex, c.is_ascii_lowercase()))
+    })?;
+    Ok(moves
+        .into_iter()
+        .map(|((vertex, tip), prime)| PyraminxMove {
+            vertex,
+            tip,
+            amount: if prime { 2 } else { 1 },
+        })
+        .collect())
+}
+
+fn edge_slot(a: usize, b: usize) -> usize {
+    EDGES
+        .iter()
+        .position(|&edge| edge == (a.min(b), a.max(b)))
+        .unwrap()
+}
+
+// 一个顶点层顺时针转动一次时棱块的去向和朝向变化
+#[derive(Debug, Clone, Copy)]
+struct LayerTurn {
+    vertex: usize,
+    edge_target: [usize; 6],
+    edge_flip: [u8; 6],
+}
+
+impl LayerTurn {
+    fn new(vertex: usize) -> Self {
+        let cycle = VERTEX_CYCLES[vertex];
+        let mut turn = Self {
+            vertex,
+            edge_target: [0, 1, 2, 3, 4, 5],
+            edge_flip: [0; 6],
+        };
+        for (slot, &(a, b)) in EDGES.iter().enumerate() {
+            if a != vertex && b != vertex {
+                continue;
+            }
+            turn.edge_target[slot] = edge_slot(cycle[a], cycle[b]);
+            // 棱块的两个贴纸位于另外两个顶点相对的面上，朝向0表示编号较小的顶点相对的面上是主贴纸
+            let others: Vec<usize> = (0..4).filter(|&v| v != a && v != b).collect();
+            turn.edge_flip[slot] = (cycle[others[0]] > cycle[others[1]]) as u8;
+        }
+        turn
+    }
+}
+
+/// 不含顶角的金字塔状态：棱块的排列和朝向，以及四个轴心块的朝向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PyraminxState {
+    pub ep: [u8; 6],
+    pub eo: [u8; 6],
+    pub co: [u8; 4],
+}
+
+impl PyraminxState {
+    pub const SOLVED: PyraminxState = PyraminxState {
+        ep: [0, 1, 2, 3, 4, 5],
+        eo: [0; 6],
+        co: [0; 4],
+    };
+
+    fn turn(&self, turn: &LayerTurn) -> Self {
+        let mut next = *self;
+        for slot in 0..6 {
+            let target = turn.edge_target[slot];
+            next.ep[target] = self.ep[slot];
+            next.eo[target] = self.eo[slot] ^ turn.edge_flip[slot];
+        }
+        next.co[turn.vertex] = (self.co[turn.vertex] + 1) % 3;
+        next
+    }
+
+    /// 执行一步层转动，顶角转动不影响此状态
+    pub fn apply(&self, m: PyraminxMove) -> Self {
+        let mut state = *self;
+        if !m.tip {
+            let turn = &tables().turns[m.vertex];
+            for _ in 0..m.amount {
+                state = state.turn(turn);
+            }
+        }
+        state
+    }
+
+    // 三个坐标：棱块排列的康托编码、棱块朝向、轴心块朝向
+    fn perm_coord(&self) -> usize {
+        let mut perm = 0;
+        for i in 0..6 {
+            let smaller = self.ep[i + 1..].iter().filter(|&&p| p < self.ep[i]).count();
+            perm = perm * (6 - i) + smaller;
+        }
+        perm
+    }
+
+    fn eo_coord(&self) -> usize {
+        self.eo.iter().rev().fold(0, |acc, &o| acc * 2 + o as usize)
+    }
+
+    fn co_coord(&self) -> usize {
+        self.co.iter().rev().fold(0, |acc, &o| acc * 3 + o as usize)
+    }
+
+    fn set_perm_coord(&mut self, mut perm: usize) {
+        let mut digits = [0; 6];
+        for i in (0..6).rev() {
+            digits[i] = perm % (6 - i);
+            perm /= 6 - i;
+        }
+        let mut available: Vec<u8> = (0..6).collect();
+        for (p, digit) in self.ep.iter_mut().zip(digits) {
+            *p = available.remove(digit);
+        }
+    }
+
+    fn set_eo_coord(&mut self, mut eo: usize) {
+        for o in &mut self.eo {
+            *o = (eo % 2) as u8;
+            eo /= 2;
+        }
+    }
+
+    fn set_co_coord(&mut self, mut co: usize) {
+        for o in &mut self.co {
+            *o = (co % 3) as u8;
+            co /= 3;
+        }
+    }
+
+    fn index(&self) -> usize {
+        (self.perm_coord() * N_EO + self.eo_coord()) * N_CO + self.co_coord()
+    }
+}
+
+// 坐标的转动表：coord_move[坐标][顶点]为该顶点层顺时针转动一次后的坐标
+fn move_table(
+    size: usize,
+    turns: &[LayerTurn; 4],
+    set: fn(&mut PyraminxState, usize),
+    get: fn(&PyraminxState) -> usize,
+) -> Vec<[u16; 4]> {
+    (0..size)
+        .map(|coord| {
+            let mut state = PyraminxState::SOLVED;
+            set(&mut state, coord);
+            turns.map(|turn| get(&state.turn(&turn)) as u16)
+        })
+        .collect()
+}
+
+struct Tables {
+    turns: [LayerTurn; 4],
+    // 每个状态到还原状态的最少步数
+    distance: Vec<u8>,
+}
+
+/// 提前生成距离表，避免第一次打乱时卡顿
+pub fn warm_up() {
+    tables();
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let turns = [0, 1, 2, 3].map(LayerTurn::new);
+        let perm_move = move_table(
+            N_PERM,
+            &turns,
+            PyraminxState::set_perm_coord,
+            PyraminxState::perm_coord,
+        );
+        let eo_move = move_table(
+            N_EO,
+            &turns,
+            PyraminxState::set_eo_coord,
+            PyraminxState::eo_coord,
+        );
+        let co_move = move_table(
+            N_CO,
+            &turns,
+            PyraminxState::set_co_coord,
+            PyraminxState::co_coord,
+        );
+
+        // 从还原状态广度优先搜索
+        let mut distance = vec![u8::MAX; N_STATES];
+        let start = PyraminxState::SOLVED.index();
+        distance[start] = 0;
+        let mut queue = VecDeque::from([start]);
+        while let Some(index) = queue.pop_front() {
+            let (perm, eo, co) = (index / N_CO / N_EO, index / N_CO % N_EO, index % N_CO);
+            for vertex in 0..4 {
+                let (mut p, mut e, mut c) = (perm, eo, co);
+                // 顺时针和逆时针各算一步
+                for _ in 0..2 {
+                    p = perm_move[p][vertex] as usize;
+                    e = eo_move[e][vertex] as usize;
+                    c = co_move[c][vertex] as usize;
+                    let next = (p * N_EO + e) * N_CO + c;
+                    if distance[next] == u8::MAX {
+                        distance[next] = distance[index] + 1;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        Tables { turns, distance }
+    })
+}
+
+/// 求最少步数的层转动解法，不含顶角
+pub fn solve(state: &PyraminxState) -> Vec<PyraminxMove> {
+    let distance = &tables().distance;
+    let mut state = *state;
+    let mut solution = Vec::new();
+    while distance[state.index()] > 0 {
+        let current = distance[state.index()];
+        let (m, next) = (0..4)
+            .flat_map(|vertex| {
+                [1, 2].map(|amount| PyraminxMove {
+                    vertex,
+                    tip: false,
+                    amount,
+                })
+            })
+            .map(|m| (m, state.apply(m)))
+            .find(|(_, next)| distance[next.index()] < current)
+            .expect("distance table covers every reachable state");
+        solution.push(m);
+        state = next;
+    }
+    solution
+}
+
+fn random_state<R: Rng>(rng: &mut R) -> PyraminxState {
+    let mut state = PyraminxState::SOLVED;
+    state.ep.shuffle(rng);
+    // 棱块只能是偶排列
+    if permutation_parity(&state.ep) == 1 {
+        state.ep.swap(0, 1);
+    }
+    let mut flip = 0;
+    for o in &mut state.eo[..5] {
+        *o = rng.gen_range(0..2);
+        flip += *o;
+    }
+    state.eo[5] = flip % 2;
+    for o in &mut state.co {
+        *o = rng.gen_range(0..3);
+    }
+    state
+}
+
+/// 随机状态打乱：求解随机状态后取逆，最后随机转动顶角
+pub fn scramble<R: Rng>(rng: &mut R) -> Vec<PyraminxMove> {
+    loop {
+        let solution = solve(&random_state(rng));
+        if solution.len() < MIN_SCRAMBLE_LENGTH {
+            continue;
+        }
+        let mut moves: Vec<PyraminxMove> = solution
+            .into_iter()
+            .rev()
+            .map(PyraminxMove::inverse)
+            .collect();
+        for vertex in 0..4 {
+            let amount = rng.gen_range(0..3);
+            if amount > 0 {
+                moves.push(PyraminxMove {
+                    vertex,
+                    tip: true,
+                    amount,
+                });
+            }
+        }
+        return moves;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn parses_moves() {
+        let moves = parse_algorithm("U L' r b'").unwrap();
+        assert_eq!(
+            moves[2],
+            PyraminxMove {
+                vertex: 2,
+                tip: true,
+                amount: 1
+            }
+        );
+        assert_eq!(moves[1].to_string(), "L'");
+        let err = parse_algorithm("U ''").unwrap_err();
+        assert_eq!(err.position, 2);
+    }
+
+    #[test]
+    fn distance_table_covers_all_states() {
+        let distance = &tables().distance;
+        let reachable: Vec<u8> = distance.iter().copied().filter(|&d| d != u8::MAX).collect();
+        // 不含顶角共933120个状态，最多需要11步
+        assert_eq!(reachable.len(), 933_120);
+        assert_eq!(reachable.iter().max(), Some(&11));
+    }
+
+    #[test]
+    fn solution_undoes_scramble() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..5 {
+            let state = scramble(&mut rng)
+                .iter()
+                .fold(PyraminxState::SOLVED, |state, &m| state.apply(m));
+            let solution = solve(&state);
+            assert!(solution.len() <= 11);
+            assert_eq!(
+                solution.iter().fold(state, |state, &m| state.apply(m)),
+                PyraminxState::SOLVED
+            );
+        }
+    }
+}
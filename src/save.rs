@@ -0,0 +1,167 @@
+use crate::cube::*;
+use crate::move_history::MoveHistory;
+use crate::puzzle::{ActiveMove, MoveQueue, PuzzleState};
+use crate::save_data::*;
+use crate::state::CubeState;
+use bevy::prelude::*;
+
+// 保存到存档文件
+#[derive(Debug, Default, Event)]
+pub struct SaveEvent;
+
+// 读取状态，为空时从存档文件读取
+#[derive(Debug, Default, Event)]
+pub struct LoadEvent(pub Option<SaveData>);
+
+// 最近一次保存或读取的结果，用于UI展示
+#[derive(Debug, Default, Resource)]
+pub struct SaveStatus(pub Option<Result<String, String>>);
+
+// 等待魔方重建后应用的存档
+#[derive(Debug, Default, Resource)]
+pub struct PendingLoad(pub Option<SaveData>);
+
+#[allow(clippy::too_many_arguments)]
+pub fn save_cube(
+    mut events: EventReader<SaveEvent>,
+    cube_state: Res<PuzzleState<CubePuzzle>>,
+    history: Res<MoveHistory>,
+    current_scramble: Res<CurrentScramble>,
+    side_move_queue: Res<MoveQueue<CubePuzzle>>,
+    active_side_moves: Res<ActiveMove<CubePuzzle>>,
+    cube_settings: Res<CubeSettings>,
+    mut save_status: ResMut<SaveStatus>,
+) {
+    for _ in events.read() {
+        // 存档只记录块的状态，无法恢复其他谜题或粘块
+        let (PuzzleKind::Cube | PuzzleKind::Cuboid, Some(cube)) =
+            (cube_settings.puzzle, &cube_state.0)
+        else {
+            save_status.0 = Some(Err(format!(
+                "cannot save a {} puzzle",
+                cube_settings.puzzle.name()
+            )));
+            continue;
+        };
+        // 转动过程中逻辑魔方尚未更新
+        if !side_move_queue.0.is_empty() || active_side_moves.0.is_some() {
+            save_status.0 = Some(Err("cube is still moving".to_string()));
+            continue;
+        }
+        let data = SaveData {
+            version: SAVE_VERSION,
+            cube: cube.clone(),
+            history: history.clone(),
+            scramble: current_scramble.0.clone(),
+        };
+        save_status.0 = Some(
+            save_to_file(SAVE_FILE, &data)
+                .map(|_| format!("saved to {}", SAVE_FILE))
+                .map_err(|err| err.to_string()),
+        );
+    }
+}
+
+// 读取存档后按存档的阶数或长方体的层数重建魔方，重建完成后由apply_loaded_cube摆放块
+pub fn load_cube(
+    mut events: EventReader<LoadEvent>,
+    mut cube_settings: ResMut<CubeSettings>,
+    mut side_move_queue: ResMut<MoveQueue<CubePuzzle>>,
+    mut reset_event: EventWriter<ResetEvent>,
+    mut pending_load: ResMut<PendingLoad>,
+    mut save_status: ResMut<SaveStatus>,
+) {
+    for event in events.read() {
+        let result = match &event.0 {
+            Some(data) => Ok(data.clone()),
+            None => load_from_file(SAVE_FILE).inspect(|_| {
+                save_status.0 = Some(Ok(format!("loaded from {}", SAVE_FILE)));
+            }),
+        };
+        match result {
+            Ok(data) => {
+                if data.cube.is_cuboid() {
+                    cube_settings.puzzle = PuzzleKind::Cuboid;
+                    cube_settings.cuboid = data.cube.dims();
+                } else {
+                    cube_settings.puzzle = PuzzleKind::Cube;
+                    cube_settings.cube_order = data.cube.order();
+                }
+                side_move_queue.0.clear();
+                reset_event.send_default();
+                pending_load.0 = Some(data);
+            }
+            Err(err) => save_status.0 = Some(Err(err.to_string())),
+        }
+    }
+}
+
+// 将块直接移动到存档中的位置和朝向，并恢复逻辑魔方、转动历史和打乱公式
+pub fn apply_loaded_cube(
+    mut pending_load: ResMut<PendingLoad>,
+    mut q_pieces: Query<(&Piece, &mut Transform)>,
+    mut cube_state: ResMut<PuzzleState<CubePuzzle>>,
+    mut history: ResMut<MoveHistory>,
+    mut current_scramble: ResMut<CurrentScramble>,
+) {
+    let Some(data) = pending_load.0.take() else {
+        return;
+    };
+    teleport_pieces(&data.cube, &mut q_pieces);
+    cube_state.0 = Some(data.cube);
+    *history = data.history;
+    current_scramble.0 = data.scramble;
+}
+
+pub fn teleport_pieces(state: &CubeState, q_pieces: &mut Query<(&Piece, &mut Transform)>) {
+    for (piece, mut transform) in q_pieces {
+        let home = (piece.init_pos * 2.0).round().as_ivec3().to_array();
+        let Some(cubie) = state.cubies().iter().find(|cubie| cubie.home == home) else {
+            continue;
+        };
+        let [x, y, z] = cubie.orient.map(|axis| IVec3::from_array(axis).as_vec3());
+        transform.translation = IVec3::from_array(cubie.pos).as_vec3() / 2.0;
+        transform.rotation = Quat::from_mat3(&Mat3::from_cols(x, y, z));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save_data(cube: CubeState) -> SaveData {
+        SaveData {
+            version: SAVE_VERSION,
+            cube,
+            history: MoveHistory::default(),
+            scramble: None,
+        }
+    }
+
+    #[test]
+    fn loading_restores_puzzle_kind() {
+        let mut app = App::new();
+        app.add_event::<LoadEvent>()
+            .add_event::<ResetEvent>()
+            .init_resource::<CubeSettings>()
+            .init_resource::<MoveQueue<CubePuzzle>>()
+            .init_resource::<PendingLoad>()
+            .init_resource::<SaveStatus>()
+            .add_systems(Update, load_cube);
+
+        let cuboid = CubeState::cuboid([3, 4, 3]);
+        app.world_mut()
+            .send_event(LoadEvent(Some(save_data(cuboid.clone()))));
+        app.update();
+        let settings = app.world().resource::<CubeSettings>();
+        assert_eq!(settings.puzzle, PuzzleKind::Cuboid);
+        assert_eq!(settings.dims(), cuboid.dims());
+
+        app.world_mut()
+            .send_event(LoadEvent(Some(save_data(CubeState::solved(5)))));
+        app.update();
+        let settings = app.world().resource::<CubeSettings>();
+        assert_eq!(settings.puzzle, PuzzleKind::Cube);
+        assert_eq!(settings.dims(), [5; 3]);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::move_history::MoveHistory;
+use crate::state::CubeState;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+
+// 存档格式版本，格式不兼容时递增；版本2的转动历史按步记录，版本3的历史还记录每步撤销时的转动
+pub const SAVE_VERSION: u32 = 3;
+pub const SAVE_FILE: &str = "rubiks-cube-save.ron";
+
+/// 存档内容：魔方状态、转动历史和打乱公式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    pub cube: CubeState,
+    pub history: MoveHistory,
+    pub scramble: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Serialize(ron::Error),
+    Deserialize(ron::error::SpannedError),
+    UnsupportedVersion(u32),
+    InvalidState,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "{}", err),
+            SaveError::Serialize(err) => write!(f, "{}", err),
+            SaveError::Deserialize(err) => write!(f, "{}", err),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save version {}", version)
+            }
+            SaveError::InvalidState => write!(f, "save file contains an invalid cube state"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl SaveData {
+    pub fn to_ron(&self) -> Result<String, SaveError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(SaveError::Serialize)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, SaveError> {
+        let data: SaveData = ron::from_str(text).map_err(SaveError::Deserialize)?;
+        if data.version != SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(data.version));
+        }
+        if !data.cube.is_valid() || !(2..=12).contains(&data.cube.order()) {
+            return Err(SaveError::InvalidState);
+        }
+        // 转动历史中的层必须在存档的魔方上存在，否则撤销时会转动不存在的层
+        let dims = data.cube.dims();
+        if !data
+            .history
+            .moves()
+            .flatten()
+            .all(|event| event.is_valid(dims))
+        {
+            return Err(SaveError::InvalidState);
+        }
+        Ok(data)
+    }
+}
+
+pub fn save_to_file(path: &str, data: &SaveData) -> Result<(), SaveError> {
+    fs::write(path, data.to_ron()?).map_err(SaveError::Io)
+}
+
+pub fn load_from_file(path: &str) -> Result<SaveData, SaveError> {
+    SaveData::from_ron(&fs::read_to_string(path).map_err(SaveError::Io)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_side_moves;
+    use crate::state::SideMoveEvent;
+
+    fn save_data(cube: CubeState) -> SaveData {
+        SaveData {
+            version: SAVE_VERSION,
+            cube,
+            history: MoveHistory::default(),
+            scramble: Some("R U".to_string()),
+        }
+    }
+
+    // 按步记录公式，撤销时各层反向转动
+    fn record(data: &mut SaveData, algorithm: &str, order: u8) {
+        for layers in parse_side_moves(algorithm, order).unwrap() {
+            let undo = layers
+                .iter()
+                .map(|event| SideMoveEvent {
+                    side: event.side,
+                    rotate: event.rotate.inverse(),
+                })
+                .collect();
+            data.history.record(layers, vec![undo]);
+        }
+    }
+
+    #[test]
+    fn round_trips_through_ron() {
+        let data = save_data(CubeState::cuboid([2, 3, 2]));
+        let loaded = SaveData::from_ron(&data.to_ron().unwrap()).unwrap();
+        assert_eq!(loaded.cube, data.cube);
+        assert_eq!(loaded.scramble, data.scramble);
+
+        let old = data
+            .to_ron()
+            .unwrap()
+            .replace(&format!("version: {}", SAVE_VERSION), "version: 1");
+        assert!(matches!(
+            SaveData::from_ron(&old),
+            Err(SaveError::UnsupportedVersion(1))
+        ));
+    }
+
+    #[test]
+    fn rejects_history_outside_the_cube() {
+        let mut data = save_data(CubeState::solved(3));
+        record(&mut data, "R 2L", 3);
+        assert!(SaveData::from_ron(&data.to_ron().unwrap()).is_ok());
+
+        // 4阶魔方的外层在3阶魔方上不存在
+        let mut data = save_data(CubeState::solved(3));
+        record(&mut data, "R", 4);
+        assert!(matches!(
+            SaveData::from_ron(&data.to_ron().unwrap()),
+            Err(SaveError::InvalidState)
+        ));
+
+        // 2x3x2的侧面不能转动90度
+        let mut data = save_data(CubeState::cuboid([2, 3, 2]));
+        record(&mut data, "R", 2);
+        assert!(matches!(
+            SaveData::from_ron(&data.to_ron().unwrap()),
+            Err(SaveError::InvalidState)
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::cubie::{permutation_parity, CubieCube};
+use crate::notation::{face_axis, Move, MoveKind};
+use crate::solver;
+use crate::state::{quarter_turn_allowed, Face};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// 生成打乱公式：3阶为随机状态打乱，其余阶数为不会相互抵消的随机转动
+pub fn scramble<R: Rng>(rng: &mut R, order: u8) -> Vec<Move> {
+    if order == 3 {
+        random_state_scramble(rng)
+    } else {
+        random_move_scramble(rng, order)
+    }
+}
+
+/// 均匀随机选取一个可还原的状态，求解后取逆得到只含外层转动的打乱公式
+pub fn random_state_scramble<R: Rng>(rng: &mut R) -> Vec<Move> {
+    loop {
+        let cube = random_cubie_cube(rng);
+        let solution = solver::solve(&cube).expect("random cube is always solvable");
+        // WCA要求打乱状态至少需要2步才能还原
+        if solution.len() >= 2 {
+            return solution.into_iter().rev().map(Move::inverse).collect();
+        }
+    }
+}
+
+fn random_cubie_cube<R: Rng>(rng: &mut R) -> CubieCube {
+    let mut cube = CubieCube::SOLVED;
+    cube.cp.shuffle(rng);
+    cube.ep.shuffle(rng);
+    // 角块和棱块排列的奇偶性必须一致
+    if permutation_parity(&cube.cp) != permutation_parity(&cube.ep) {
+        cube.ep.swap(0, 1);
+    }
+    let mut twist = 0;
+    for co in &mut cube.co[..7] {
+        *co = rng.gen_range(0..3);
+        twist += *co;
+    }
+    cube.co[7] = (3 - twist % 3) % 3;
+    let mut flip = 0;
+    for eo in &mut cube.eo[..11] {
+        *eo = rng.gen_range(0..2);
+        flip += *eo;
+    }
+    cube.eo[11] = flip % 2;
+    cube
+}
+
+/// 随机转动打乱，相邻两步不转同一个轴，避免R R'这类相互抵消的转动
+pub fn random_move_scramble<R: Rng>(rng: &mut R, order: u8) -> Vec<Move> {
+    // 2阶固定一个角块，只转R、U、F
+    let faces: &[Face] = if order == 2 {
+        &[Face::R, Face::U, Face::F]
+    } else {
+        &Face::ALL
+    };
+    let length = match order {
+        2 => 11,
+        3 => 25,
+        _ => 20 * (order as usize - 2),
+    };
+    // 大魔方可以转动的最深宽层
+    let max_depth = (order / 2).max(1);
+    let mut moves: Vec<Move> = Vec::with_capacity(length);
+    while moves.len() < length {
+        let face = *faces.choose(rng).unwrap();
+        let same_axis = moves.last().is_some_and(|last| match last.kind {
+            MoveKind::Layer(last_face, _) | MoveKind::Wide(last_face, _) => {
+                last_face as usize % 3 == face as usize % 3
+            }
+            _ => false,
+        });
+        if same_axis {
+            continue;
+        }
+        let depth = rng.gen_range(1..=max_depth);
+        let kind = if depth == 1 {
+            MoveKind::Layer(face, 1)
+        } else {
+            MoveKind::Wide(face, depth)
+        };
+        moves.push(Move {
+            kind,
+            amount: rng.gen_range(1..=3),
+        });
+    }
+    moves
+}
+
+/// 长方体魔方的随机转动打乱：只有1层的方向不转（等于整体转动），2层的方向只转正向的面，
+/// 非正方形的面只转180度
+pub fn cuboid_scramble<R: Rng>(rng: &mut R, dims: [u8; 3]) -> Vec<Move> {
+    let layers = |face: Face| dims[face_axis(face).0.index()];
+    let faces: Vec<Face> = Face::ALL
+        .into_iter()
+        .filter(|&face| match layers(face) {
+            1 => false,
+            2 => face_axis(face).1 > 0.0,
+            _ => true,
+        })
+        .collect();
+    let length = dims.iter().map(|&n| n as usize).sum::<usize>() * 3;
+    let mut moves: Vec<Move> = Vec::with_capacity(length);
+    while moves.len() < length {
+        let face = *faces.choose(rng).unwrap();
+        let axis = face_axis(face).0;
+        let same_axis = moves.last().is_some_and(|last| match last.kind {
+            MoveKind::Layer(last_face, _) | MoveKind::Wide(last_face, _) => {
+                face_axis(last_face).0 == axis
+            }
+            _ => false,
+        });
+        if same_axis {
+            continue;
+        }
+        let depth = rng.gen_range(1..=(layers(face) / 2).max(1));
+        let kind = if depth == 1 {
+            MoveKind::Layer(face, 1)
+        } else {
+            MoveKind::Wide(face, depth)
+        };
+        let amount = if quarter_turn_allowed(axis, dims) {
+            rng.gen_range(1..=3)
+        } else {
+            2
+        };
+        moves.push(Move { kind, amount });
+    }
+    moves
+}
@@ -0,0 +1,6 @@
+use crate::stats::SolveSession;
+use bevy::prelude::*;
+
+/// 本次会话的所有计时成绩，统计由`SolveSession`完成
+#[derive(Debug, Default, Resource, Deref, DerefMut)]
+pub struct Session(pub SolveSession);
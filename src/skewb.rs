@@ -0,0 +1,176 @@
+use crate::color_scheme::ColorScheme;
+use crate::keyboard::KeyBinding;
+use crate::notation::NotationError;
+use crate::skewb_solver::*;
+use crate::twisty::{Twist, TwistAxis, Twisty, TwistyDef, TwistyModel};
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+
+// 面到中心的距离，与三阶魔方大小相同
+const HALF_SIZE: f32 = 1.5;
+
+fn corner_axis(corner: usize) -> Vec3 {
+    IVec3::from_array(CORNERS[AXIS_CORNERS[corner]])
+        .as_vec3()
+        .normalize()
+}
+
+/// 斜转魔方的几何定义，面的顺序为URFDLB，与魔方配色的顺序一致
+pub fn definition(colors: &ColorScheme) -> TwistyDef {
+    TwistyDef {
+        faces: CENTERS
+            .iter()
+            .map(|&center| (IVec3::from_array(center).as_vec3(), HALF_SIZE))
+            .collect(),
+        colors: colors.0.to_vec(),
+        axes: (0..4)
+            .map(|axis| TwistAxis {
+                normal: corner_axis(axis),
+                order: 3,
+                cuts: vec![0.0],
+            })
+            .collect(),
+    }
+}
+
+impl SkewbMove {
+    pub fn to_twist(self) -> Twist {
+        Twist {
+            axis: self.axis,
+            cut: 0,
+            amount: if self.amount == 2 { -1 } else { 1 },
+        }
+    }
+}
+
+/// 按键绑定：R、U、L、B转动，Shift为逆时针
+pub fn key_bindings() -> Vec<KeyBinding> {
+    let keys = [
+        (KeyCode::KeyR, "R"),
+        (KeyCode::KeyU, "U"),
+        (KeyCode::KeyL, "L"),
+        (KeyCode::KeyB, "B"),
+    ];
+    keys.into_iter()
+        .flat_map(|(key, name)| {
+            [
+                KeyBinding::new(key, false, name),
+                KeyBinding::new(key, true, &format!("{}'", name)),
+            ]
+        })
+        .collect()
+}
+
+/// 由谜题模型中各贴纸位置的颜色得到状态，颜色组合不可能出现时返回None
+pub fn state_from_stickers(model: &TwistyModel, colors: &[u8]) -> Option<SkewbState> {
+    let centers = CENTERS.map(IVec3::from_array);
+    let corners = CORNERS.map(IVec3::from_array);
+    let axes = AXES.map(IVec3::from_array);
+    let mut state = SkewbState::SOLVED;
+    for piece in &model.pieces {
+        // 块的位置为其贴纸法向量之和
+        let position = piece
+            .stickers
+            .iter()
+            .map(|&slot| model.stickers[slot].normal)
+            .sum::<Vec3>()
+            .round()
+            .as_ivec3();
+        let faces = piece
+            .stickers
+            .iter()
+            .map(|&slot| centers.get(*colors.get(slot)? as usize).copied())
+            .collect::<Option<Vec<IVec3>>>()?;
+        if let [face] = faces[..] {
+            let slot = centers.iter().position(|&p| p == position)?;
+            state.centers[slot] = centers.iter().position(|&p| p == face)? as u8;
+            continue;
+        }
+        // 三个颜色确定是哪个角块，U/D色贴纸所在的坐标轴即为朝向
+        let slot = corners.iter().position(|&p| p == position)?;
+        let home = faces.iter().sum::<IVec3>();
+        state.cp[slot] = corners.iter().position(|&p| p == home)? as u8;
+        let up_down = faces.iter().position(|face| face.y != 0)?;
+        let axis = model.stickers[piece.stickers[up_down]]
+            .normal
+            .round()
+            .as_ivec3()
+            .abs();
+        state.co[slot] = axes.iter().position(|&a| a == axis)? as u8;
+    }
+    state.is_reachable().then_some(state)
+}
+
+pub struct Skewb;
+
+impl Twisty for Skewb {
+    type State = SkewbState;
+    type Move = SkewbMove;
+
+    fn definition(&self, colors: &ColorScheme) -> TwistyDef {
+        definition(colors)
+    }
+
+    fn to_twist(&self, m: SkewbMove) -> Twist {
+        m.to_twist()
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<SkewbMove>, NotationError> {
+        parse_algorithm(input)
+    }
+
+    fn scramble(&self, rng: &mut StdRng) -> Vec<SkewbMove> {
+        scramble(rng)
+    }
+
+    fn key_bindings(&self) -> Vec<KeyBinding> {
+        key_bindings()
+    }
+
+    fn warm_up(&self) {
+        warm_up()
+    }
+
+    fn read_state(&self, model: &TwistyModel, stickers: &[u8]) -> Option<SkewbState> {
+        state_from_stickers(model, stickers)
+    }
+
+    fn solve(&self, state: &SkewbState) -> Option<Vec<SkewbMove>> {
+        Some(solve(state))
+    }
+
+    fn can_solve(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn formats_moves() {
+        let moves = parse_algorithm("R U' L, B'").unwrap();
+        assert_eq!(Skewb.format(&moves), "R U' L B'");
+    }
+
+    #[test]
+    fn solves_scrambled_stickers() {
+        let model = TwistyModel::new(&definition(&ColorScheme::western())).unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..5 {
+            let mut stickers = model.solved_state();
+            for m in Skewb.scramble(&mut rng) {
+                model.apply(&mut stickers, m.to_twist());
+            }
+            let state = state_from_stickers(&model, &stickers).unwrap();
+            let solution = solve(&state);
+            assert!(solution.len() <= 11);
+            for m in solution {
+                model.apply(&mut stickers, m.to_twist());
+            }
+            assert!(model.is_solved(&stickers));
+        }
+    }
+}
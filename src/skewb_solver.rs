@@ -0,0 +1,354 @@
+use crate::notation::{parse_letter_moves, NotationError};
+use rand::Rng;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::OnceLock;
+
+// 转动名称，下标即轴编号；WCA记号中R、U、L、B分别绕DRB、ULB、DLF、DLB角转动，UFR角固定不动
+pub const AXIS_NAMES: [char; 4] = ['R', 'U', 'L', 'B'];
+pub const AXIS_CORNERS: [usize; 4] = [3, 1, 2, 7];
+
+// 角块位置：UFR ULB DLF DRB UFL URB DRF DLB
+pub const CORNERS: [[i32; 3]; 8] = [
+    [1, 1, 1],
+    [-1, 1, -1],
+    [-1, -1, 1],
+    [1, -1, -1],
+    [-1, 1, 1],
+    [1, 1, -1],
+    [1, -1, 1],
+    [-1, -1, -1],
+];
+
+// 中心块位置，顺序为URFDLB
+pub const CENTERS: [[i32; 3]; 6] = [
+    [0, 1, 0],
+    [1, 0, 0],
+    [0, 0, 1],
+    [0, -1, 0],
+    [-1, 0, 0],
+    [0, 0, -1],
+];
+
+// 角块朝向为其U/D色贴纸所在的坐标轴
+pub const AXES: [[i32; 3]; 3] = [[0, 1, 0], [0, 0, 1], [1, 0, 0]];
+
+// 打乱状态至少需要的步数，排除过于简单的状态
+const MIN_SCRAMBLE_LENGTH: usize = 7;
+
+fn dot(a: [i32; 3], b: [i32; 3]) -> i32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [i32; 3], b: [i32; 3]) -> [i32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// 斜转魔方的一步转动，amount为从角看顺时针转动的次数（1、2）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkewbMove {
+    pub axis: usize,
+    pub amount: u8,
+}
+
+impl SkewbMove {
+    pub fn inverse(self) -> Self {
+        Self {
+            amount: (3 - self.amount % 3) % 3,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for SkewbMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", AXIS_NAMES[self.axis])?;
+        if self.amount == 2 {
+            write!(f, "'")?;
+        }
+        Ok(())
+    }
+}
+
+/// 解析如`R U' L B'`的公式
+pub fn parse_algorithm(input: &str) -> Result<Vec<SkewbMove>, NotationError> {
+    let moves = parse_letter_moves(input, |c| AXIS_NAMES.iter().position(|&name| name == c))?;
+    Ok(moves
+        .into_iter()
+        .map(|(axis, prime)| SkewbMove {
+            axis,
+            amount: if prime { 2 } else { 1 },
+        })
+        .collect())
+}
+
+// 一次顺时针转动后各位置的去向，以及转动的角块朝向（坐标轴）的变化
+#[derive(Debug, Clone, Copy)]
+struct SkewbTurn {
+    corner_turned: [bool; 8],
+    corner_target: [usize; 8],
+    center_target: [usize; 6],
+    axis_target: [u8; 3],
+}
+
+impl SkewbTurn {
+    fn new(axis: usize) -> Self {
+        let d = CORNERS[AXIS_CORNERS[axis]];
+        let turned = |p: [i32; 3]| dot(d, p) > 0;
+        // 绕角的对角线转-120°：R v = (d(d·v) - v - d×v) / 2
+        let rotate = |v: [i32; 3]| {
+            let c = cross(d, v);
+            std::array::from_fn(|k| (d[k] * dot(d, v) - v[k] - c[k]) / 2)
+        };
+        // 转动的一半中的块换到新位置，另一半不动
+        let target = |positions: &[[i32; 3]], i: usize| {
+            if turned(positions[i]) {
+                let moved = rotate(positions[i]);
+                positions.iter().position(|&p| p == moved).unwrap()
+            } else {
+                i
+            }
+        };
+        Self {
+            corner_turned: CORNERS.map(turned),
+            corner_target: std::array::from_fn(|i| target(&CORNERS, i)),
+            center_target: std::array::from_fn(|i| target(&CENTERS, i)),
+            axis_target: std::array::from_fn(|i| {
+                let moved = rotate(AXES[i]).map(i32::abs);
+                AXES.iter().position(|&a| a == moved).unwrap() as u8
+            }),
+        }
+    }
+}
+
+/// 斜转魔方的状态：角块的排列和朝向，中心块的排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SkewbState {
+    pub cp: [u8; 8],
+    pub co: [u8; 8],
+    pub centers: [u8; 6],
+}
+
+impl SkewbState {
+    pub const SOLVED: SkewbState = SkewbState {
+        cp: [0, 1, 2, 3, 4, 5, 6, 7],
+        co: [0; 8],
+        centers: [0, 1, 2, 3, 4, 5],
+    };
+
+    fn turn(&self, turn: &SkewbTurn) -> Self {
+        let mut next = *self;
+        for slot in 0..8 {
+            let target = turn.corner_target[slot];
+            next.cp[target] = self.cp[slot];
+            next.co[target] = if turn.corner_turned[slot] {
+                turn.axis_target[self.co[slot] as usize]
+            } else {
+                self.co[slot]
+            };
+        }
+        for slot in 0..6 {
+            next.centers[turn.center_target[slot]] = self.centers[slot];
+        }
+        next
+    }
+
+    pub fn apply(&self, m: SkewbMove) -> Self {
+        let turn = &tables().turns[m.axis];
+        let mut state = *self;
+        for _ in 0..m.amount {
+            state = state.turn(turn);
+        }
+        state
+    }
+
+    // 角块部分，与中心块部分分别编号
+    fn corners(&self) -> ([u8; 8], [u8; 8]) {
+        (self.cp, self.co)
+    }
+
+    /// 状态是否能由还原状态转到
+    pub fn is_reachable(&self) -> bool {
+        let tables = tables();
+        tables.corners.index.contains_key(&self.corners())
+            && tables.centers.index.contains_key(&self.centers)
+    }
+}
+
+// 从还原状态出发枚举某一部分所有可达的状态，返回编号、状态列表和转动表
+struct Coordinate<T> {
+    index: HashMap<T, u32>,
+    states: Vec<T>,
+    moves: Vec<[u32; 4]>,
+}
+
+impl<T: Copy + Eq + Hash> Coordinate<T> {
+    fn enumerate(solved: T, turns: &[SkewbTurn; 4], turn: fn(&T, &SkewbTurn) -> T) -> Self {
+        let mut coord = Self {
+            index: HashMap::from([(solved, 0)]),
+            states: vec![solved],
+            moves: Vec::new(),
+        };
+        let mut i = 0;
+        while i < coord.states.len() {
+            let state = coord.states[i];
+            let next = turns.map(|t| {
+                let next = turn(&state, &t);
+                let len = coord.index.len() as u32;
+                *coord.index.entry(next).or_insert_with(|| {
+                    coord.states.push(next);
+                    len
+                })
+            });
+            coord.moves.push(next);
+            i += 1;
+        }
+        coord
+    }
+}
+
+struct Tables {
+    turns: [SkewbTurn; 4],
+    corners: Coordinate<([u8; 8], [u8; 8])>,
+    centers: Coordinate<[u8; 6]>,
+    // 每个状态到还原状态的最少步数，下标为角块编号*中心块状态数+中心块编号
+    distance: Vec<u8>,
+}
+
+/// 提前生成距离表，避免第一次打乱时卡顿
+pub fn warm_up() {
+    tables();
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let turns = [0, 1, 2, 3].map(SkewbTurn::new);
+        let corners = Coordinate::enumerate(SkewbState::SOLVED.corners(), &turns, |c, t| {
+            SkewbState {
+                cp: c.0,
+                co: c.1,
+                ..SkewbState::SOLVED
+            }
+            .turn(t)
+            .corners()
+        });
+        let centers = Coordinate::enumerate(SkewbState::SOLVED.centers, &turns, |c, t| {
+            SkewbState {
+                centers: *c,
+                ..SkewbState::SOLVED
+            }
+            .turn(t)
+            .centers
+        });
+
+        // 从还原状态广度优先搜索
+        let n_centers = centers.states.len();
+        let mut distance = vec![u8::MAX; corners.states.len() * n_centers];
+        distance[0] = 0;
+        let mut queue = VecDeque::from([0]);
+        while let Some(index) = queue.pop_front() {
+            let (corner, center) = (index / n_centers, index % n_centers);
+            for axis in 0..4 {
+                let (mut k, mut c) = (corner, center);
+                // 顺时针和逆时针各算一步
+                for _ in 0..2 {
+                    k = corners.moves[k][axis] as usize;
+                    c = centers.moves[c][axis] as usize;
+                    let next = k * n_centers + c;
+                    if distance[next] == u8::MAX {
+                        distance[next] = distance[index] + 1;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        Tables {
+            turns,
+            corners,
+            centers,
+            distance,
+        }
+    })
+}
+
+fn state_index(tables: &Tables, state: &SkewbState) -> usize {
+    let corner = tables.corners.index[&state.corners()] as usize;
+    let center = tables.centers.index[&state.centers] as usize;
+    corner * tables.centers.states.len() + center
+}
+
+/// 求最少步数的解法
+pub fn solve(state: &SkewbState) -> Vec<SkewbMove> {
+    let tables = tables();
+    let mut state = *state;
+    let mut solution = Vec::new();
+    while tables.distance[state_index(tables, &state)] > 0 {
+        let current = tables.distance[state_index(tables, &state)];
+        let (m, next) = (0..4)
+            .flat_map(|axis| [1, 2].map(|amount| SkewbMove { axis, amount }))
+            .map(|m| (m, state.apply(m)))
+            .find(|(_, next)| tables.distance[state_index(tables, next)] < current)
+            .expect("distance table covers every reachable state");
+        solution.push(m);
+        state = next;
+    }
+    solution
+}
+
+// 在距离表中均匀地随机选取一个可达状态
+fn random_state<R: Rng>(rng: &mut R) -> SkewbState {
+    let tables = tables();
+    loop {
+        let index = rng.gen_range(0..tables.distance.len());
+        if tables.distance[index] == u8::MAX {
+            continue;
+        }
+        let n_centers = tables.centers.states.len();
+        let (cp, co) = tables.corners.states[index / n_centers];
+        return SkewbState {
+            cp,
+            co,
+            centers: tables.centers.states[index % n_centers],
+        };
+    }
+}
+
+/// 随机状态打乱：求解随机状态后取逆
+pub fn scramble<R: Rng>(rng: &mut R) -> Vec<SkewbMove> {
+    loop {
+        let solution = solve(&random_state(rng));
+        if solution.len() >= MIN_SCRAMBLE_LENGTH {
+            return solution.into_iter().rev().map(SkewbMove::inverse).collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_moves() {
+        let moves = parse_algorithm("R U' L, B'").unwrap();
+        assert_eq!(moves[1], SkewbMove { axis: 1, amount: 2 });
+        assert_eq!(moves[1].to_string(), "U'");
+        let err = parse_algorithm("R F").unwrap_err();
+        assert_eq!(err.position, 2);
+    }
+
+    #[test]
+    fn distance_table_covers_all_states() {
+        let distance = &tables().distance;
+        let reachable: Vec<u8> = distance.iter().copied().filter(|&d| d != u8::MAX).collect();
+        // 斜转魔方共3149280个状态，最多需要11步
+        assert_eq!(reachable.len(), 3_149_280);
+        assert_eq!(reachable.iter().max(), Some(&11));
+    }
+}
@@ -0,0 +1,521 @@
+use crate::cubie::{CubieCube, BASIC_MOVES};
+use crate::notation::{Move, MoveKind};
+use crate::state::{CubeState, Face};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::OnceLock;
+
+// 坐标取值范围
+const N_TWIST: usize = 2187;
+const N_FLIP: usize = 2048;
+const N_SLICE: usize = 495;
+const N_CORNERS: usize = 40320;
+const N_UD_EDGES: usize = 40320;
+const N_SLICE_SORTED: usize = 24;
+
+// 18种转动，下标为3*面+次数-1，面顺序为URFDLB
+const N_MOVES: usize = 18;
+// 第二阶段可用的转动：U、D任意，其余面只能转180度
+const PHASE2_MOVES: [usize; 10] = [0, 1, 2, 4, 7, 9, 10, 11, 13, 16];
+
+// 求解的目标步数
+const TARGET_LENGTH: usize = 22;
+// 每次搜索的节点上限，超过后放宽步数限制
+const NODE_LIMIT: u64 = 3_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveError {
+    // 只支持3阶魔方，记录实际的x/y/z层数
+    UnsupportedShape([u8; 3]),
+    // 状态无法还原
+    InvalidState,
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::UnsupportedShape([x, y, z]) => {
+                write!(f, "the solver supports 3x3x3 only, got {}x{}x{}", x, y, z)
+            }
+            SolveError::InvalidState => write!(f, "the cube state is not solvable"),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+/// 求解逻辑魔方，返回以当前朝向为准的外层转动序列
+pub fn solve_state(state: &CubeState) -> Result<Vec<Move>, SolveError> {
+    let facelets = state
+        .oriented_facelets()
+        .filter(|_| state.dims() == [3; 3])
+        .ok_or(SolveError::UnsupportedShape(state.dims()))?;
+    let cube = CubieCube::from_facelets(&facelets).map_err(|_| SolveError::InvalidState)?;
+    solve(&cube).ok_or(SolveError::InvalidState)
+}
+
+/// Kociemba两阶段算法，先在目标步数内搜索，超出节点上限后逐步放宽
+pub fn solve(cube: &CubieCube) -> Option<Vec<Move>> {
+    if cube.verify().is_err() {
+        return None;
+    }
+    let tables = tables();
+    for (max_length, node_limit) in [
+        (TARGET_LENGTH, NODE_LIMIT),
+        (TARGET_LENGTH + 2, NODE_LIMIT),
+        (30, u64::MAX),
+    ] {
+        let mut search = Search {
+            tables,
+            cube: *cube,
+            path: Vec::new(),
+            max_length,
+            nodes: 0,
+            node_limit,
+        };
+        if let Some(solution) = search.run() {
+            return Some(
+                solution
+                    .into_iter()
+                    .map(|m| Move {
+                        kind: MoveKind::Layer(Face::ALL[m / 3], 1),
+                        amount: (m % 3 + 1) as u8,
+                    })
+                    .collect(),
+            );
+        }
+    }
+    None
+}
+
+/// 提前生成转动表和剪枝表，避免第一次求解时卡顿
+pub fn warm_up() {
+    tables();
+}
+
+struct Tables {
+    twist_move: Vec<u16>,
+    flip_move: Vec<u16>,
+    slice_move: Vec<u16>,
+    corners_move: Vec<u16>,
+    ud_edges_move: Vec<u16>,
+    slice_sorted_move: Vec<u8>,
+    twist_slice_prune: Vec<u8>,
+    flip_slice_prune: Vec<u8>,
+    corners_slice_prune: Vec<u8>,
+    ud_edges_slice_prune: Vec<u8>,
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(Tables::new)
+}
+
+impl Tables {
+    fn new() -> Self {
+        let twist_move = move_table(N_TWIST, &all_moves(), set_twist, twist);
+        let flip_move = move_table(N_FLIP, &all_moves(), set_flip, flip);
+        let slice_move = move_table(N_SLICE, &all_moves(), set_slice, slice);
+        let corners_move = move_table(N_CORNERS, &PHASE2_MOVES, set_corners, corners);
+        let ud_edges_move = move_table(N_UD_EDGES, &PHASE2_MOVES, set_ud_edges, ud_edges);
+        let slice_sorted_move: Vec<u8> = move_table(
+            N_SLICE_SORTED,
+            &PHASE2_MOVES,
+            set_slice_sorted,
+            slice_sorted,
+        )
+        .into_iter()
+        .map(|v| v as u8)
+        .collect();
+
+        let twist_slice_prune = prune_table(N_TWIST, N_SLICE, N_MOVES, |a, b, m| {
+            (
+                twist_move[a * N_MOVES + m] as usize,
+                slice_move[b * N_MOVES + m] as usize,
+            )
+        });
+        let flip_slice_prune = prune_table(N_FLIP, N_SLICE, N_MOVES, |a, b, m| {
+            (
+                flip_move[a * N_MOVES + m] as usize,
+                slice_move[b * N_MOVES + m] as usize,
+            )
+        });
+        let n2 = PHASE2_MOVES.len();
+        let corners_slice_prune = prune_table(N_CORNERS, N_SLICE_SORTED, n2, |a, b, m| {
+            (
+                corners_move[a * n2 + m] as usize,
+                slice_sorted_move[b * n2 + m] as usize,
+            )
+        });
+        let ud_edges_slice_prune = prune_table(N_UD_EDGES, N_SLICE_SORTED, n2, |a, b, m| {
+            (
+                ud_edges_move[a * n2 + m] as usize,
+                slice_sorted_move[b * n2 + m] as usize,
+            )
+        });
+
+        Self {
+            twist_move,
+            flip_move,
+            slice_move,
+            corners_move,
+            ud_edges_move,
+            slice_sorted_move,
+            twist_slice_prune,
+            flip_slice_prune,
+            corners_slice_prune,
+            ud_edges_slice_prune,
+        }
+    }
+}
+
+fn all_moves() -> Vec<usize> {
+    (0..N_MOVES).collect()
+}
+
+fn apply_move(cube: &CubieCube, m: usize) -> CubieCube {
+    let mut result = *cube;
+    for _ in 0..=m % 3 {
+        result = result.multiply(&BASIC_MOVES[m / 3]);
+    }
+    result
+}
+
+// 转动表，table[coord * moves.len() + i]为坐标coord执行moves[i]后的坐标
+fn move_table(
+    size: usize,
+    moves: &[usize],
+    set: fn(&mut CubieCube, usize),
+    get: fn(&CubieCube) -> usize,
+) -> Vec<u16> {
+    let mut table = vec![0; size * moves.len()];
+    for coord in 0..size {
+        let mut cube = CubieCube::SOLVED;
+        set(&mut cube, coord);
+        for (i, &m) in moves.iter().enumerate() {
+            table[coord * moves.len() + i] = get(&apply_move(&cube, m)) as u16;
+        }
+    }
+    table
+}
+
+// 两个坐标组合的剪枝表，记录到还原状态的最少步数
+fn prune_table(
+    size_a: usize,
+    size_b: usize,
+    n_moves: usize,
+    next: impl Fn(usize, usize, usize) -> (usize, usize),
+) -> Vec<u8> {
+    let mut table = vec![u8::MAX; size_a * size_b];
+    let mut queue = VecDeque::new();
+    table[0] = 0;
+    queue.push_back(0);
+    while let Some(index) = queue.pop_front() {
+        let (a, b) = (index / size_b, index % size_b);
+        let depth = table[index];
+        for m in 0..n_moves {
+            let (next_a, next_b) = next(a, b, m);
+            let next_index = next_a * size_b + next_b;
+            if table[next_index] == u8::MAX {
+                table[next_index] = depth + 1;
+                queue.push_back(next_index);
+            }
+        }
+    }
+    table
+}
+
+struct Search<'a> {
+    tables: &'a Tables,
+    cube: CubieCube,
+    path: Vec<usize>,
+    max_length: usize,
+    nodes: u64,
+    node_limit: u64,
+}
+
+impl Search<'_> {
+    fn run(&mut self) -> Option<Vec<usize>> {
+        let (tw, fl, sl) = (twist(&self.cube), flip(&self.cube), slice(&self.cube));
+        for depth in 0..=self.max_length {
+            if self.phase1(tw, fl, sl, depth) {
+                return Some(self.path.clone());
+            }
+            if self.nodes > self.node_limit {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn phase1(&mut self, tw: usize, fl: usize, sl: usize, depth: usize) -> bool {
+        self.nodes += 1;
+        if self.nodes > self.node_limit {
+            return false;
+        }
+        if depth == 0 {
+            // 第一阶段结束时最后一步若是第二阶段的转动，说明存在更短的第一阶段解
+            let redundant = self.path.last().is_some_and(|m| PHASE2_MOVES.contains(m));
+            return tw == 0 && fl == 0 && sl == 0 && !redundant && self.start_phase2();
+        }
+        let t = self.tables;
+        let estimate =
+            t.twist_slice_prune[tw * N_SLICE + sl].max(t.flip_slice_prune[fl * N_SLICE + sl]);
+        if estimate as usize > depth {
+            return false;
+        }
+        for m in 0..N_MOVES {
+            if self.is_redundant(m) {
+                continue;
+            }
+            self.path.push(m);
+            if self.phase1(
+                t.twist_move[tw * N_MOVES + m] as usize,
+                t.flip_move[fl * N_MOVES + m] as usize,
+                t.slice_move[sl * N_MOVES + m] as usize,
+                depth - 1,
+            ) {
+                return true;
+            }
+            self.path.pop();
+        }
+        false
+    }
+
+    fn start_phase2(&mut self) -> bool {
+        let mut cube = self.cube;
+        for &m in &self.path {
+            cube = apply_move(&cube, m);
+        }
+        let (co, ed, ss) = (corners(&cube), ud_edges(&cube), slice_sorted(&cube));
+        let phase1_length = self.path.len();
+        for depth in 0..=self.max_length - phase1_length {
+            if self.phase2(co, ed, ss, depth) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn phase2(&mut self, co: usize, ed: usize, ss: usize, depth: usize) -> bool {
+        self.nodes += 1;
+        if co == 0 && ed == 0 && ss == 0 {
+            return true;
+        }
+        let t = self.tables;
+        let estimate = t.corners_slice_prune[co * N_SLICE_SORTED + ss]
+            .max(t.ud_edges_slice_prune[ed * N_SLICE_SORTED + ss]);
+        if estimate as usize > depth {
+            return false;
+        }
+        let n2 = PHASE2_MOVES.len();
+        for (i, &m) in PHASE2_MOVES.iter().enumerate() {
+            if self.is_redundant(m) {
+                continue;
+            }
+            self.path.push(m);
+            if self.phase2(
+                t.corners_move[co * n2 + i] as usize,
+                t.ud_edges_move[ed * n2 + i] as usize,
+                t.slice_sorted_move[ss * n2 + i] as usize,
+                depth - 1,
+            ) {
+                return true;
+            }
+            self.path.pop();
+        }
+        false
+    }
+
+    // 同一个面不连续转动，相对的两个面只按固定顺序转动
+    fn is_redundant(&self, m: usize) -> bool {
+        let Some(&last) = self.path.last() else {
+            return false;
+        };
+        let (face, last_face) = (m / 3, last / 3);
+        face == last_face || (face % 3 == last_face % 3 && face < last_face)
+    }
+}
+
+// 角块朝向坐标
+fn twist(cube: &CubieCube) -> usize {
+    cube.co[..7].iter().fold(0, |acc, &o| acc * 3 + o as usize)
+}
+
+fn set_twist(cube: &mut CubieCube, mut value: usize) {
+    let mut sum = 0;
+    for i in (0..7).rev() {
+        cube.co[i] = (value % 3) as u8;
+        sum += cube.co[i];
+        value /= 3;
+    }
+    cube.co[7] = (3 - sum % 3) % 3;
+}
+
+// 棱块朝向坐标
+fn flip(cube: &CubieCube) -> usize {
+    cube.eo[..11].iter().fold(0, |acc, &o| acc * 2 + o as usize)
+}
+
+fn set_flip(cube: &mut CubieCube, mut value: usize) {
+    let mut sum = 0;
+    for i in (0..11).rev() {
+        cube.eo[i] = (value % 2) as u8;
+        sum += cube.eo[i];
+        value /= 2;
+    }
+    cube.eo[11] = sum % 2;
+}
+
+// 中层棱块（FR、FL、BL、BR）所在位置的组合坐标，不考虑顺序
+fn slice(cube: &CubieCube) -> usize {
+    let mut value = 0;
+    let mut x = 0;
+    for j in (0..12).rev() {
+        if cube.ep[j] >= 8 {
+            value += binomial(11 - j, x + 1);
+            x += 1;
+        }
+    }
+    value
+}
+
+fn set_slice(cube: &mut CubieCube, mut value: usize) {
+    let mut ep = [u8::MAX; 12];
+    let mut x = 4;
+    for (j, e) in ep.iter_mut().enumerate() {
+        if x > 0 && value >= binomial(11 - j, x) {
+            *e = 12 - x as u8;
+            value -= binomial(11 - j, x);
+            x -= 1;
+        }
+    }
+    let mut other = 0;
+    for e in ep.iter_mut() {
+        if *e == u8::MAX {
+            *e = other;
+            other += 1;
+        }
+    }
+    cube.ep = ep;
+}
+
+// 角块排列坐标
+fn corners(cube: &CubieCube) -> usize {
+    permutation_rank(&cube.cp)
+}
+
+fn set_corners(cube: &mut CubieCube, value: usize) {
+    permutation_unrank(value, &mut cube.cp);
+}
+
+// 第二阶段U、D层8个棱块的排列坐标
+fn ud_edges(cube: &CubieCube) -> usize {
+    permutation_rank(&cube.ep[..8])
+}
+
+fn set_ud_edges(cube: &mut CubieCube, value: usize) {
+    permutation_unrank(value, &mut cube.ep[..8]);
+}
+
+// 第二阶段中层4个棱块的排列坐标
+fn slice_sorted(cube: &CubieCube) -> usize {
+    let edges: Vec<u8> = cube.ep[8..].iter().map(|e| e - 8).collect();
+    permutation_rank(&edges)
+}
+
+fn set_slice_sorted(cube: &mut CubieCube, value: usize) {
+    let mut edges = [0; 4];
+    permutation_unrank(value, &mut edges);
+    for (i, e) in edges.into_iter().enumerate() {
+        cube.ep[8 + i] = e + 8;
+    }
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
+}
+
+// 排列的康托展开，还原状态为0
+fn permutation_rank(perm: &[u8]) -> usize {
+    let n = perm.len();
+    let mut rank = 0;
+    for i in 0..n {
+        let smaller = perm[i + 1..].iter().filter(|&&p| p < perm[i]).count();
+        rank = rank * (n - i) + smaller;
+    }
+    rank
+}
+
+fn permutation_unrank(mut rank: usize, perm: &mut [u8]) {
+    let n = perm.len();
+    let mut digits = vec![0; n];
+    for i in (0..n).rev() {
+        digits[i] = rank % (n - i);
+        rank /= n - i;
+    }
+    let mut available: Vec<u8> = (0..n as u8).collect();
+    for (p, digit) in perm.iter_mut().zip(digits) {
+        *p = available.remove(digit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_algorithm;
+    use crate::scramble;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn unsupported_shape_reports_dims() {
+        let err = solve_state(&CubeState::cuboid([3, 3, 2])).unwrap_err();
+        assert_eq!(err, SolveError::UnsupportedShape([3, 3, 2]));
+        assert_eq!(err.to_string(), "the solver supports 3x3x3 only, got 3x3x2");
+        assert_eq!(
+            solve_state(&CubeState::solved(4)).unwrap_err(),
+            SolveError::UnsupportedShape([4, 4, 4])
+        );
+        assert!(solve_state(&CubeState::cuboid([3, 3, 3])).is_ok());
+    }
+
+    fn apply(state: &mut CubeState, moves: &[Move]) {
+        for m in moves {
+            for event in m.to_side_moves(3) {
+                state.apply_side_move(&event);
+            }
+        }
+    }
+
+    #[test]
+    fn solves_random_states() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..5 {
+            let mut state = CubeState::solved(3);
+            apply(&mut state, &scramble::random_state_scramble(&mut rng));
+            let solution = solve_state(&state).unwrap();
+            assert!(
+                solution.len() <= 22,
+                "solution too long: {}",
+                solution.len()
+            );
+            apply(&mut state, &solution);
+            assert!(state.is_solved());
+        }
+    }
+
+    #[test]
+    fn solves_rotated_cubes() {
+        // 整体转动后中心块不在标准位置，解法按中心块的颜色还原
+        let mut state = CubeState::solved(3);
+        apply(&mut state, &parse_algorithm("x R U y' F2", 3).unwrap());
+        let solution = solve_state(&state).unwrap();
+        apply(&mut state, &solution);
+        assert!(state.is_solved());
+        assert!(solve_state(&CubeState::solved(3)).unwrap().is_empty());
+    }
+}
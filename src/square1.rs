@@ -0,0 +1,675 @@
+use crate::color_scheme::ColorScheme;
+use crate::cube::*;
+use crate::moving::*;
+use crate::notation::{NotationError, NotationErrorKind};
+use crate::puzzle::{
+    predicted_state, ActiveMove, MoveEvent, MoveQueue, MoveSource, Puzzle, PuzzleState,
+};
+use crate::state::Face;
+use crate::twisty::{polygon_mesh, sticker_polygon};
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::f32::consts::PI;
+use std::fmt;
+
+// 魔方大小与三阶相同，上下层和中层各高1
+const HALF_SIZE: f32 = 1.5;
+const LAYER_HEIGHT: f32 = 1.0;
+
+// 每层12个单位，每个单位30度；棱块占1个单位，角块占2个单位
+const UNITS: usize = 12;
+const UNIT_ANGLE: f32 = PI / 6.0;
+
+// 切片平面经过上层第0、1个单位之间，从前方略偏左处切到后方略偏右处，
+// 转动的右半边包含上层的0、7~11和下层的0~5号单位
+const SLICE_ANGLE: f32 = -PI / 12.0;
+
+// 打乱的(x,y)/组数
+const SCRAMBLE_LENGTH: usize = 12;
+
+/// 切片转动的轴，垂直于切片平面指向右半边
+fn slice_normal() -> Vec3 {
+    let angle = SLICE_ANGLE + PI / 2.0;
+    Vec3::new(angle.sin(), 0.0, angle.cos())
+}
+
+/// 一步转动：上层和下层分别从各自的面看顺时针转动若干个30度，或者转动右半边180度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Square1Move {
+    Turn { top: i32, bottom: i32 },
+    Slice,
+}
+
+impl fmt::Display for Square1Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Square1Move::Turn { top, bottom } => write!(f, "({},{})", top, bottom),
+            Square1Move::Slice => write!(f, "/"),
+        }
+    }
+}
+
+// 转动量规范到-5..=6
+fn normalize_units(units: i32) -> i32 {
+    let units = units.rem_euclid(UNITS as i32);
+    if units > 6 {
+        units - UNITS as i32
+    } else {
+        units
+    }
+}
+
+/// 解析WCA记号，如`(1,0)/ (-1,3)/ (0,-3)/`
+pub fn parse_algorithm(input: &str) -> Result<Vec<Square1Move>, NotationError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut moves = Vec::new();
+    let mut pos = 0;
+    let error = |position: usize, chars: &[char]| NotationError {
+        position,
+        kind: match chars.get(position) {
+            Some(&c) => NotationErrorKind::UnexpectedChar(c),
+            None => NotationErrorKind::UnexpectedEnd,
+        },
+    };
+    let skip_spaces = |pos: &mut usize| {
+        while chars.get(*pos).is_some_and(|c| c.is_whitespace()) {
+            *pos += 1;
+        }
+    };
+    // 带符号的整数
+    let number = |pos: &mut usize| -> Result<i32, NotationError> {
+        skip_spaces(pos);
+        let start = *pos;
+        if matches!(chars.get(*pos), Some('-' | '+')) {
+            *pos += 1;
+        }
+        while chars.get(*pos).is_some_and(|c| c.is_ascii_digit()) {
+            *pos += 1;
+        }
+        let text: String = chars[start..*pos].iter().collect();
+        text.parse().map_err(|_| match chars.get(start) {
+            Some(c) if c.is_ascii_digit() || *c == '-' || *c == '+' => NotationError {
+                position: start,
+                kind: NotationErrorKind::InvalidCount(text.clone()),
+            },
+            _ => error(start, &chars),
+        })
+    };
+    while let Some(&c) = chars.get(pos) {
+        match c {
+            c if c.is_whitespace() => pos += 1,
+            '/' => {
+                pos += 1;
+                moves.push(Square1Move::Slice);
+            }
+            '(' => {
+                let open = pos;
+                pos += 1;
+                let top = number(&mut pos)?;
+                skip_spaces(&mut pos);
+                if chars.get(pos) != Some(&',') {
+                    return Err(error(pos, &chars));
+                }
+                pos += 1;
+                let bottom = number(&mut pos)?;
+                skip_spaces(&mut pos);
+                if chars.get(pos) != Some(&')') {
+                    return Err(NotationError {
+                        position: open,
+                        kind: NotationErrorKind::UnclosedParen,
+                    });
+                }
+                pos += 1;
+                moves.push(Square1Move::Turn {
+                    top: normalize_units(top),
+                    bottom: normalize_units(bottom),
+                });
+            }
+            _ => return Err(error(pos, &chars)),
+        }
+    }
+    Ok(moves)
+}
+
+pub fn format_algorithm(moves: &[Square1Move]) -> String {
+    let mut text = String::new();
+    for m in moves {
+        if matches!(m, Square1Move::Turn { .. }) && !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(&m.to_string());
+    }
+    text
+}
+
+/// 上下层每个单位上的块编号，角块占两个相邻单位；上层从前方开始从上面看顺时针编号，下层从下面看顺时针编号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Square1State {
+    pub top: [u8; UNITS],
+    pub bottom: [u8; UNITS],
+    // 中层右半边是否翻转
+    pub middle_flipped: bool,
+}
+
+// 块编号：上层角块0~3、下层角块4~7，上层棱块8~11、下层棱块12~15
+const fn solved_layer(corner: u8, edge: u8) -> [u8; UNITS] {
+    [
+        edge,
+        corner,
+        corner,
+        edge + 1,
+        corner + 1,
+        corner + 1,
+        edge + 2,
+        corner + 2,
+        corner + 2,
+        edge + 3,
+        corner + 3,
+        corner + 3,
+    ]
+}
+
+impl Square1State {
+    pub const SOLVED: Square1State = Square1State {
+        top: solved_layer(0, 8),
+        bottom: solved_layer(4, 12),
+        middle_flipped: false,
+    };
+
+    /// 切片平面两侧没有角块跨过时才能转动切片
+    pub fn can_slice(&self) -> bool {
+        self.top[0] != self.top[1]
+            && self.top[6] != self.top[7]
+            && self.bottom[11] != self.bottom[0]
+            && self.bottom[5] != self.bottom[6]
+    }
+
+    /// 执行一步转动，切片被挡住时返回None
+    pub fn apply(&self, m: Square1Move) -> Option<Self> {
+        let mut next = *self;
+        match m {
+            Square1Move::Turn { top, bottom } => {
+                next.top.rotate_right(top.rem_euclid(UNITS as i32) as usize);
+                next.bottom
+                    .rotate_right(bottom.rem_euclid(UNITS as i32) as usize);
+            }
+            Square1Move::Slice => {
+                if !self.can_slice() {
+                    return None;
+                }
+                // 上层第k个单位与下层第k+5个单位互换
+                for k in [7, 8, 9, 10, 11, 0] {
+                    let j = (k + 5) % UNITS;
+                    next.top[k] = self.bottom[j];
+                    next.bottom[j] = self.top[k];
+                }
+                next.middle_flipped = !self.middle_flipped;
+            }
+        }
+        Some(next)
+    }
+
+    pub fn is_solved(&self) -> bool {
+        *self == Self::SOLVED
+    }
+
+    // 从当前形状开始，向某个方向转动一层直到切片可以转动所需的单位数
+    fn units_to_slice(&self, top: bool, clockwise: bool) -> i32 {
+        let sign = if clockwise { 1 } else { -1 };
+        (1..UNITS as i32)
+            .map(|units| units * sign)
+            .find(|&units| {
+                let m = if top {
+                    Square1Move::Turn {
+                        top: units,
+                        bottom: 0,
+                    }
+                } else {
+                    Square1Move::Turn {
+                        top: 0,
+                        bottom: units,
+                    }
+                };
+                let next = self.apply(m).unwrap();
+                let (layer, a, b) = if top {
+                    (next.top, 0, 6)
+                } else {
+                    (next.bottom, 5, 11)
+                };
+                layer[a] != layer[(a + 1) % UNITS] && layer[b] != layer[(b + 1) % UNITS]
+            })
+            .unwrap_or(sign)
+    }
+}
+
+/// 随机转动打乱，每组转动后切片都可以转动
+pub fn scramble<R: Rng>(rng: &mut R) -> Vec<Square1Move> {
+    let mut state = Square1State::SOLVED;
+    let mut moves = Vec::new();
+    while moves.len() < SCRAMBLE_LENGTH * 2 {
+        let m = Square1Move::Turn {
+            top: rng.gen_range(-5..=6),
+            bottom: rng.gen_range(-5..=6),
+        };
+        if m == (Square1Move::Turn { top: 0, bottom: 0 }) && !moves.is_empty() {
+            continue;
+        }
+        let next = state.apply(m).unwrap();
+        if let Some(sliced) = next.apply(Square1Move::Slice) {
+            moves.extend([m, Square1Move::Slice]);
+            state = sliced;
+        }
+    }
+    moves
+}
+
+// 水平方向的角度：从前方（+Z）转向右方（+X）为正
+fn direction(angle: f32) -> Vec3 {
+    Vec3::new(angle.sin(), 0.0, angle.cos())
+}
+
+// 块的一个面及其贴纸颜色，顶点从外侧看为逆时针
+struct PieceFace {
+    vertices: Vec<Vec3>,
+    color: Option<Face>,
+}
+
+// 水平方向最接近的侧面
+fn side_face(normal: Vec3) -> Face {
+    if normal.x.abs() > normal.z.abs() {
+        if normal.x > 0.0 {
+            Face::R
+        } else {
+            Face::L
+        }
+    } else if normal.z > 0.0 {
+        Face::F
+    } else {
+        Face::B
+    }
+}
+
+// 在正方形的边上
+fn on_boundary(v: Vec3) -> bool {
+    (v.x.abs() - HALF_SIZE).abs() < 1e-3 || (v.z.abs() - HALF_SIZE).abs() < 1e-3
+}
+
+// 由水平截面和高度范围生成棱柱，位于魔方表面的面贴上对应颜色的贴纸
+fn prism(outline: &[Vec3], bottom: f32, top: f32, cap: Option<Face>) -> Vec<PieceFace> {
+    let mut outline = outline.to_vec();
+    let n = outline.len();
+    // 统一为从上面看逆时针
+    let area: Vec3 = (0..n).map(|i| outline[i].cross(outline[(i + 1) % n])).sum();
+    if area.y < 0.0 {
+        outline.reverse();
+    }
+    let at = |v: Vec3, y: f32| Vec3::new(v.x, y, v.z);
+    let mut faces = vec![
+        PieceFace {
+            vertices: outline.iter().map(|&v| at(v, top)).collect(),
+            color: cap.filter(|_| top >= HALF_SIZE - 1e-3),
+        },
+        PieceFace {
+            vertices: outline.iter().rev().map(|&v| at(v, bottom)).collect(),
+            color: cap.filter(|_| bottom <= -HALF_SIZE + 1e-3),
+        },
+    ];
+    for i in 0..n {
+        let (a, b) = (outline[i], outline[(i + 1) % n]);
+        // 两端都在正方形同一条边上的侧面才露在外面
+        let outer = on_boundary(a)
+            && on_boundary(b)
+            && ((a.x - b.x).abs() < 1e-3 || (a.z - b.z).abs() < 1e-3);
+        faces.push(PieceFace {
+            vertices: vec![at(a, bottom), at(b, bottom), at(b, top), at(a, top)],
+            color: outer.then(|| side_face((b - a).cross(Vec3::Y))),
+        });
+    }
+    faces
+}
+
+// 上下层的块：从中心出发的三角形（棱块）或筝形（角块），占据从first开始的units个单位
+fn layer_piece(top: bool, first: usize, units: usize) -> Vec<PieceFace> {
+    // 上层编号方向为从上面看顺时针，即角度减小，下层相反
+    let sign = if top { -1.0 } else { 1.0 };
+    let start = sign * (first as f32 - 0.5) * UNIT_ANGLE;
+    let end = start + sign * units as f32 * UNIT_ANGLE;
+    let edge_radius = HALF_SIZE / (UNIT_ANGLE / 2.0).cos();
+    let mut outline = vec![Vec3::ZERO, direction(start) * edge_radius];
+    if units == 2 {
+        outline.push(direction((start + end) / 2.0) * HALF_SIZE * 2f32.sqrt());
+    }
+    outline.push(direction(end) * edge_radius);
+    if top {
+        prism(&outline, HALF_SIZE - LAYER_HEIGHT, HALF_SIZE, Some(Face::U))
+    } else {
+        prism(
+            &outline,
+            -HALF_SIZE,
+            LAYER_HEIGHT - HALF_SIZE,
+            Some(Face::D),
+        )
+    }
+}
+
+// 中层的半边，right为切片转动的一侧
+fn middle_piece(right: bool) -> Vec<PieceFace> {
+    let offset = HALF_SIZE * SLICE_ANGLE.tan();
+    let sign = if right { 1.0 } else { -1.0 };
+    let outline = [
+        Vec3::new(offset, 0.0, HALF_SIZE),
+        Vec3::new(HALF_SIZE, 0.0, HALF_SIZE),
+        Vec3::new(HALF_SIZE, 0.0, -HALF_SIZE),
+        Vec3::new(-offset, 0.0, -HALF_SIZE),
+    ]
+    .map(|v| v * sign);
+    let half = (HALF_SIZE * 2.0 - LAYER_HEIGHT * 2.0) / 2.0;
+    prism(&outline, -half, half, None)
+}
+
+/// Square-1的块，几何体以谜题中心为原点生成，转动时只改变Transform的旋转
+#[derive(Debug, Component, Clone, Copy)]
+pub struct Square1Piece {
+    // 还原状态下块的中心
+    pub home: Vec3,
+}
+
+/// Square-1前端的谜题，只在当前谜题为Square-1时存在
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct Square1Puzzle;
+
+impl Puzzle for Square1Puzzle {
+    type State = Square1State;
+    type Move = Square1Move;
+
+    fn solved_state(&self) -> Square1State {
+        Square1State::SOLVED
+    }
+
+    fn apply(&self, state: &Square1State, m: &Square1Move) -> Option<Square1State> {
+        state.apply(*m)
+    }
+
+    fn invert(&self, _state: &Square1State, m: &Square1Move) -> Vec<Square1Move> {
+        vec![match *m {
+            Square1Move::Turn { top, bottom } => Square1Move::Turn {
+                top: -top,
+                bottom: -bottom,
+            },
+            Square1Move::Slice => Square1Move::Slice,
+        }]
+    }
+
+    fn is_solved(&self, state: &Square1State) -> bool {
+        state.is_solved()
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Square1Move>, NotationError> {
+        parse_algorithm(input)
+    }
+
+    fn scramble(&self, rng: &mut StdRng) -> (String, Vec<Square1Move>) {
+        let moves = scramble(rng);
+        (format_algorithm(&moves), moves)
+    }
+}
+
+fn spawn_square1(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    colors: &ColorScheme,
+) {
+    let solved = Square1State::SOLVED;
+    let mut pieces = vec![middle_piece(true), middle_piece(false)];
+    for (top, layer) in [(true, solved.top), (false, solved.bottom)] {
+        for unit in 0..UNITS {
+            // 每个块从其第一个单位生成
+            let previous = layer[(unit + UNITS - 1) % UNITS];
+            if previous == layer[unit] {
+                continue;
+            }
+            let units = if layer[(unit + 1) % UNITS] == layer[unit] {
+                2
+            } else {
+                1
+            };
+            pieces.push(layer_piece(top, unit, units));
+        }
+    }
+    for faces in pieces {
+        let normal = |vertices: &[Vec3]| {
+            let n = vertices.len();
+            (0..n)
+                .map(|i| vertices[i].cross(vertices[(i + 1) % n]))
+                .sum::<Vec3>()
+                .normalize()
+        };
+        let vertices = faces.iter().flat_map(|face| &face.vertices);
+        let home = vertices.clone().sum::<Vec3>() / vertices.count() as f32;
+        let body = polygon_mesh(
+            faces
+                .iter()
+                .map(|face| (face.vertices.clone(), normal(&face.vertices))),
+        );
+        commands
+            .spawn((
+                Mesh3d(meshes.add(body)),
+                MeshMaterial3d(materials.add(Color::BLACK)),
+                Transform::default(),
+                Square1Piece { home },
+                RayCastPickable,
+            ))
+            .observe(handle_drag_start)
+            .observe(handle_square1_drag)
+            .observe(handle_drag_end)
+            .with_children(|parent| {
+                for face in &faces {
+                    let Some(color) = face.color else {
+                        continue;
+                    };
+                    let normal = normal(&face.vertices);
+                    let vertices = sticker_polygon(&face.vertices, normal);
+                    parent.spawn((
+                        Mesh3d(meshes.add(polygon_mesh(std::iter::once((vertices, normal))))),
+                        MeshMaterial3d(materials.add(StandardMaterial {
+                            base_color: colors.color(color),
+                            unlit: true,
+                            ..default()
+                        })),
+                    ));
+                }
+            });
+    }
+}
+
+// 重置时重建，切换到其他谜题时只移除；离开Square-1时丢弃尚未执行的转动
+#[allow(clippy::too_many_arguments)]
+pub fn reset_square1(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    cube_settings: Res<CubeSettings>,
+    mut events: EventReader<ResetEvent>,
+    q_pieces: Query<Entity, With<Square1Piece>>,
+    puzzle: Option<Res<Square1Puzzle>>,
+    mut queue: ResMut<MoveQueue<Square1Puzzle>>,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+    for piece in &q_pieces {
+        commands.entity(piece).despawn_recursive();
+    }
+    if cube_settings.puzzle == PuzzleKind::Square1 {
+        spawn_square1(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &cube_settings.color_scheme(),
+        );
+        commands.insert_resource(Square1Puzzle);
+    } else if puzzle.is_some() {
+        queue.0.clear();
+        commands.remove_resource::<Square1Puzzle>();
+    }
+}
+
+// 上一次转动的动画结束后，取出下一次转动并标记要转动的块
+pub fn choose_square1_pieces(
+    mut commands: Commands,
+    q_pieces: Query<(Entity, &Square1Piece, &Transform)>,
+    q_movable_pieces: Query<Entity, With<MovablePiece>>,
+    mut queue: ResMut<MoveQueue<Square1Puzzle>>,
+    mut active_move: ResMut<ActiveMove<Square1Puzzle>>,
+    mut events: EventWriter<MoveEvent<Square1Puzzle>>,
+) {
+    if !q_movable_pieces.is_empty() {
+        return;
+    }
+    if let Some((m, source)) = active_move.0.take() {
+        events.send(MoveEvent(m, source));
+    }
+    let Some((m, source)) = queue.0.pop_front() else {
+        return;
+    };
+    for (entity, piece, transform) in &q_pieces {
+        let center = transform.rotation * piece.home;
+        // 转动前已检查过切片不会被挡住，同一侧的块一起转动
+        let movable = match m {
+            Square1Move::Turn { top, .. } if center.y > LAYER_HEIGHT / 2.0 => MovablePiece {
+                axis: Vec3::Y,
+                left_angle: -(top as f32) * UNIT_ANGLE,
+            },
+            Square1Move::Turn { bottom, .. } if center.y < -LAYER_HEIGHT / 2.0 => MovablePiece {
+                axis: Vec3::Y,
+                left_angle: bottom as f32 * UNIT_ANGLE,
+            },
+            Square1Move::Slice if slice_normal().dot(center) > 0.0 => MovablePiece {
+                axis: slice_normal(),
+                left_angle: PI,
+            },
+            _ => continue,
+        };
+        if movable.left_angle != 0.0 {
+            commands.entity(entity).insert(movable);
+        }
+    }
+    active_move.0 = Some((m, source));
+}
+
+// 竖直拖动右半边转动切片；水平拖动上层或下层时，按形状转到下一个可以转动切片的位置
+pub fn handle_square1_drag(
+    pointer_move: Trigger<Pointer<Move>>,
+    mut recorder: ResMut<MouseDraggingRecorder>,
+    mut queue: ResMut<MoveQueue<Square1Puzzle>>,
+    puzzle: Option<Res<Square1Puzzle>>,
+    state: Res<PuzzleState<Square1Puzzle>>,
+    active_move: Res<ActiveMove<Square1Puzzle>>,
+) {
+    let (Some(current_pos), Some(start_pos)) =
+        (pointer_move.event.hit.position, recorder.start_pos)
+    else {
+        return;
+    };
+    if start_pos.distance(current_pos) <= 0.5 || recorder.triggered {
+        return;
+    }
+    recorder.triggered = true;
+    let (Some(puzzle), Some(state)) = (puzzle, &state.0) else {
+        return;
+    };
+    let Some(state) = predicted_state(&*puzzle, state, &active_move, &queue) else {
+        return;
+    };
+    let delta = current_pos - start_pos;
+    let horizontal = Vec3::new(delta.x, 0.0, delta.z);
+    if delta.y.abs() > horizontal.length() {
+        if slice_normal().dot(start_pos) <= 0.0 {
+            info!("only the right half can be sliced");
+        } else if state.can_slice() {
+            queue.push_moves([Square1Move::Slice], MoveSource::Player);
+        } else {
+            info!("slice is blocked by a corner piece");
+        }
+        return;
+    }
+    let top = if start_pos.y > LAYER_HEIGHT / 2.0 {
+        true
+    } else if start_pos.y < -LAYER_HEIGHT / 2.0 {
+        false
+    } else {
+        info!("the middle layer only turns with the slice");
+        return;
+    };
+    // 从该层的面看顺时针转动时起点的运动方向
+    let axis = if top { Vec3::NEG_Y } else { Vec3::Y };
+    let tangent = axis.cross(start_pos);
+    let units = state.units_to_slice(top, tangent.dot(horizontal) > 0.0);
+    let m = if top {
+        Square1Move::Turn {
+            top: units,
+            bottom: 0,
+        }
+    } else {
+        Square1Move::Turn {
+            top: 0,
+            bottom: units,
+        }
+    };
+    info!("gen square-1 move: {}", m);
+    queue.push_moves([m], MoveSource::Player);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::move_history::MoveHistory;
+    use crate::puzzle::{check_moves, PuzzlePlugin};
+
+    #[test]
+    fn parses_and_formats_moves() {
+        let moves = parse_algorithm("(1,0)/ (-1,3) /").unwrap();
+        assert_eq!(moves.len(), 4);
+        assert_eq!(format_algorithm(&moves), "(1,0)/ (-1,3)/");
+        assert!(check_moves(&Square1Puzzle, Square1State::SOLVED, &moves).is_ok());
+        // 转动(1,0)后切片被挡住
+        let blocked = parse_algorithm("/ (1,0) /").unwrap();
+        assert_eq!(
+            check_moves(&Square1Puzzle, Square1State::SOLVED, &blocked),
+            Err(2)
+        );
+    }
+
+    #[test]
+    fn undo_returns_to_solved() {
+        let mut app = App::new();
+        app.add_plugins(PuzzlePlugin::<Square1Puzzle>::default())
+            .insert_resource(Square1Puzzle);
+        app.world_mut().send_event(ResetEvent);
+        app.update();
+        for m in parse_algorithm("(1,0)/ (3,-3)/").unwrap() {
+            app.world_mut()
+                .send_event(MoveEvent::<Square1Puzzle>(m, MoveSource::Player));
+        }
+        app.update();
+        assert_eq!(app.world().resource::<SolveProgress>().move_count, 4);
+
+        while let Some(moves) = app
+            .world_mut()
+            .resource_mut::<MoveHistory<Square1Move>>()
+            .undo()
+        {
+            for m in moves {
+                app.world_mut()
+                    .send_event(MoveEvent::<Square1Puzzle>(m, MoveSource::History));
+            }
+            app.update();
+        }
+        let state = app.world().resource::<PuzzleState<Square1Puzzle>>();
+        assert!(state.0.unwrap().is_solved());
+        assert_eq!(app.world().resource::<SolveProgress>().move_count, 8);
+    }
+}
@@ -0,0 +1,495 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
+use std::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SideRotation {
+    Clockwise90,
+    Clockwise180,
+    Counterclockwise90,
+}
+
+impl SideRotation {
+    pub fn inverse(self) -> Self {
+        match self {
+            SideRotation::Clockwise90 => SideRotation::Counterclockwise90,
+            SideRotation::Clockwise180 => SideRotation::Clockwise180,
+            SideRotation::Counterclockwise90 => SideRotation::Clockwise90,
+        }
+    }
+
+    // 绕转轴正方向按右手定则转动的角度
+    pub fn angle(self) -> f32 {
+        match self {
+            SideRotation::Clockwise90 => FRAC_PI_2,
+            SideRotation::Clockwise180 => PI,
+            SideRotation::Counterclockwise90 => -FRAC_PI_2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub fn index(self) -> usize {
+        match self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        }
+    }
+}
+
+/// 垂直于转轴的截面为正方形时才能转动90度，否则只能转动180度；dims为x/y/z方向的层数
+pub fn quarter_turn_allowed(axis: Axis, dims: [u8; 3]) -> bool {
+    let index = axis.index();
+    dims[(index + 1) % 3] == dims[(index + 2) % 3]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SideMoveEvent {
+    // 旋转的面，对应固定的x/y/z坐标值
+    pub side: (Axis, f32),
+    // 旋转
+    pub rotate: SideRotation,
+}
+
+impl SideMoveEvent {
+    // 转动后魔方的形状不变
+    pub fn is_allowed(&self, dims: [u8; 3]) -> bool {
+        self.rotate == SideRotation::Clockwise180 || quarter_turn_allowed(self.side.0, dims)
+    }
+
+    // 转动的层在该形状的魔方上存在，且转动后形状不变
+    pub fn is_valid(&self, dims: [u8; 3]) -> bool {
+        let (axis, value) = self.side;
+        let layer = value * 2.0;
+        layer.fract() == 0.0
+            && layer_coords(dims[axis.index()]).any(|coord| coord as f32 == layer)
+            && self.is_allowed(dims)
+    }
+}
+
+/// 魔方的六个面，顺序与Kociemba的facelet字符串（URFDLB）一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Face {
+    U,
+    R,
+    F,
+    D,
+    L,
+    B,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::U, Face::R, Face::F, Face::D, Face::L, Face::B];
+
+    // 面的外法线方向
+    pub fn normal(self) -> [i32; 3] {
+        match self {
+            Face::U => [0, 1, 0],
+            Face::R => [1, 0, 0],
+            Face::F => [0, 0, 1],
+            Face::D => [0, -1, 0],
+            Face::L => [-1, 0, 0],
+            Face::B => [0, 0, -1],
+        }
+    }
+
+    pub fn from_normal(normal: [i32; 3]) -> Option<Face> {
+        Face::ALL.into_iter().find(|face| face.normal() == normal)
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Face::U => 'U',
+            Face::R => 'R',
+            Face::F => 'F',
+            Face::D => 'D',
+            Face::L => 'L',
+            Face::B => 'B',
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<Face> {
+        Face::ALL.into_iter().find(|face| face.to_char() == c)
+    }
+
+    // 从外部看向该面时，facelet网格的向右、向下方向
+    fn grid_dirs(self) -> ([i32; 3], [i32; 3]) {
+        match self {
+            Face::U => ([1, 0, 0], [0, 0, 1]),
+            Face::R => ([0, 0, -1], [0, -1, 0]),
+            Face::F => ([1, 0, 0], [0, -1, 0]),
+            Face::D => ([1, 0, 0], [0, 0, -1]),
+            Face::L => ([0, 0, 1], [0, -1, 0]),
+            Face::B => ([-1, 0, 0], [0, -1, 0]),
+        }
+    }
+}
+
+/// 单个块的状态，坐标均为Piece坐标的两倍，使偶数阶魔方也能用整数表示
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cubie {
+    // 初始位置，与Piece::init_pos对应
+    pub home: [i32; 3],
+    // 当前位置
+    pub pos: [i32; 3],
+    // 初始的x/y/z轴当前指向的方向
+    pub orient: [[i32; 3]; 3],
+}
+
+impl Cubie {
+    fn solved(home: [i32; 3]) -> Self {
+        Self {
+            home,
+            pos: home,
+            orient: [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+        }
+    }
+
+    // 当前朝向dir的贴纸，在还原状态下所在的面
+    pub fn sticker_facing(&self, dir: [i32; 3]) -> Option<Face> {
+        for (i, axis) in self.orient.iter().enumerate() {
+            for sign in [1, -1] {
+                if axis.map(|v| v * sign) == dir {
+                    let mut home_normal = [0; 3];
+                    home_normal[i] = sign;
+                    return Face::from_normal(home_normal);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// 脱离渲染世界的逻辑魔方，由块的排列和朝向组成
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CubeState {
+    // 阶数，长方体魔方为最长边的层数
+    order: u8,
+    // 长方体魔方x/y/z方向的层数，正阶魔方为None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shape: Option<[u8; 3]>,
+    cubies: Vec<Cubie>,
+}
+
+impl Default for CubeState {
+    fn default() -> Self {
+        Self::solved(3)
+    }
+}
+
+impl CubeState {
+    pub fn solved(order: u8) -> Self {
+        Self {
+            order,
+            shape: None,
+            cubies: solved_cubies([order; 3]),
+        }
+    }
+
+    /// 还原状态的长方体魔方
+    pub fn cuboid(dims: [u8; 3]) -> Self {
+        Self {
+            order: dims.into_iter().max().unwrap(),
+            shape: Some(dims),
+            cubies: solved_cubies(dims),
+        }
+    }
+
+    /// 由各块的状态构造正阶魔方，外部数据需要再用is_valid检查
+    pub fn from_cubies(order: u8, cubies: Vec<Cubie>) -> Self {
+        Self {
+            order,
+            shape: None,
+            cubies,
+        }
+    }
+
+    pub fn order(&self) -> u8 {
+        self.order
+    }
+
+    /// 是否为长方体魔方，三个方向层数相同的长方体魔方也算
+    pub fn is_cuboid(&self) -> bool {
+        self.shape.is_some()
+    }
+
+    /// x/y/z方向的层数
+    pub fn dims(&self) -> [u8; 3] {
+        self.shape.unwrap_or([self.order; 3])
+    }
+
+    pub fn cubies(&self) -> &[Cubie] {
+        &self.cubies
+    }
+
+    /// 检查从存档等外部数据得到的状态：块与阶数相符、位置互不重复、朝向为旋转且贴纸都朝外
+    pub fn is_valid(&self) -> bool {
+        match self.shape {
+            None if self.order < 2 => return false,
+            Some(dims) if dims.contains(&0) || dims.into_iter().max() != Some(self.order) => {
+                return false
+            }
+            _ => {}
+        }
+        let mut expected: Vec<[i32; 3]> = solved_cubies(self.dims())
+            .iter()
+            .map(|cubie| cubie.home)
+            .collect();
+        expected.sort();
+        let mut homes: Vec<[i32; 3]> = self.cubies.iter().map(|cubie| cubie.home).collect();
+        homes.sort();
+        let mut positions: Vec<[i32; 3]> = self.cubies.iter().map(|cubie| cubie.pos).collect();
+        positions.sort();
+        let outer = self.dims().map(|n| n as i32 - 1);
+        homes == expected
+            && positions == expected
+            && self
+                .cubies
+                .iter()
+                .all(|cubie| is_rotation(&cubie.orient) && stickers_face_outward(cubie, outer))
+    }
+
+    pub fn cubie_at(&self, pos: [i32; 3]) -> Option<&Cubie> {
+        self.cubies.iter().find(|cubie| cubie.pos == pos)
+    }
+
+    pub fn apply_side_move(&mut self, event: &SideMoveEvent) {
+        let (axis, value) = event.side;
+        let layer = (value * 2.0).round() as i32;
+        let quarter_turns = match event.rotate {
+            SideRotation::Clockwise90 => 1,
+            SideRotation::Clockwise180 => 2,
+            SideRotation::Counterclockwise90 => 3,
+        };
+        let index = axis.index();
+        for cubie in &mut self.cubies {
+            if cubie.pos[index] == layer {
+                cubie.pos = rotate_quarter(cubie.pos, axis, quarter_turns);
+                for dir in &mut cubie.orient {
+                    *dir = rotate_quarter(*dir, axis, quarter_turns);
+                }
+            }
+        }
+    }
+
+    /// 按URFDLB顺序返回6*N*N个贴纸，每个面从外部看按行优先排列；
+    /// 长方体魔方每个面的行列数为该面两个方向的层数
+    pub fn facelets(&self) -> Vec<Face> {
+        let dims = self.dims();
+        let mut facelets = Vec::new();
+        for face in Face::ALL {
+            let normal = face.normal();
+            let (right, down) = face.grid_dirs();
+            let outer = dims[axis_of(normal)] as i32 - 1;
+            for row in layer_coords(dims[axis_of(down)]) {
+                for col in layer_coords(dims[axis_of(right)]) {
+                    let pos: [i32; 3] =
+                        std::array::from_fn(|i| normal[i] * outer + right[i] * col + down[i] * row);
+                    let sticker = self
+                        .cubie_at(pos)
+                        .and_then(|cubie| cubie.sticker_facing(normal))
+                        .expect("every surface position holds a cubie");
+                    facelets.push(sticker);
+                }
+            }
+        }
+        facelets
+    }
+
+    /// 按当前各面中心块重新标记的贴纸，整体转动或中层转动后仍得到中心块在标准位置的facelet，
+    /// 偶数阶没有中心块，返回None；各边层数不同的长方体魔方也返回None
+    pub fn oriented_facelets(&self) -> Option<Vec<Face>> {
+        let [n, y, z] = self.dims();
+        if n.is_multiple_of(2) || n != y || n != z {
+            return None;
+        }
+        let n = n as usize;
+        let facelets = self.facelets();
+        let mut relabel = [Face::U; 6];
+        for face in Face::ALL {
+            relabel[facelets[face as usize * n * n + n * n / 2] as usize] = face;
+        }
+        Some(facelets.into_iter().map(|f| relabel[f as usize]).collect())
+    }
+
+    pub fn facelet_string(&self) -> String {
+        self.facelets().into_iter().map(Face::to_char).collect()
+    }
+
+    /// 每个面颜色一致即为还原，不要求整体朝向
+    pub fn is_solved(&self) -> bool {
+        let dims = self.dims();
+        let facelets = self.facelets();
+        let mut rest = facelets.as_slice();
+        Face::ALL.into_iter().all(|face| {
+            let (right, down) = face.grid_dirs();
+            let len = dims[axis_of(right)] as usize * dims[axis_of(down)] as usize;
+            let (stickers, next) = rest.split_at(len);
+            rest = next;
+            stickers.iter().all(|sticker| *sticker == stickers[0])
+        })
+    }
+}
+
+// 各方向层数为dims的还原状态，内部的块不可见，不参与状态
+fn solved_cubies(dims: [u8; 3]) -> Vec<Cubie> {
+    let outer = dims.map(|n| n as i32 - 1);
+    let mut cubies = Vec::new();
+    for x in layer_coords(dims[0]) {
+        for y in layer_coords(dims[1]) {
+            for z in layer_coords(dims[2]) {
+                let pos = [x, y, z];
+                if pos.iter().zip(outer).any(|(v, outer)| v.abs() == outer) {
+                    cubies.push(Cubie::solved(pos));
+                }
+            }
+        }
+    }
+    cubies
+}
+
+// 各层坐标（两倍），如3阶为-2、0、2，4阶为-3、-1、1、3
+pub fn layer_coords(order: u8) -> impl Iterator<Item = i32> {
+    let outer = order as i32 - 1;
+    (0..order as i32).map(move |i| 2 * i - outer)
+}
+
+// 每个轴都指向坐标轴方向，且行列式为1（不含镜像）
+fn is_rotation(m: &[[i32; 3]; 3]) -> bool {
+    let unit = m
+        .iter()
+        .all(|v| v.iter().map(|x| x.abs()).sum::<i32>() == 1 && v.iter().all(|x| x.abs() <= 1));
+    let [a, b, c] = m;
+    let det = a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+        + a[2] * (b[0] * c[1] - b[1] * c[0]);
+    unit && det == 1
+}
+
+// 还原时朝外的贴纸转动后仍朝向其所在位置的外表面，outer为各方向最外层的坐标
+fn stickers_face_outward(cubie: &Cubie, outer: [i32; 3]) -> bool {
+    (0..3).all(|i| {
+        [1, -1].into_iter().all(|sign| {
+            if cubie.home[i] != sign * outer[i] {
+                return true;
+            }
+            let dir = cubie.orient[i].map(|v| v * sign);
+            let j = axis_of(dir);
+            cubie.pos[j] == dir[j] * outer[j]
+        })
+    })
+}
+
+// 坐标轴方向的单位向量所在的轴
+fn axis_of(dir: [i32; 3]) -> usize {
+    dir.iter().position(|&v| v != 0).unwrap()
+}
+
+// 绕坐标轴按右手定则旋转quarter_turns个90度，与rotate_cube中正角度方向一致
+fn rotate_quarter(v: [i32; 3], axis: Axis, quarter_turns: i32) -> [i32; 3] {
+    let mut v = v;
+    for _ in 0..quarter_turns.rem_euclid(4) {
+        let [x, y, z] = v;
+        v = match axis {
+            Axis::X => [x, -z, y],
+            Axis::Y => [z, y, -x],
+            Axis::Z => [-y, x, z],
+        };
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge_mut(state: &mut CubeState, pos: [i32; 3]) -> &mut Cubie {
+        state.cubies.iter_mut().find(|c| c.pos == pos).unwrap()
+    }
+
+    #[test]
+    fn valid_states() {
+        assert!(CubeState::solved(3).is_valid());
+        assert!(CubeState::cuboid([3, 1, 3]).is_valid());
+        let mut state = CubeState::solved(4);
+        state.apply_side_move(&SideMoveEvent {
+            side: (Axis::X, 1.5),
+            rotate: SideRotation::Clockwise90,
+        });
+        assert!(state.is_valid());
+        assert!(!CubeState::solved(1).is_valid());
+    }
+
+    #[test]
+    fn rejects_stickers_facing_inward() {
+        // 在原位绕x轴转90度的棱块，U面贴纸朝向没有块面的方向
+        let mut state = CubeState::solved(3);
+        edge_mut(&mut state, [2, 2, 0]).orient = [[1, 0, 0], [0, 0, 1], [0, -1, 0]];
+        assert!(!state.is_valid());
+
+        // 原位扭转的角块贴纸仍然朝外
+        let mut state = CubeState::solved(3);
+        edge_mut(&mut state, [2, 2, 2]).orient = [[0, 1, 0], [0, 0, 1], [1, 0, 0]];
+        assert!(state.is_valid());
+    }
+
+    #[test]
+    fn rejects_inconsistent_shape() {
+        let mut state = CubeState::cuboid([3, 2, 3]);
+        state.shape = Some([3, 2, 2]);
+        assert!(!state.is_valid());
+        state.shape = Some([3, 2, 4]);
+        assert!(!state.is_valid());
+    }
+
+    fn apply(state: &mut CubeState, algorithm: &str) {
+        let moves = crate::notation::parse_cuboid_side_moves(algorithm, state.dims()).unwrap();
+        for event in moves.iter().flatten() {
+            state.apply_side_move(event);
+        }
+    }
+
+    #[test]
+    fn applies_moves() {
+        let mut state = CubeState::solved(3);
+        apply(&mut state, "U");
+        assert_eq!(
+            state.facelet_string(),
+            "UUUUUUUUUBBBRRRRRRRRRFFFFFFDDDDDDDDDFFFLLLLLLLLLBBBBBB"
+        );
+        assert!(!state.is_solved());
+        apply(&mut state, "U'");
+        assert_eq!(state, CubeState::solved(3));
+
+        // (R U R' U')六次回到原状态
+        let mut state = CubeState::solved(4);
+        for _ in 0..6 {
+            apply(&mut state, "R U R' U'");
+        }
+        assert_eq!(state, CubeState::solved(4));
+    }
+
+    #[test]
+    fn solved_ignores_orientation() {
+        let mut state = CubeState::solved(3);
+        apply(&mut state, "x y2 z'");
+        assert!(state.is_solved());
+        assert!(state.is_valid());
+        apply(&mut state, "M2");
+        assert!(!state.is_solved());
+
+        let mut cuboid = CubeState::cuboid([3, 2, 3]);
+        apply(&mut cuboid, "R2 U D'");
+        assert!(cuboid.is_valid());
+        assert!(!cuboid.is_solved());
+        apply(&mut cuboid, "D U' R2");
+        assert!(cuboid.is_solved());
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Penalty {
+    #[default]
+    None,
+    // 加罚2秒
+    PlusTwo,
+    // 成绩无效
+    Dnf,
+}
+
+/// 一次成绩，DNF比任何时间都差
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SolveResult {
+    Time(Duration),
+    Dnf,
+}
+
+impl fmt::Display for SolveResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveResult::Time(time) => write!(f, "{}", format_duration(*time)),
+            SolveResult::Dnf => write!(f, "DNF"),
+        }
+    }
+}
+
+/// 一次完成的计时还原
+#[derive(Debug, Clone)]
+pub struct SolveRecord {
+    pub scramble: Option<String>,
+    // 毫秒精度
+    pub time: Duration,
+    pub move_count: u32,
+    pub penalty: Penalty,
+}
+
+impl SolveRecord {
+    pub fn new(
+        scramble: Option<String>,
+        time: Duration,
+        move_count: u32,
+        penalty: Penalty,
+    ) -> Self {
+        Self {
+            scramble,
+            time: Duration::from_millis(time.as_millis() as u64),
+            move_count,
+            penalty,
+        }
+    }
+
+    // 计入罚时后的成绩
+    pub fn result(&self) -> SolveResult {
+        match self.penalty {
+            Penalty::None => SolveResult::Time(self.time),
+            Penalty::PlusTwo => SolveResult::Time(self.time + Duration::from_secs(2)),
+            Penalty::Dnf => SolveResult::Dnf,
+        }
+    }
+}
+
+/// 一组计时成绩及其统计
+#[derive(Debug, Default, Clone)]
+pub struct SolveSession {
+    pub solves: Vec<SolveRecord>,
+}
+
+impl SolveSession {
+    // 最好单次
+    pub fn best(&self) -> Option<SolveResult> {
+        self.solves.iter().map(SolveRecord::result).min()
+    }
+
+    // 所有有效成绩的平均值
+    pub fn mean(&self) -> Option<Duration> {
+        let times: Vec<Duration> = self
+            .solves
+            .iter()
+            .filter_map(|solve| match solve.result() {
+                SolveResult::Time(time) => Some(time),
+                SolveResult::Dnf => None,
+            })
+            .collect();
+        if times.is_empty() {
+            return None;
+        }
+        Some(times.iter().sum::<Duration>() / times.len() as u32)
+    }
+
+    // 最近n次的平均成绩
+    pub fn current_average(&self, n: usize) -> Option<SolveResult> {
+        let start = self.solves.len().checked_sub(n)?;
+        average(&self.solves[start..])
+    }
+
+    // 所有连续n次中最好的平均成绩
+    pub fn best_average(&self, n: usize) -> Option<SolveResult> {
+        self.solves.windows(n).filter_map(average).min()
+    }
+}
+
+// 按WCA规则去掉最好和最差各5%（向上取整，至少1个）后取平均，
+// DNF多于去掉的个数时平均成绩为DNF
+pub fn average(solves: &[SolveRecord]) -> Option<SolveResult> {
+    if solves.len() < 3 {
+        return None;
+    }
+    let trim = solves.len().div_ceil(20);
+    let mut results: Vec<SolveResult> = solves.iter().map(SolveRecord::result).collect();
+    results.sort();
+    let counted = &results[trim..results.len() - trim];
+    let mut total = Duration::ZERO;
+    for result in counted {
+        match result {
+            SolveResult::Time(time) => total += *time,
+            SolveResult::Dnf => return Some(SolveResult::Dnf),
+        }
+    }
+    let millis = total.as_millis() / counted.len() as u128;
+    Some(SolveResult::Time(Duration::from_millis(millis as u64)))
+}
+
+// 格式化为秒，超过一分钟时为分:秒，精确到毫秒
+pub fn format_duration(time: Duration) -> String {
+    let millis = time.as_millis();
+    let minutes = millis / 60_000;
+    let seconds = millis % 60_000 / 1000;
+    let millis = millis % 1000;
+    if minutes > 0 {
+        format!("{}:{:02}.{:03}", minutes, seconds, millis)
+    } else {
+        format!("{}.{:03}", seconds, millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solves(results: &[Option<u64>]) -> Vec<SolveRecord> {
+        results
+            .iter()
+            .map(|result| match result {
+                Some(millis) => {
+                    SolveRecord::new(None, Duration::from_millis(*millis), 0, Penalty::None)
+                }
+                None => SolveRecord::new(None, Duration::ZERO, 0, Penalty::Dnf),
+            })
+            .collect()
+    }
+
+    fn time(millis: u64) -> Option<SolveResult> {
+        Some(SolveResult::Time(Duration::from_millis(millis)))
+    }
+
+    #[test]
+    fn averages_trim_best_and_worst() {
+        assert_eq!(average(&solves(&[Some(1000), Some(2000)])), None);
+        // 去掉最快的1秒和最慢的9秒
+        assert_eq!(
+            average(&solves(&[
+                Some(1000),
+                Some(3000),
+                Some(9000),
+                Some(4000),
+                Some(5000)
+            ])),
+            time(4000)
+        );
+        // 一个DNF作为最差成绩去掉，两个DNF时平均为DNF
+        assert_eq!(
+            average(&solves(&[
+                Some(1000),
+                None,
+                Some(3000),
+                Some(4000),
+                Some(5000)
+            ])),
+            time(4000)
+        );
+        assert_eq!(
+            average(&solves(&[Some(1000), None, Some(3000), None, Some(5000)])),
+            Some(SolveResult::Dnf)
+        );
+    }
+
+    #[test]
+    fn large_averages_trim_five_percent() {
+        // 100次去掉两端各5次，剩下的是6到95秒
+        let results: Vec<Option<u64>> = (1..=100).map(|s| Some(s * 1000)).collect();
+        assert_eq!(average(&solves(&results)), time(50_500));
+        // 12次两端各去掉1次
+        let results: Vec<Option<u64>> = (1..=12).map(|s| Some(s * 1000)).collect();
+        assert_eq!(average(&solves(&results)), time(6500));
+    }
+
+    #[test]
+    fn session_statistics() {
+        let mut session = SolveSession {
+            solves: solves(&[
+                Some(5000),
+                Some(3000),
+                None,
+                Some(4000),
+                Some(8000),
+                Some(2000),
+            ]),
+        };
+        session.solves[0].penalty = Penalty::PlusTwo;
+        assert_eq!(session.best(), time(2000));
+        assert_eq!(session.mean(), Some(Duration::from_millis(4800)));
+        assert_eq!(session.current_average(5), time(5000));
+        assert_eq!(session.best_average(5), time(5000));
+        assert_eq!(session.current_average(12), None);
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(Duration::from_millis(9_050)), "9.050");
+        assert_eq!(format_duration(Duration::from_millis(83_004)), "1:23.004");
+        assert_eq!(SolveResult::Dnf.to_string(), "DNF");
+    }
+}
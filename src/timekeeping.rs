@@ -0,0 +1,207 @@
+use crate::cube::*;
+use crate::puzzle::PuzzleStatus;
+use crate::session::*;
+use crate::stats::*;
+use bevy::prelude::*;
+use bevy::utils::Instant;
+use std::time::Duration;
+
+// WCA观察时间
+pub const INSPECTION_TIME: Duration = Duration::from_secs(15);
+// 超过观察时间2秒以内加罚2秒，再往后成绩无效
+pub const INSPECTION_DNF_TIME: Duration = Duration::from_secs(17);
+
+/// 计时模式下一次还原的阶段：打乱 -> 观察 -> 还原 -> 完成
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum AttemptPhase {
+    // 等待打乱
+    #[default]
+    Idle,
+    // 打乱动画进行中
+    Scrambling,
+    // 观察中，记录观察开始时间
+    Inspecting(Instant),
+    // 还原中，记录开始时间和观察超时的罚时
+    Solving {
+        start: Instant,
+        penalty: Penalty,
+    },
+    // 已还原，记录最终用时
+    Finished(Duration),
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct TimekeepingTimer(pub AttemptPhase);
+
+impl TimekeepingTimer {
+    // 计时模式下打乱后到还原前，求解、公式、花样和撤销都不能使用
+    pub fn is_running(&self, play_mode: PlayMode) -> bool {
+        play_mode == PlayMode::Timekeeping
+            && matches!(
+                self.0,
+                AttemptPhase::Scrambling
+                    | AttemptPhase::Inspecting(_)
+                    | AttemptPhase::Solving { .. }
+            )
+    }
+
+    // 还原用时，未开始还原时为0
+    pub fn elapsed(&self) -> Duration {
+        match self.0 {
+            AttemptPhase::Solving { start, .. } => start.elapsed(),
+            AttemptPhase::Finished(time) => time,
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+// 还原完成面板展示的成绩
+#[derive(Debug, Clone, Copy)]
+pub struct SolveSummary {
+    // 仅计时模式下有成绩
+    pub time: Option<Duration>,
+    pub move_count: u32,
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct CompletedSolve(pub Option<SolveSummary>);
+
+// 观察时间对应的罚时
+pub fn inspection_penalty(inspection: Duration) -> Penalty {
+    if inspection > INSPECTION_DNF_TIME {
+        Penalty::Dnf
+    } else if inspection > INSPECTION_TIME {
+        Penalty::PlusTwo
+    } else {
+        Penalty::None
+    }
+}
+
+// 推进计时模式的阶段：打乱完成后开始观察，观察中第一次转动时开始计时
+pub fn update_attempt(
+    mut reset_events: EventReader<ResetEvent>,
+    mut scramble_events: EventReader<ScrambleEvent>,
+    mut timekeeping_timer: ResMut<TimekeepingTimer>,
+    status: Res<PuzzleStatus>,
+    cube_settings: Res<CubeSettings>,
+) {
+    if !reset_events.is_empty() {
+        reset_events.clear();
+        timekeeping_timer.0 = AttemptPhase::Idle;
+    }
+    if !scramble_events.is_empty() {
+        scramble_events.clear();
+        timekeeping_timer.0 = AttemptPhase::Scrambling;
+    }
+    if cube_settings.play_mode != PlayMode::Timekeeping {
+        return;
+    }
+    match timekeeping_timer.0 {
+        AttemptPhase::Scrambling if !status.scrambling => {
+            timekeeping_timer.0 = AttemptPhase::Inspecting(Instant::now());
+        }
+        AttemptPhase::Inspecting(start) if status.turning => {
+            timekeeping_timer.0 = AttemptPhase::Solving {
+                start: Instant::now(),
+                penalty: inspection_penalty(start.elapsed()),
+            };
+        }
+        _ => {}
+    }
+}
+
+// 还原完成后停止计时并记录成绩
+pub fn finish_solve(
+    mut events: EventReader<CubeSolvedEvent>,
+    cube_settings: Res<CubeSettings>,
+    mut timekeeping_timer: ResMut<TimekeepingTimer>,
+    mut completed_solve: ResMut<CompletedSolve>,
+    mut session: ResMut<Session>,
+    current_scramble: Res<CurrentScramble>,
+) {
+    for event in events.read() {
+        let mut time = None;
+        if cube_settings.play_mode == PlayMode::Timekeeping {
+            // 同一次计时只记录第一次还原
+            if let AttemptPhase::Solving { start, penalty } = timekeeping_timer.0 {
+                let elapsed = start.elapsed();
+                timekeeping_timer.0 = AttemptPhase::Finished(elapsed);
+                session.solves.push(SolveRecord::new(
+                    current_scramble.0.clone(),
+                    elapsed,
+                    event.move_count,
+                    penalty,
+                ));
+                time = Some(elapsed);
+            }
+        }
+        completed_solve.0 = Some(SolveSummary {
+            time,
+            move_count: event.move_count,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bandage::BandageConfig;
+    use crate::notation::parse_side_moves;
+    use crate::puzzle::{MoveQueue, MoveSource, PuzzlePlugin, PuzzleSystems};
+
+    #[test]
+    fn rotations_during_inspection_do_not_start_the_timer() {
+        let mut app = App::new();
+        let settings = CubeSettings {
+            play_mode: PlayMode::Timekeeping,
+            ..default()
+        };
+        app.add_plugins(PuzzlePlugin::<CubePuzzle>::default())
+            .insert_resource(CubePuzzle::new(&settings, &BandageConfig::default()).unwrap())
+            .insert_resource(settings)
+            .init_resource::<TimekeepingTimer>()
+            .add_systems(Update, update_attempt.after(PuzzleSystems::Track));
+        app.world_mut().send_event(ResetEvent);
+        app.update();
+        app.world_mut().resource_mut::<TimekeepingTimer>().0 =
+            AttemptPhase::Inspecting(Instant::now());
+
+        let queue_moves = |app: &mut App, algorithm: &str| {
+            let moves = parse_side_moves(algorithm, 3).unwrap();
+            app.world_mut()
+                .resource_mut::<MoveQueue<CubePuzzle>>()
+                .push_moves(moves, MoveSource::Player);
+            app.update();
+        };
+        queue_moves(&mut app, "x y' z2");
+        assert!(matches!(
+            app.world().resource::<TimekeepingTimer>().0,
+            AttemptPhase::Inspecting(_)
+        ));
+        queue_moves(&mut app, "Rw");
+        assert!(matches!(
+            app.world().resource::<TimekeepingTimer>().0,
+            AttemptPhase::Solving { .. }
+        ));
+    }
+
+    #[test]
+    fn only_timekeeping_attempts_are_running() {
+        let mut timer = TimekeepingTimer::default();
+        assert!(!timer.is_running(PlayMode::Timekeeping));
+        for phase in [
+            AttemptPhase::Scrambling,
+            AttemptPhase::Inspecting(Instant::now()),
+            AttemptPhase::Solving {
+                start: Instant::now(),
+                penalty: Penalty::None,
+            },
+        ] {
+            timer.0 = phase;
+            assert!(timer.is_running(PlayMode::Timekeeping));
+            assert!(!timer.is_running(PlayMode::Practice));
+        }
+        timer.0 = AttemptPhase::Finished(Duration::from_secs(10));
+        assert!(!timer.is_running(PlayMode::Timekeeping));
+    }
+}
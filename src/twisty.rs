@@ -0,0 +1,807 @@
+use crate::color_scheme::ColorScheme;
+use crate::cube::*;
+use crate::keyboard::KeyBinding;
+use crate::moving::*;
+use crate::notation::NotationError;
+use crate::puzzle::{ActiveMove, MoveEvent, MoveQueue, MoveSource, Puzzle};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy_egui::EguiContexts;
+use rand::rngs::StdRng;
+use std::f32::consts::TAU;
+use std::fmt;
+use std::sync::Arc;
+
+// 判断点在平面哪一侧时的容差
+const EPSILON: f32 = 1e-4;
+// 贴纸相对所在面的缩放比例，以及高出块表面的距离
+const STICKER_SCALE: f32 = 0.9;
+const STICKER_OFFSET: f32 = 0.01;
+
+/// 转动轴，cuts为各切割面到中心的距离，转动时切割面外侧（法向量一侧）的块一起转动
+#[derive(Debug, Clone)]
+pub struct TwistAxis {
+    pub normal: Vec3,
+    // 转一整圈需要的次数
+    pub order: u32,
+    pub cuts: Vec<f32>,
+}
+
+/// 由外形多面体和切割面定义的扭转类谜题
+#[derive(Debug, Clone)]
+pub struct TwistyDef {
+    // 外形多面体各面的外法向量和到中心的距离，下标即颜色编号
+    pub faces: Vec<(Vec3, f32)>,
+    pub colors: Vec<Color>,
+    pub axes: Vec<TwistAxis>,
+}
+
+/// 一次转动，amount为从轴的外侧看顺时针转动的次数，负数为逆时针
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Twist {
+    pub axis: usize,
+    pub cut: usize,
+    pub amount: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TwistyError {
+    // 切割后没有带贴纸的块
+    NoPieces,
+    // 转动后的贴纸无法与原有位置重合
+    InvalidTwist { axis: usize, cut: usize },
+}
+
+impl fmt::Display for TwistyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TwistyError::NoPieces => write!(f, "the puzzle has no visible pieces"),
+            TwistyError::InvalidTwist { axis, cut } => {
+                write!(
+                    f,
+                    "cut {} of axis {} does not map the puzzle onto itself",
+                    cut, axis
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TwistyError {}
+
+// 凸多面体的一个面，顶点从外侧看为逆时针；face为外形多面体的面，切割产生的内部面为None
+#[derive(Debug, Clone)]
+struct Polygon {
+    vertices: Vec<Vec3>,
+    face: Option<usize>,
+}
+
+impl Polygon {
+    fn centroid(&self) -> Vec3 {
+        self.vertices.iter().sum::<Vec3>() / self.vertices.len() as f32
+    }
+
+    // Newell法求面积向量，方向为外法向量，长度为面积的两倍
+    fn area_vector(&self) -> Vec3 {
+        let n = self.vertices.len();
+        (0..n)
+            .map(|i| self.vertices[i].cross(self.vertices[(i + 1) % n]))
+            .sum()
+    }
+
+    fn normal(&self) -> Vec3 {
+        self.area_vector().normalize()
+    }
+
+    fn area(&self) -> f32 {
+        self.area_vector().length() / 2.0
+    }
+}
+
+// 去掉重合的顶点
+fn dedup_points(points: &mut Vec<Vec3>) {
+    let mut unique: Vec<Vec3> = Vec::with_capacity(points.len());
+    for &p in points.iter() {
+        if unique.iter().all(|q| q.distance(p) > EPSILON) {
+            unique.push(p);
+        }
+    }
+    *points = unique;
+}
+
+// 用平面normal·x = distance切割凸多面体，保留normal·x <= distance的部分，切口的面记为cap；
+// 完全在外侧时返回None
+fn clip(
+    polygons: &[Polygon],
+    normal: Vec3,
+    distance: f32,
+    cap: Option<usize>,
+) -> Option<Vec<Polygon>> {
+    let side = |p: Vec3| distance - normal.dot(p);
+    let mut vertices = polygons.iter().flat_map(|polygon| &polygon.vertices);
+    if vertices.clone().all(|&v| side(v) >= -EPSILON) {
+        return Some(polygons.to_vec());
+    }
+    if vertices.all(|&v| side(v) <= EPSILON) {
+        return None;
+    }
+
+    let mut result = Vec::new();
+    let mut section = Vec::new();
+    for polygon in polygons {
+        let n = polygon.vertices.len();
+        let mut clipped = Vec::with_capacity(n + 1);
+        for i in 0..n {
+            let (a, b) = (polygon.vertices[i], polygon.vertices[(i + 1) % n]);
+            let (sa, sb) = (side(a), side(b));
+            if sa >= -EPSILON {
+                clipped.push(a);
+            }
+            if (sa > EPSILON && sb < -EPSILON) || (sa < -EPSILON && sb > EPSILON) {
+                clipped.push(a + (b - a) * (sa / (sa - sb)));
+            }
+        }
+        dedup_points(&mut clipped);
+        section.extend(
+            clipped
+                .iter()
+                .copied()
+                .filter(|&v| side(v).abs() <= EPSILON),
+        );
+        let clipped = Polygon {
+            vertices: clipped,
+            face: polygon.face,
+        };
+        if clipped.vertices.len() >= 3 && clipped.area() > EPSILON * EPSILON {
+            result.push(clipped);
+        }
+    }
+
+    // 切口上的点按绕法向量逆时针排序
+    dedup_points(&mut section);
+    if section.len() >= 3 {
+        let center = section.iter().sum::<Vec3>() / section.len() as f32;
+        let u = normal.any_orthonormal_vector();
+        let v = normal.cross(u);
+        let angle = |p: &Vec3| (*p - center).dot(v).atan2((*p - center).dot(u));
+        section.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+        result.push(Polygon {
+            vertices: section,
+            face: cap,
+        });
+    }
+    Some(result)
+}
+
+// 包住整个谜题的立方体，边长的一半为size
+fn bounding_box(size: f32) -> Vec<Polygon> {
+    [
+        Vec3::X,
+        Vec3::NEG_X,
+        Vec3::Y,
+        Vec3::NEG_Y,
+        Vec3::Z,
+        Vec3::NEG_Z,
+    ]
+    .into_iter()
+    .map(|axis| {
+        let t1 = axis.any_orthonormal_vector();
+        let t2 = axis.cross(t1);
+        Polygon {
+            vertices: [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .into_iter()
+                .map(|(a, b)| (axis + t1 * a + t2 * b) * size)
+                .collect(),
+            face: None,
+        }
+    })
+    .collect()
+}
+
+// 所有不重复的切割平面，同一平面可能以相反的法向量出现在两个轴上
+fn cut_planes(def: &TwistyDef) -> Vec<(Vec3, f32)> {
+    let mut planes: Vec<(Vec3, f32)> = Vec::new();
+    for axis in &def.axes {
+        for &cut in &axis.cuts {
+            let same = |&(normal, distance): &(Vec3, f32)| {
+                (normal.distance(axis.normal) < EPSILON && (distance - cut).abs() < EPSILON)
+                    || (normal.distance(-axis.normal) < EPSILON && (distance + cut).abs() < EPSILON)
+            };
+            if !planes.iter().any(same) {
+                planes.push((axis.normal, cut));
+            }
+        }
+    }
+    planes
+}
+
+/// 块的几何形状
+#[derive(Debug, Clone)]
+pub struct PieceShape {
+    polygons: Vec<Polygon>,
+    // 还原状态下块的中心
+    pub center: Vec3,
+    // 块上的贴纸编号
+    pub stickers: Vec<usize>,
+}
+
+/// 还原状态下贴纸所在的位置
+#[derive(Debug, Clone, Copy)]
+pub struct StickerSlot {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub face: usize,
+}
+
+/// 由扭转类谜题前端驱动的谜题，目前为金字塔、五魔方、斜转和自定义谜题。
+/// 前端按几何定义生成块，并负责拖动、动画和贴纸状态的转动，`TwistyPuzzle`再把它接入通用的`Puzzle`，
+/// 实现此trait并在`PuzzleKind::twisty`中登记即可加入谜题列表
+pub trait Twisty: Send + Sync + 'static {
+    /// 求解器使用的逻辑状态，没有求解器的谜题为()
+    type State;
+    /// 谜题记号中的一步转动
+    type Move: Clone + fmt::Display;
+
+    /// 外形和切割面，前端由此生成块和贴纸；六面的谜题可使用当前的魔方配色
+    fn definition(&self, colors: &ColorScheme) -> TwistyDef;
+
+    fn to_twist(&self, m: Self::Move) -> Twist;
+
+    fn parse(&self, input: &str) -> Result<Vec<Self::Move>, NotationError>;
+
+    fn format(&self, moves: &[Self::Move]) -> String {
+        moves
+            .iter()
+            .map(|m| m.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn scramble(&self, rng: &mut StdRng) -> Vec<Self::Move>;
+
+    // 打乱公式的显示方式，例如五魔方按行分开
+    fn format_scramble(&self, moves: &[Self::Move]) -> String {
+        self.format(moves)
+    }
+
+    fn key_bindings(&self) -> Vec<KeyBinding> {
+        Vec::new()
+    }
+
+    // 在后台生成求解或打乱需要的表
+    fn warm_up(&self) {}
+
+    /// 由贴纸颜色得到逻辑状态，不支持求解或颜色组合不可能出现时返回None
+    fn read_state(&self, _model: &TwistyModel, _stickers: &[u8]) -> Option<Self::State> {
+        None
+    }
+
+    /// 求解，没有求解器的谜题返回None
+    fn solve(&self, _state: &Self::State) -> Option<Vec<Self::Move>> {
+        None
+    }
+
+    fn can_solve(&self) -> bool {
+        false
+    }
+}
+
+/// 去掉关联类型后的谜题，转动统一为Twist，供前端的系统使用
+pub trait DynTwisty: Send + Sync {
+    fn definition(&self, colors: &ColorScheme) -> TwistyDef;
+
+    fn key_bindings(&self) -> Vec<KeyBinding>;
+
+    fn warm_up(&self);
+
+    fn can_solve(&self) -> bool;
+
+    fn parse_twists(&self, input: &str) -> Result<Vec<Twist>, NotationError>;
+
+    /// 打乱公式及对应的转动
+    fn scramble_twists(&self, rng: &mut StdRng) -> (String, Vec<Twist>);
+
+    /// 解法及对应的转动，状态无效或不支持求解时返回None
+    fn solve_twists(&self, model: &TwistyModel, stickers: &[u8]) -> Option<(String, Vec<Twist>)>;
+}
+
+impl<P: Twisty> DynTwisty for P {
+    fn definition(&self, colors: &ColorScheme) -> TwistyDef {
+        Twisty::definition(self, colors)
+    }
+
+    fn key_bindings(&self) -> Vec<KeyBinding> {
+        Twisty::key_bindings(self)
+    }
+
+    fn warm_up(&self) {
+        Twisty::warm_up(self)
+    }
+
+    fn can_solve(&self) -> bool {
+        Twisty::can_solve(self)
+    }
+
+    fn parse_twists(&self, input: &str) -> Result<Vec<Twist>, NotationError> {
+        Ok(self
+            .parse(input)?
+            .into_iter()
+            .map(|m| self.to_twist(m))
+            .collect())
+    }
+
+    fn scramble_twists(&self, rng: &mut StdRng) -> (String, Vec<Twist>) {
+        let moves = self.scramble(rng);
+        (
+            self.format_scramble(&moves),
+            moves.into_iter().map(|m| self.to_twist(m)).collect(),
+        )
+    }
+
+    fn solve_twists(&self, model: &TwistyModel, stickers: &[u8]) -> Option<(String, Vec<Twist>)> {
+        let state = self.read_state(model, stickers)?;
+        let moves = self.solve(&state)?;
+        Some((
+            self.format(&moves),
+            moves.into_iter().map(|m| self.to_twist(m)).collect(),
+        ))
+    }
+}
+
+/// 切割得到的块、贴纸，以及每种转动对贴纸位置的置换
+#[derive(Debug, Clone)]
+pub struct TwistyModel {
+    pub def: TwistyDef,
+    pub pieces: Vec<PieceShape>,
+    pub stickers: Vec<StickerSlot>,
+    // [轴][切割面]顺时针转动一次后每个贴纸的新位置
+    permutations: Vec<Vec<Vec<usize>>>,
+}
+
+impl TwistyModel {
+    pub fn new(def: &TwistyDef) -> Result<Self, TwistyError> {
+        let mut def = def.clone();
+        for face in &mut def.faces {
+            face.0 = face.0.normalize();
+        }
+        for axis in &mut def.axes {
+            axis.normal = axis.normal.normalize();
+        }
+
+        // 先切出外形，再用所有切割平面把外形分成块
+        let size = def.faces.iter().map(|face| face.1).fold(1.0, f32::max) * 4.0;
+        let mut shape = bounding_box(size);
+        for (i, &(normal, distance)) in def.faces.iter().enumerate() {
+            shape = clip(&shape, normal, distance, Some(i)).ok_or(TwistyError::NoPieces)?;
+        }
+        let mut parts = vec![shape];
+        for (normal, distance) in cut_planes(&def) {
+            parts = parts
+                .into_iter()
+                .flat_map(|part| {
+                    [
+                        clip(&part, normal, distance, None),
+                        clip(&part, -normal, -distance, None),
+                    ]
+                })
+                .flatten()
+                .collect();
+        }
+
+        let mut pieces = Vec::new();
+        let mut stickers = Vec::new();
+        for polygons in parts {
+            // 内部看不到的块无需生成
+            if polygons.iter().all(|polygon| polygon.face.is_none()) {
+                continue;
+            }
+            let vertices = polygons.iter().flat_map(|polygon| &polygon.vertices);
+            let center = vertices.clone().sum::<Vec3>() / vertices.count() as f32;
+            let mut piece_stickers = Vec::new();
+            for polygon in &polygons {
+                if let Some(face) = polygon.face {
+                    piece_stickers.push(stickers.len());
+                    stickers.push(StickerSlot {
+                        center: polygon.centroid(),
+                        normal: polygon.normal(),
+                        face,
+                    });
+                }
+            }
+            pieces.push(PieceShape {
+                polygons,
+                center,
+                stickers: piece_stickers,
+            });
+        }
+        if pieces.is_empty() {
+            return Err(TwistyError::NoPieces);
+        }
+
+        let mut model = Self {
+            def,
+            pieces,
+            stickers,
+            permutations: Vec::new(),
+        };
+        model.permutations = (0..model.def.axes.len())
+            .map(|axis| {
+                (0..model.def.axes[axis].cuts.len())
+                    .map(|cut| model.permutation(axis, cut))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(model)
+    }
+
+    fn permutation(&self, axis: usize, cut: usize) -> Result<Vec<usize>, TwistyError> {
+        let twist = Twist {
+            axis,
+            cut,
+            amount: 1,
+        };
+        let rotation = Quat::from_axis_angle(self.def.axes[axis].normal, self.twist_angle(twist));
+        let mut targets: Vec<usize> = (0..self.stickers.len()).collect();
+        for piece in &self.pieces {
+            if !self.twist_contains(twist, piece.center) {
+                continue;
+            }
+            for &sticker in &piece.stickers {
+                let moved = rotation * self.stickers[sticker].center;
+                targets[sticker] = self
+                    .stickers
+                    .iter()
+                    .position(|slot| slot.center.distance(moved) < 1e-3)
+                    .ok_or(TwistyError::InvalidTwist { axis, cut })?;
+            }
+        }
+        Ok(targets)
+    }
+
+    /// 转动的角度，按右手定则为正
+    pub fn twist_angle(&self, twist: Twist) -> f32 {
+        -(twist.amount as f32) * TAU / self.def.axes[twist.axis].order as f32
+    }
+
+    /// 中心在point的块是否随此转动
+    pub fn twist_contains(&self, twist: Twist, point: Vec3) -> bool {
+        let axis = &self.def.axes[twist.axis];
+        axis.normal.dot(point) > axis.cuts[twist.cut]
+    }
+
+    /// 还原状态，每个贴纸位置上的颜色
+    pub fn solved_state(&self) -> Vec<u8> {
+        self.stickers.iter().map(|slot| slot.face as u8).collect()
+    }
+
+    pub fn apply(&self, state: &mut [u8], twist: Twist) {
+        let order = self.def.axes[twist.axis].order as i32;
+        let targets = &self.permutations[twist.axis][twist.cut];
+        for _ in 0..twist.amount.rem_euclid(order) {
+            let old = state.to_vec();
+            for (sticker, &target) in targets.iter().enumerate() {
+                state[target] = old[sticker];
+            }
+        }
+    }
+
+    /// 同一颜色的贴纸都朝向同一个方向即为还原，不要求整体朝向
+    pub fn is_solved(&self, state: &[u8]) -> bool {
+        let mut normals: Vec<Option<Vec3>> = vec![None; self.def.faces.len()];
+        for (slot, &color) in self.stickers.iter().zip(state) {
+            match normals[color as usize] {
+                None => normals[color as usize] = Some(slot.normal),
+                Some(normal) if normal.dot(slot.normal) < 1.0 - EPSILON => return false,
+                Some(_) => {}
+            }
+        }
+        true
+    }
+}
+
+/// 多边形三角化为扇形，每个面使用自己的法向量
+pub fn polygon_mesh(polygons: impl Iterator<Item = (Vec<Vec3>, Vec3)>) -> Mesh {
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for (vertices, normal) in polygons {
+        let start = positions.len() as u32;
+        let n = vertices.len() as u32;
+        for i in 1..n - 1 {
+            indices.extend([start, start + i, start + i + 1]);
+        }
+        normals.extend(std::iter::repeat_n(normal, vertices.len()));
+        positions.extend(vertices);
+    }
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// 扭转类谜题的块，几何体以谜题中心为原点生成，转动时只改变Transform的旋转
+#[derive(Debug, Component, Clone, Copy)]
+pub struct TwistyPiece {
+    // 还原状态下块的中心
+    pub home: Vec3,
+}
+
+/// 扭转类谜题前端当前的谜题：谜题定义及按定义切割得到的模型，逻辑状态为每个贴纸位置上的颜色
+#[derive(Clone, Resource)]
+pub struct TwistyPuzzle {
+    pub kind: PuzzleKind,
+    pub twisty: Arc<dyn DynTwisty>,
+    pub model: TwistyModel,
+}
+
+// 内置谜题每次重新创建，按种类比较；自定义谜题重新读取后是另一个谜题
+impl PartialEq for TwistyPuzzle {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && (self.kind != PuzzleKind::Custom || Arc::ptr_eq(&self.twisty, &other.twisty))
+    }
+}
+
+impl Puzzle for TwistyPuzzle {
+    type State = Vec<u8>;
+    type Move = Twist;
+
+    fn solved_state(&self) -> Vec<u8> {
+        self.model.solved_state()
+    }
+
+    fn apply(&self, state: &Vec<u8>, twist: &Twist) -> Option<Vec<u8>> {
+        let mut state = state.clone();
+        self.model.apply(&mut state, *twist);
+        Some(state)
+    }
+
+    fn invert(&self, _state: &Vec<u8>, twist: &Twist) -> Vec<Twist> {
+        vec![Twist {
+            amount: -twist.amount,
+            ..*twist
+        }]
+    }
+
+    fn is_solved(&self, state: &Vec<u8>) -> bool {
+        self.model.is_solved(state)
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Twist>, NotationError> {
+        self.twisty.parse_twists(input)
+    }
+
+    fn scramble(&self, rng: &mut StdRng) -> (String, Vec<Twist>) {
+        self.twisty.scramble_twists(rng)
+    }
+
+    fn can_solve(&self) -> bool {
+        self.twisty.can_solve()
+    }
+
+    // 由谜题的求解器根据当前贴纸状态求解
+    fn solve(&self, state: &Vec<u8>) -> Result<(String, Vec<Twist>), String> {
+        self.twisty
+            .solve_twists(&self.model, state)
+            .ok_or_else(|| format!("invalid {} state", self.kind.name().to_lowercase()))
+    }
+}
+
+/// 贴纸向中心缩小，并略高于块的表面
+pub fn sticker_polygon(vertices: &[Vec3], normal: Vec3) -> Vec<Vec3> {
+    let center = vertices.iter().sum::<Vec3>() / vertices.len() as f32;
+    vertices
+        .iter()
+        .map(|&v| center + (v - center) * STICKER_SCALE + normal * STICKER_OFFSET)
+        .collect()
+}
+
+fn spawn_twisty(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    model: &TwistyModel,
+) {
+    for piece in &model.pieces {
+        let body = polygon_mesh(
+            piece
+                .polygons
+                .iter()
+                .map(|polygon| (polygon.vertices.clone(), polygon.normal())),
+        );
+        commands
+            .spawn((
+                Mesh3d(meshes.add(body)),
+                MeshMaterial3d(materials.add(Color::BLACK)),
+                Transform::default(),
+                TwistyPiece { home: piece.center },
+                RayCastPickable,
+            ))
+            .observe(handle_drag_start)
+            .observe(handle_twist_drag)
+            .observe(handle_drag_end)
+            .with_children(|parent| {
+                for polygon in &piece.polygons {
+                    let Some(face) = polygon.face else {
+                        continue;
+                    };
+                    let normal = polygon.normal();
+                    let vertices = sticker_polygon(&polygon.vertices, normal);
+                    parent.spawn((
+                        Mesh3d(meshes.add(polygon_mesh(std::iter::once((vertices, normal))))),
+                        MeshMaterial3d(materials.add(StandardMaterial {
+                            base_color: model.def.colors[face],
+                            unlit: true,
+                            ..default()
+                        })),
+                    ));
+                }
+            });
+    }
+}
+
+// 重置时按当前谜题重建并更换谜题资源，切换到其他谜题时只移除；换了谜题时丢弃尚未执行的转动
+#[allow(clippy::too_many_arguments)]
+pub fn reset_twisty(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    cube_settings: Res<CubeSettings>,
+    mut events: EventReader<ResetEvent>,
+    q_pieces: Query<Entity, With<TwistyPiece>>,
+    puzzle: Option<Res<TwistyPuzzle>>,
+    mut twist_queue: ResMut<MoveQueue<TwistyPuzzle>>,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+    for piece in &q_pieces {
+        commands.entity(piece).despawn_recursive();
+    }
+    let next = cube_settings.puzzle.twisty().map(|twisty| TwistyPuzzle {
+        kind: cube_settings.puzzle,
+        model: TwistyModel::new(&twisty.definition(&cube_settings.color_scheme()))
+            .expect("built-in and loaded puzzle definitions are valid"),
+        twisty,
+    });
+    if puzzle.as_deref() != next.as_ref() {
+        twist_queue.0.clear();
+    }
+    match next {
+        Some(next) => {
+            spawn_twisty(&mut commands, &mut meshes, &mut materials, &next.model);
+            commands.insert_resource(next);
+        }
+        None => commands.remove_resource::<TwistyPuzzle>(),
+    }
+}
+
+// 上一次转动的动画结束后，取出下一次转动并标记要转动的块
+pub fn choose_twisting_pieces(
+    mut commands: Commands,
+    q_pieces: Query<(Entity, &TwistyPiece, &Transform)>,
+    q_movable_pieces: Query<Entity, With<MovablePiece>>,
+    mut twist_queue: ResMut<MoveQueue<TwistyPuzzle>>,
+    mut active_twist: ResMut<ActiveMove<TwistyPuzzle>>,
+    mut twist_events: EventWriter<MoveEvent<TwistyPuzzle>>,
+    puzzle: Option<Res<TwistyPuzzle>>,
+) {
+    if !q_movable_pieces.is_empty() {
+        return;
+    }
+    if let Some((twist, source)) = active_twist.0.take() {
+        twist_events.send(MoveEvent(twist, source));
+    }
+    let Some(model) = puzzle.as_ref().map(|puzzle| &puzzle.model) else {
+        return;
+    };
+    let Some((twist, source)) = twist_queue.0.pop_front() else {
+        return;
+    };
+    let axis = model.def.axes[twist.axis].normal;
+    let left_angle = model.twist_angle(twist);
+    for (entity, piece, transform) in &q_pieces {
+        if model.twist_contains(twist, transform.rotation * piece.home) {
+            commands
+                .entity(entity)
+                .insert(MovablePiece { axis, left_angle });
+        }
+    }
+    active_twist.0 = Some((twist, source));
+}
+
+// 按键转动，键位由各谜题定义
+pub fn twisty_keyboard_moves(
+    mut egui_context: EguiContexts,
+    keys: Res<ButtonInput<KeyCode>>,
+    puzzle: Option<Res<TwistyPuzzle>>,
+    mut twist_queue: ResMut<MoveQueue<TwistyPuzzle>>,
+) {
+    let Some(puzzle) = puzzle else {
+        return;
+    };
+    if egui_context.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    if keys.any_pressed([
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::AltLeft,
+        KeyCode::AltRight,
+    ]) {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let bindings = puzzle.twisty.key_bindings();
+    for key in keys.get_just_pressed() {
+        let Some(binding) = bindings
+            .iter()
+            .find(|binding| binding.key == *key && binding.shift == shift)
+        else {
+            continue;
+        };
+        match puzzle.parse(&binding.algorithm) {
+            Ok(twists) => twist_queue.push_moves(twists, MoveSource::Player),
+            Err(err) => info!("ignore key binding {}: {}", binding.algorithm, err),
+        }
+    }
+}
+
+// 拖动距离超过临界值时，在包含该块的转动中选择转动方向与拖动方向最接近的一个
+pub fn handle_twist_drag(
+    pointer_move: Trigger<Pointer<Move>>,
+    mut recorder: ResMut<MouseDraggingRecorder>,
+    mut twist_queue: ResMut<MoveQueue<TwistyPuzzle>>,
+    q_pieces: Query<(&TwistyPiece, &Transform)>,
+    puzzle: Option<Res<TwistyPuzzle>>,
+) {
+    let (Some(current_pos), Some(start_pos)) =
+        (pointer_move.event.hit.position, recorder.start_pos)
+    else {
+        return;
+    };
+    if start_pos.distance(current_pos) <= 0.5 || recorder.triggered {
+        return;
+    }
+    recorder.triggered = true;
+    let (Some(model), Some(Ok((piece, transform)))) = (
+        puzzle.as_ref().map(|puzzle| &puzzle.model),
+        recorder.piece.map(|entity| q_pieces.get(entity)),
+    ) else {
+        return;
+    };
+    let center = transform.rotation * piece.home;
+    let direction = (current_pos - start_pos).normalize();
+    let best = model
+        .def
+        .axes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, axis)| {
+            // 每个轴只考虑包含该块的最内层切割面，负的切割面（如宽层转动）不能拖动
+            let (cut, _) = axis
+                .cuts
+                .iter()
+                .enumerate()
+                .filter(|(_, &cut)| cut >= 0.0 && axis.normal.dot(center) > cut)
+                .max_by(|a, b| a.1.total_cmp(b.1))?;
+            // 绕轴正向转动时起点的运动方向
+            let tangent = axis.normal.cross(start_pos).normalize_or_zero();
+            Some((index, cut, tangent.dot(direction)))
+        })
+        .max_by(|a, b| a.2.abs().total_cmp(&b.2.abs()));
+    if let Some((axis, cut, score)) = best {
+        let twist = Twist {
+            axis,
+            cut,
+            amount: if score > 0.0 { -1 } else { 1 },
+        };
+        info!("gen twist: {:?}", twist);
+        twist_queue.push_moves([twist], MoveSource::Player);
+    }
+}
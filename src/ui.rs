@@ -1,17 +1,73 @@
+use crate::color_scheme::*;
 use crate::cube::*;
+use crate::custom::{self, CustomPuzzleInput};
+use crate::facelet::*;
+use crate::history::*;
+use crate::keyboard::KeyScheme;
+use crate::move_history::MoveHistory;
+use crate::net::NetView;
+use crate::puzzle::{ApplyAlgorithmEvent, PuzzleState, PuzzleStatus};
+use crate::save::*;
+use crate::save_data::*;
+use crate::session::*;
+use crate::state::Face;
+use crate::stats::*;
+use crate::timekeeping::*;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use bevy::utils::Instant;
 use bevy_egui::{egui, EguiContexts};
 
-#[derive(Debug, Resource)]
-pub struct TimekeepingTimer(pub Instant);
+// 打乱种子，启用时打乱结果可复现
+#[derive(Debug, Default, Resource)]
+pub struct ScrambleSeedInput {
+    pub enabled: bool,
+    pub seed: u64,
+}
+
+// facelet字符串输入框
+#[derive(Debug, Default, Resource)]
+pub struct FaceletInput {
+    pub text: String,
+    pub error: Option<String>,
+}
+
+// 公式输入框
+#[derive(Debug, Default, Resource)]
+pub struct AlgorithmInput {
+    pub text: String,
+    pub error: Option<String>,
+}
+
+// 游戏UI发出的事件
+#[derive(SystemParam)]
+pub struct UiEvents<'w> {
+    scramble: EventWriter<'w, ScrambleEvent>,
+    reset: EventWriter<'w, ResetEvent>,
+    solve: EventWriter<'w, SolveEvent>,
+    undo: EventWriter<'w, UndoEvent>,
+    redo: EventWriter<'w, RedoEvent>,
+    save: EventWriter<'w, SaveEvent>,
+    load: EventWriter<'w, LoadEvent>,
+    apply: EventWriter<'w, ApplyAlgorithmEvent>,
+}
 
+#[allow(clippy::too_many_arguments)]
 pub fn game_ui(
     mut egui_context: EguiContexts,
     mut cube_settings: ResMut<CubeSettings>,
     mut timekeeping_timer: ResMut<TimekeepingTimer>,
-    mut scramble_event: EventWriter<ScrambleEvent>,
-    mut reset_event: EventWriter<ResetEvent>,
+    mut events: UiEvents,
+    mut latest_solution: ResMut<LatestSolution>,
+    current_scramble: Res<CurrentScramble>,
+    mut scramble_seed: ResMut<ScrambleSeedInput>,
+    mut algorithm_input: ResMut<AlgorithmInput>,
+    status: Res<PuzzleStatus>,
+    mut completed_solve: ResMut<CompletedSolve>,
+    mut save_status: ResMut<SaveStatus>,
+    mut facelet_input: ResMut<FaceletInput>,
+    cube_state: Res<PuzzleState<CubePuzzle>>,
+    mut net_view: ResMut<NetView>,
+    mut custom_input: ResMut<CustomPuzzleInput>,
 ) {
     egui::Window::new("Game UI").show(egui_context.ctx_mut(), |ui| {
         egui::Grid::new("ui_grid")
@@ -19,6 +75,82 @@ pub fn game_ui(
             .spacing([10.0, 20.0])
             .striped(true)
             .show(ui, |ui| {
+                ui.add(egui::Label::new("Puzzle"));
+                ui.horizontal(|ui| {
+                    for kind in PuzzleKind::ALL {
+                        if ui
+                            .selectable_value(&mut cube_settings.puzzle, kind, kind.name())
+                            .changed()
+                        {
+                            // 切换谜题，各前端重置时丢弃旧谜题尚未执行的转动
+                            latest_solution.0 = None;
+                            events.reset.send_default();
+                            completed_solve.0 = None;
+                        }
+                    }
+                });
+                ui.end_row();
+
+                // 阶数、配色、存档等只对正阶魔方有效
+                let is_cube = cube_settings.puzzle == PuzzleKind::Cube;
+
+                if is_cube {
+                    ui.add(egui::Label::new("Cube Order"));
+                    if ui
+                        .add(egui::Slider::new(&mut cube_settings.cube_order, 2..=12))
+                        .changed()
+                    {
+                        // 按新的阶数重建魔方
+                        events.reset.send_default();
+                    }
+                    ui.end_row();
+                }
+
+                if cube_settings.puzzle == PuzzleKind::Cuboid {
+                    ui.add(egui::Label::new("Shape"));
+                    ui.horizontal(|ui| {
+                        for (name, dims) in CUBOID_PRESETS {
+                            if ui
+                                .selectable_value(&mut cube_settings.cuboid, dims, name)
+                                .changed()
+                            {
+                                events.reset.send_default();
+                                completed_solve.0 = None;
+                            }
+                        }
+                    });
+                    ui.end_row();
+                }
+
+                if cube_settings.puzzle == PuzzleKind::Custom {
+                    ui.add(egui::Label::new("Definition"));
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut custom_input.path);
+                        if ui.button("Load").clicked() {
+                            // 重新读取文件，修改定义后不需要重新编译
+                            match custom::load_custom_puzzle(&custom_input.path) {
+                                Ok(puzzle) => {
+                                    info!("loaded custom puzzle {}", puzzle.name);
+                                    custom_input.error = None;
+                                    latest_solution.0 = None;
+                                    events.reset.send_default();
+                                    completed_solve.0 = None;
+                                }
+                                Err(err) => custom_input.error = Some(err.to_string()),
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    if let Some(error) = &custom_input.error {
+                        ui.colored_label(egui::Color32::RED, error);
+                        ui.end_row();
+                    } else if let Some(puzzle) = custom::loaded() {
+                        ui.add(egui::Label::new(&puzzle.name));
+                        ui.end_row();
+                    }
+                }
+
                 ui.add(egui::Label::new("Rotate Speed"));
                 ui.add(egui::Slider::new(
                     &mut cube_settings.rotate_speed,
@@ -26,6 +158,68 @@ pub fn game_ui(
                 ));
                 ui.end_row();
 
+                if is_cube {
+                    ui.add(egui::Label::new("Net View"));
+                    ui.checkbox(&mut net_view.open, "Show");
+                    ui.end_row();
+
+                    ui.add(egui::Label::new("Colors"));
+                    ui.horizontal(|ui| {
+                        let current = cube_settings.color_scheme();
+                        for (name, scheme) in ColorScheme::presets() {
+                            if ui.selectable_label(current == scheme, name).clicked() {
+                                cube_settings.set_color_scheme(scheme);
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    if ui
+                        .add_sized([100.0, 30.0], egui::Button::new("Save Colors"))
+                        .clicked()
+                    {
+                        save_status.0 = Some(
+                            save_color_scheme(COLOR_SCHEME_FILE, &cube_settings.color_scheme())
+                                .map(|()| format!("saved colors to {}", COLOR_SCHEME_FILE))
+                                .map_err(|err| err.to_string()),
+                        );
+                    }
+                    ui.horizontal(|ui| {
+                        let mut scheme = cube_settings.color_scheme();
+                        let mut changed = false;
+                        for face in Face::ALL {
+                            let color = &mut scheme.0[face as usize];
+                            let [r, g, b, _] = color.to_srgba().to_u8_array();
+                            let mut rgb = [r, g, b];
+                            ui.label(face.to_char().to_string());
+                            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                                *color = Color::srgb_u8(rgb[0], rgb[1], rgb[2]);
+                                changed = true;
+                            }
+                        }
+                        // 只在修改时写入，避免每帧触发配色变化
+                        if changed {
+                            cube_settings.set_color_scheme(scheme);
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.add(egui::Label::new("Keyboard"));
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(
+                            &mut cube_settings.key_scheme,
+                            KeyScheme::Plain,
+                            "Plain",
+                        );
+                        ui.selectable_value(
+                            &mut cube_settings.key_scheme,
+                            KeyScheme::CsTimer,
+                            "csTimer",
+                        );
+                    });
+                    ui.end_row();
+                }
+
                 ui.add(egui::Label::new("Play Mode"));
                 ui.horizontal(|ui| {
                     ui.selectable_value(
@@ -41,15 +235,12 @@ pub fn game_ui(
                         )
                         .clicked()
                     {
-                        // 重置计时器
-                        timekeeping_timer.0 = Instant::now();
+                        // 重新开始，等待打乱
+                        timekeeping_timer.0 = AttemptPhase::Idle;
                     }
                 });
                 if cube_settings.play_mode == PlayMode::Timekeeping {
-                    ui.add(egui::Label::new(format!(
-                        "{}s",
-                        timekeeping_timer.0.elapsed().as_secs()
-                    )));
+                    timekeeping_label(ui, &timekeeping_timer);
                 }
                 ui.end_row();
 
@@ -57,17 +248,319 @@ pub fn game_ui(
                     .add_sized([100.0, 30.0], egui::Button::new("Scramble"))
                     .clicked()
                 {
-                    scramble_event.send_default();
+                    events.scramble.send(ScrambleEvent {
+                        seed: scramble_seed.enabled.then_some(scramble_seed.seed),
+                    });
+                    completed_solve.0 = None;
                 }
 
                 if ui
                     .add_sized([100.0, 30.0], egui::Button::new("Reset"))
                     .clicked()
                 {
-                    reset_event.send_default();
+                    events.reset.send_default();
+                    completed_solve.0 = None;
+                }
+
+                ui.end_row();
+
+                // 计时中只能自己转动，求解、公式和撤销得到的还原不能计入成绩
+                let running = timekeeping_timer.is_running(cube_settings.play_mode);
+
+                if ui
+                    .add_enabled(
+                        status.can_undo && !running,
+                        egui::Button::new("Undo").min_size([100.0, 30.0].into()),
+                    )
+                    .clicked()
+                {
+                    events.undo.send_default();
+                }
+                if ui
+                    .add_enabled(
+                        status.can_redo && !running,
+                        egui::Button::new("Redo").min_size([100.0, 30.0].into()),
+                    )
+                    .clicked()
+                {
+                    events.redo.send_default();
+                }
+                ui.end_row();
+
+                ui.add(egui::Label::new("Scramble Seed"));
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut scramble_seed.enabled, "Fixed");
+                    ui.add_enabled(
+                        scramble_seed.enabled,
+                        egui::DragValue::new(&mut scramble_seed.seed),
+                    );
+                });
+                ui.end_row();
+
+                if let Some(scramble) = &current_scramble.0 {
+                    ui.add(egui::Label::new("Last Scramble"));
+                    ui.add(egui::Label::new(scramble).wrap());
+                    ui.end_row();
                 }
 
+                if is_cube || cube_settings.puzzle == PuzzleKind::Cuboid {
+                    if ui
+                        .add_sized([100.0, 30.0], egui::Button::new("Save"))
+                        .clicked()
+                    {
+                        events.save.send_default();
+                    }
+                    if ui
+                        .add_sized([100.0, 30.0], egui::Button::new("Load"))
+                        .clicked()
+                    {
+                        events.load.send_default();
+                    }
+                    ui.end_row();
+
+                    match &save_status.0 {
+                        Some(Ok(message)) => {
+                            ui.add(egui::Label::new(message));
+                            ui.end_row();
+                        }
+                        Some(Err(err)) => {
+                            ui.colored_label(egui::Color32::RED, err);
+                            ui.end_row();
+                        }
+                        None => {}
+                    }
+                }
+
+                // 三阶魔方和带求解器的扭转类谜题可以求解
+                if status.can_solve {
+                    if ui
+                        .add_enabled(
+                            !running,
+                            egui::Button::new("Solve").min_size([100.0, 30.0].into()),
+                        )
+                        .clicked()
+                    {
+                        events.solve.send_default();
+                    }
+                    match &latest_solution.0 {
+                        Some(Ok(solution)) => {
+                            ui.add(egui::Label::new(solution).wrap());
+                        }
+                        Some(Err(err)) => {
+                            ui.colored_label(egui::Color32::RED, err);
+                        }
+                        None => {}
+                    }
+                    ui.end_row();
+                }
+
+                ui.add(egui::Label::new("Algorithm"));
+                ui.text_edit_singleline(&mut algorithm_input.text);
+                ui.end_row();
+
+                if ui
+                    .add_enabled(
+                        !running,
+                        egui::Button::new("Apply").min_size([100.0, 30.0].into()),
+                    )
+                    .clicked()
+                {
+                    // 解析和检查由当前谜题完成，错误写回algorithm_input
+                    events
+                        .apply
+                        .send(ApplyAlgorithmEvent(algorithm_input.text.clone()));
+                }
+                if let Some(error) = &algorithm_input.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
                 ui.end_row();
+
+                if is_cube {
+                    ui.add(egui::Label::new("Facelets"));
+                    ui.text_edit_singleline(&mut facelet_input.text);
+                    ui.end_row();
+
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_sized([100.0, 30.0], egui::Button::new("Import"))
+                            .clicked()
+                        {
+                            match cube_state_from_facelets(&facelet_input.text) {
+                                Ok(cube) => {
+                                    events.load.send(LoadEvent(Some(SaveData {
+                                        version: SAVE_VERSION,
+                                        cube,
+                                        history: MoveHistory::default(),
+                                        scramble: None,
+                                    })));
+                                    facelet_input.error = None;
+                                }
+                                Err(err) => facelet_input.error = Some(err.to_string()),
+                            }
+                        }
+                        // 导出当前状态并复制到剪贴板
+                        if let Some(facelets) = cube_state.0.as_ref().and_then(facelet_string) {
+                            if ui
+                                .add_sized([100.0, 30.0], egui::Button::new("Copy"))
+                                .clicked()
+                            {
+                                ui.ctx().copy_text(facelets.clone());
+                                facelet_input.text = facelets;
+                                facelet_input.error = None;
+                            }
+                        }
+                    });
+                    if let Some(error) = &facelet_input.error {
+                        ui.colored_label(egui::Color32::RED, error);
+                    }
+                    ui.end_row();
+                }
             });
     });
 }
+
+// 计时模式下当前阶段的提示，观察到8秒、12秒时给出警告
+fn timekeeping_label(ui: &mut egui::Ui, timekeeping_timer: &TimekeepingTimer) {
+    match timekeeping_timer.0 {
+        AttemptPhase::Idle => {
+            ui.label("Scramble to start");
+        }
+        AttemptPhase::Scrambling => {
+            ui.label("Scrambling...");
+        }
+        AttemptPhase::Inspecting(start) => {
+            let elapsed = start.elapsed();
+            match inspection_penalty(elapsed) {
+                Penalty::Dnf => ui.colored_label(egui::Color32::RED, "Inspection: DNF"),
+                Penalty::PlusTwo => ui.colored_label(egui::Color32::RED, "Inspection: +2"),
+                Penalty::None => {
+                    let left = (INSPECTION_TIME - elapsed).as_secs_f32().ceil();
+                    let text = format!("Inspection: {}", left);
+                    if elapsed.as_secs() >= 12 {
+                        ui.colored_label(egui::Color32::RED, format!("{} (12s!)", text))
+                    } else if elapsed.as_secs() >= 8 {
+                        ui.colored_label(egui::Color32::YELLOW, format!("{} (8s!)", text))
+                    } else {
+                        ui.label(text)
+                    }
+                }
+            };
+        }
+        AttemptPhase::Solving { penalty, .. } => {
+            let mut text = format_duration(timekeeping_timer.elapsed());
+            match penalty {
+                Penalty::PlusTwo => text.push_str(" (+2)"),
+                Penalty::Dnf => text.push_str(" (DNF)"),
+                Penalty::None => {}
+            }
+            ui.label(text);
+        }
+        AttemptPhase::Finished(time) => {
+            ui.label(format_duration(time));
+        }
+    }
+}
+
+pub fn completion_panel(
+    mut egui_context: EguiContexts,
+    mut completed_solve: ResMut<CompletedSolve>,
+    mut session: ResMut<Session>,
+) {
+    let Some(summary) = completed_solve.0 else {
+        return;
+    };
+    let mut open = true;
+    egui::Window::new("Solved!")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 20.0])
+        .show(egui_context.ctx_mut(), |ui| {
+            // 计时成绩可以手动修改罚时
+            if let (Some(_), Some(solve)) = (summary.time, session.solves.last_mut()) {
+                ui.label(format!("Time: {}", solve.result()));
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut solve.penalty, Penalty::None, "OK");
+                    ui.selectable_value(&mut solve.penalty, Penalty::PlusTwo, "+2");
+                    ui.selectable_value(&mut solve.penalty, Penalty::Dnf, "DNF");
+                });
+            }
+            ui.label(format!("Moves: {}", summary.move_count));
+        });
+    if !open {
+        completed_solve.0 = None;
+    }
+}
+
+// 计时模式下展示本次会话的成绩统计
+pub fn session_ui(
+    mut egui_context: EguiContexts,
+    cube_settings: Res<CubeSettings>,
+    mut session: ResMut<Session>,
+) {
+    if cube_settings.play_mode != PlayMode::Timekeeping {
+        return;
+    }
+    egui::Window::new("Session")
+        .default_pos([10.0, 500.0])
+        .show(egui_context.ctx_mut(), |ui| {
+            let format_result = |result: Option<SolveResult>| {
+                result.map_or("-".to_string(), |result| result.to_string())
+            };
+            egui::Grid::new("session_stats")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("");
+                    ui.label("Current");
+                    ui.label("Best");
+                    ui.end_row();
+
+                    ui.label("Single");
+                    ui.label(format_result(
+                        session.solves.last().map(SolveRecord::result),
+                    ));
+                    ui.label(format_result(session.best()));
+                    ui.end_row();
+
+                    for n in [5, 12, 50, 100] {
+                        ui.label(format!("ao{}", n));
+                        ui.label(format_result(session.current_average(n)));
+                        ui.label(format_result(session.best_average(n)));
+                        ui.end_row();
+                    }
+
+                    ui.label("Mean");
+                    ui.label(session.mean().map_or("-".to_string(), format_duration));
+                    ui.end_row();
+                });
+
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("session_solves")
+                        .num_columns(4)
+                        .show(ui, |ui| {
+                            for (i, solve) in session.solves.iter_mut().enumerate().rev() {
+                                ui.label(format!("{}.", i + 1));
+                                let label = ui.label(solve.result().to_string());
+                                if let Some(scramble) = &solve.scramble {
+                                    label.on_hover_text(scramble);
+                                }
+                                ui.label(format!("{} moves", solve.move_count));
+                                ui.horizontal(|ui| {
+                                    ui.selectable_value(&mut solve.penalty, Penalty::None, "OK");
+                                    ui.selectable_value(&mut solve.penalty, Penalty::PlusTwo, "+2");
+                                    ui.selectable_value(&mut solve.penalty, Penalty::Dnf, "DNF");
+                                });
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            if ui.button("Clear Session").clicked() {
+                session.solves.clear();
+            }
+        });
+}